@@ -0,0 +1,117 @@
+//! How expansion and `BurnNetwork` construction scale with the number of terms.
+//!
+//! `construction` expands `(x0 + ... + x5)^k` behind a hidden neuron, which has
+//! `C(k + 5, 5)` terms. `shared` expands ladders where every hidden neuron feeds both
+//! neurons of the next layer, so the number of paths doubles with each layer while the
+//! expansion stays small. Run with `cargo bench --bench expansion`.
+
+use std::sync::{Arc, RwLock};
+
+use burn::backend::NdArray;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use polynomial_neat::burn_net::network::BurnNetwork;
+use polynomial_neat::prelude::*;
+use uuid::Uuid;
+
+const INPUTS: usize = 6;
+
+fn arc<T>(value: T) -> Arc<RwLock<T>> {
+    Arc::new(RwLock::new(value))
+}
+
+/// `(x0 + ... + x5)^exponent`.
+fn power_of_sum(exponent: i32) -> PolyNetworkTopology {
+    let inputs: Vec<_> = (0..INPUTS)
+        .map(|_| arc(PolyNeuronTopology::input(Uuid::new_v4())))
+        .collect();
+    let hidden = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        inputs
+            .iter()
+            .map(|input| PolyInputTopology::downgrade(input, 1., 1))
+            .collect(),
+    ));
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&hidden, 1., exponent)],
+    ));
+
+    let mut neurons = inputs;
+    neurons.extend([hidden, output]);
+    PolyNetworkTopology::from_raw_parts(neurons, MutationChances::none())
+}
+
+/// `depth` layers of two neurons, each summing both neurons of the layer before.
+fn ladder(depth: usize) -> PolyNetworkTopology {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let mut neurons = vec![x.clone(), y.clone()];
+
+    let mut layer = [x, y];
+    for _ in 0..depth {
+        let next = [(); 2].map(|_| {
+            arc(PolyNeuronTopology::hidden(
+                Uuid::new_v4(),
+                vec![
+                    PolyInputTopology::downgrade(&layer[0], 0.5, 1),
+                    PolyInputTopology::downgrade(&layer[1], 0.5, 2),
+                ],
+            ))
+        });
+        neurons.extend(next.iter().cloned());
+        layer = next;
+    }
+    neurons.push(arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&layer[0], 1., 1)],
+    )));
+
+    PolyNetworkTopology::from_raw_parts(neurons, MutationChances::none())
+}
+
+fn shared(c: &mut Criterion) {
+    let mut group = c.benchmark_group("shared");
+    group.sample_size(10);
+
+    for depth in [2, 4, 6] {
+        let topology = ladder(depth);
+        group.bench_with_input(
+            BenchmarkId::new("expand", depth),
+            &topology,
+            |b, topology| b.iter(|| topology.output_polynomials().unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+fn construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construction");
+    group.sample_size(10);
+
+    for exponent in [3, 5, 7, 9, 11] {
+        let topology = power_of_sum(exponent);
+        let terms = topology.output_polynomials().unwrap()[0].components().len();
+        group.throughput(Throughput::Elements(terms as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("expand", terms),
+            &topology,
+            |b, topology| b.iter(|| topology.output_polynomials().unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("burn_network", terms),
+            &topology,
+            |b, topology| {
+                b.iter(|| {
+                    BurnNetwork::<NdArray>::from_topology(topology, Default::default()).unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, construction, shared);
+criterion_main!(benches);
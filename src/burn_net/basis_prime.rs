@@ -1,23 +1,28 @@
 use burn::prelude::*;
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet};
 use std::{fmt, hash::Hash};
 
-use super::expander::{Polynomial, Variable};
+use super::expander::{Monomial, Polynomial, Variable};
+use crate::core::numeric::NumericPolicy;
 
 /// a single column matrix
 #[derive(Debug)]
-pub struct BasisTemplate<T>(Vec<Vec<Variable<T>>>);
+pub struct BasisTemplate<T> {
+    rows: Vec<Monomial<T>>,
+    // row of each monomial, so terms are placed without scanning the basis
+    index: FnvHashMap<Monomial<T>, usize>,
+}
 
 impl<T: fmt::Display> fmt::Display for BasisTemplate<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Some(first) = self.0.first() else {
+        if self.rows.is_empty() {
             return write!(f, "[]");
-        };
+        }
         writeln!(f)?;
         writeln!(f, "[")?;
-        for (i, column) in self.0.iter().enumerate() {
+        for (i, column) in self.rows.iter().enumerate() {
             write!(f, "{i} [")?;
-            for variable in column.iter() {
+            for variable in column.variables() {
                 write!(f, "{variable:>5},")?;
             }
 
@@ -31,73 +36,170 @@ impl<T: fmt::Display> fmt::Display for BasisTemplate<T> {
 impl<T> BasisTemplate<T> {
     /// The tensor generated will ALWAYS have one column.
     pub fn num_rows(&self) -> usize {
-        self.0.len()
+        self.rows.len()
     }
-    pub fn from_raw(basis_vec: Vec<Vec<Variable<T>>>) -> Self {
-        Self(basis_vec)
+
+    pub fn from_raw(rows: Vec<Monomial<T>>) -> Self
+    where
+        T: Clone + Hash + Eq,
+    {
+        let index = rows
+            .iter()
+            .enumerate()
+            .map(|(row, monomial)| (monomial.clone(), row))
+            .collect();
+        Self { rows, index }
     }
 
-    pub fn position<F>(&self, predicate: F) -> Option<usize>
+    /// The row holding `monomial`, if it is part of the basis.
+    pub fn row_of(&self, monomial: &Monomial<T>) -> Option<usize>
     where
-        F: FnMut(&Vec<Variable<T>>) -> bool,
+        T: Hash + Eq,
     {
-        self.0.iter().position(predicate)
+        self.index.get(monomial).copied()
     }
 
     pub fn get(&self, index: usize) -> Option<&[Variable<T>]> {
-        self.0.get(index).map(|v| v.as_slice())
+        self.rows.get(index).map(|row| row.variables())
     }
 
     pub fn new(polynomials: &[Polynomial<T>]) -> Self
     where
-        T: Clone + PartialEq,
+        T: Clone + Hash + Eq,
     {
         let basis_vec = basis_from_poly_list(polynomials);
         Self::from_raw(basis_vec)
     }
 
+    /// Builds the `[rows, 1]` basis of a single sample.
+    ///
+    /// Variables missing from `variables` are treated as 0, like the columns past the end
+    /// of [`make_batch_tensor`](BasisTemplate::make_batch_tensor).
     pub fn make_tensor<B: Backend>(
         &self,
         variables: impl IntoIterator<Item = (T, f32)>,
+        policy: &NumericPolicy,
         device: &B::Device,
     ) -> Tensor<B, 2>
     where
         T: Hash + Eq + Clone,
     {
         let hashmap: FnvHashMap<T, f32> = variables.into_iter().collect();
-        let mut values: Vec<f32> = Vec::with_capacity(self.0.len());
+        let mut values: Vec<f32> = Vec::with_capacity(self.rows.len());
 
-        for template_vars in self.0.iter() {
+        for template_vars in self.rows.iter() {
             let mut running_val = 1.;
-            for template_var in template_vars {
-                let Some(input_val) = hashmap.get(template_var.var()) else {
-                    panic!("input val not found");
-                };
-
-                running_val *= (*input_val).powi(template_var.exponent());
+            for template_var in template_vars.variables() {
+                let input_val = hashmap.get(template_var.var()).copied().unwrap_or(0.);
+                running_val *= policy.powi(input_val, template_var.exponent());
             }
 
             values.push(running_val);
         }
 
         // Create a tensor with shape [rows, 1]
-        let data = TensorData::new(values, [self.0.len(), 1]);
+        let data = TensorData::new(values, [self.rows.len(), 1]);
+        Tensor::<B, 2>::from_data(data, device)
+    }
+}
+
+impl BasisTemplate<usize> {
+    /// Builds the basis for a whole batch at once.
+    ///
+    /// `inputs` has shape `[batch, num_inputs]`. The result has shape `[rows, batch]`, so
+    /// column `b` is the basis of sample `b`. Each distinct power of an input is computed
+    /// once and shared between the rows that use it. Inputs past the last column are
+    /// treated as 0. `policy` decides how zero inputs with negative exponents are raised.
+    pub fn make_batch_tensor<B: Backend>(
+        &self,
+        inputs: Tensor<B, 2>,
+        policy: &NumericPolicy,
+    ) -> Tensor<B, 2> {
+        let [batch, num_inputs] = inputs.dims();
+        let device = inputs.device();
+
+        if self.rows.is_empty() {
+            return Tensor::zeros([0, batch], &device);
+        }
+
+        // [num_inputs, batch], one row per input
+        let columns = inputs.transpose();
+        let mut powers: FnvHashMap<(usize, i32), Tensor<B, 2>> = FnvHashMap::default();
+
+        let rows = self
+            .rows
+            .iter()
+            .map(|template_vars| {
+                let mut running: Tensor<B, 2> = Tensor::ones([1, batch], &device);
+                for template_var in template_vars.variables() {
+                    let (var, exponent) = (*template_var.var(), template_var.exponent());
+                    let power = powers.entry((var, exponent)).or_insert_with(|| {
+                        let base = if var < num_inputs {
+                            columns.clone().narrow(0, var, 1)
+                        } else {
+                            Tensor::<B, 2>::zeros([1, batch], &device)
+                        };
+                        match policy {
+                            NumericPolicy::Epsilon(epsilon) if exponent < 0 => {
+                                let zero = base.clone().equal_elem(0.);
+                                base.mask_fill(zero, *epsilon).powi_scalar(exponent)
+                            }
+                            _ => base.powi_scalar(exponent),
+                        }
+                    });
+                    running = running * power.clone();
+                }
+                running
+            })
+            .collect::<Vec<_>>();
+
+        Tensor::cat(rows, 0)
+    }
+
+    /// Builds the basis for a batch of samples given as rows of input values.
+    ///
+    /// The values are computed on the host and uploaded as a single `[rows, batch]`
+    /// tensor. Missing inputs are treated as 0. `policy` decides how zero inputs with
+    /// negative exponents are raised.
+    pub fn make_batch_tensor_from_rows<B: Backend, R: AsRef<[f32]>>(
+        &self,
+        inputs: &[R],
+        policy: &NumericPolicy,
+        device: &B::Device,
+    ) -> Tensor<B, 2> {
+        let batch = inputs.len();
+        let mut values: Vec<f32> = vec![1.; self.rows.len() * batch];
+
+        for (template_vars, row) in self.rows.iter().zip(values.chunks_mut(batch.max(1))) {
+            for (sample, value) in inputs.iter().zip(row.iter_mut()) {
+                let sample = sample.as_ref();
+                for template_var in template_vars.variables() {
+                    let input_val = sample.get(*template_var.var()).copied().unwrap_or(0.);
+                    *value *= policy.powi(input_val, template_var.exponent());
+                }
+            }
+        }
+
+        let data = TensorData::new(values, [self.rows.len(), batch]);
         Tensor::<B, 2>::from_data(data, device)
     }
 }
 
 /// returns a basis that will be used to calculate two other matrices, to be explained
-pub(super) fn basis_from_poly_list<T: Clone + PartialEq>(
+///
+/// Rows are the distinct monomials of every polynomial, in order of first use.
+pub(super) fn basis_from_poly_list<T: Clone + Hash + Eq>(
     polynomials: &[Polynomial<T>],
-) -> Vec<Vec<Variable<T>>> {
+) -> Vec<Monomial<T>> {
     //the first thing we need to do is determine what our basis matrix looks like.
     // this is the set of used combinations for all polynomials.
-    let mut used_combinations: Vec<Vec<Variable<T>>> = Vec::new();
+    let mut seen: FnvHashSet<&Monomial<T>> = FnvHashSet::default();
+    let mut used_combinations: Vec<Monomial<T>> = Vec::new();
 
     for polynomial in polynomials {
         for component in polynomial.components() {
-            if !used_combinations.contains(&component.operands) {
-                used_combinations.push(component.operands.clone());
+            if seen.insert(component.monomial()) {
+                used_combinations.push(component.monomial().clone());
             }
         }
     }
@@ -1,70 +1,410 @@
-use std::fmt;
+use std::{fmt, hash::Hash};
 
 use burn::prelude::*;
+use tracing::debug;
 
 use super::{basis_prime::BasisTemplate, expander::Polynomial};
+use crate::{
+    core::numeric::NumericPolicy,
+    error::{PolyNeatError, Result},
+};
+
+/// Below this fraction of stored coefficients, [`CoefficientLayout::Auto`] picks the
+/// sparse layout.
+pub const SPARSE_DENSITY_THRESHOLD: f32 = 0.25;
+
+/// How the `[outputs, rows]` coefficient matrix is stored on the device.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoefficientLayout {
+    /// Pick [`Sparse`](Self::Sparse) when terms fill fewer than
+    /// [`SPARSE_DENSITY_THRESHOLD`] of the matrix, [`Dense`](Self::Dense) otherwise.
+    #[default]
+    Auto,
+    /// A full matrix, applied with a single matmul.
+    ///
+    /// An output's coefficient is 0 on every basis row it doesn't use, and `0 * inf` is
+    /// NaN, so non-finite basis entries are zeroed before the matmul. The outputs that do
+    /// use them are then fixed up on the device with a few more matmuls, fewer under
+    /// [`NumericPolicy::Sentinel`], to match the sparse layout.
+    Dense,
+    /// Only the coefficients of the terms of each polynomial, as `(output, row, weight)`
+    /// triplets, including terms whose weight is zero. Applied by gathering the basis
+    /// rows they use and summing them into their outputs.
+    ///
+    /// Besides saving memory, an output never touches basis rows it doesn't use, so
+    /// non-finite basis entries need no fixing up.
+    Sparse,
+}
 
 #[derive(Debug)]
-pub struct Coefficients<B: Backend>(Tensor<B, 2>);
+pub struct Coefficients<B: Backend> {
+    num_outputs: usize,
+    num_rows: usize,
+    // host copy of the coefficient of every term as (output, row, weight)
+    entries: Vec<(usize, usize, f32)>,
+    storage: Storage<B>,
+}
+
+#[derive(Debug)]
+enum Storage<B: Backend> {
+    Dense {
+        weights: Tensor<B, 2>,
+        // 1 wherever a term is stored, including terms whose weight is zero
+        used: Tensor<B, 2>,
+    },
+    Sparse {
+        outputs: Tensor<B, 1, Int>,
+        rows: Tensor<B, 1, Int>,
+        // [entries, 1]
+        weights: Tensor<B, 2>,
+    },
+}
 
 impl<B: Backend> fmt::Display for Coefficients<B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Coefficients({:?})", self.0.shape())
+        write!(
+            f,
+            "Coefficients([{}, {}], {:?}, {} terms)",
+            self.num_outputs,
+            self.num_rows,
+            self.layout(),
+            self.entries.len()
+        )
     }
 }
 
 impl<B: Backend> Coefficients<B> {
-    pub fn new<T: PartialEq>(
+    pub fn new<T: Hash + Eq>(
         polynomials: &[Polynomial<T>],
         basis_template: &BasisTemplate<T>,
         device: &B::Device,
-    ) -> Self {
-        //so each polynomial will be represented as a row and conform to the basis template.
-        //the vector will be flat and then we will reshape in the tensor.
-        let mut coef_vec: Vec<f32> = vec![0.; polynomials.len() * basis_template.num_rows()];
+    ) -> Result<Self> {
+        Self::with_layout(
+            polynomials,
+            basis_template,
+            CoefficientLayout::default(),
+            device,
+        )
+    }
 
+    /// Place every term of every polynomial against its row of the basis.
+    ///
+    /// Each polynomial becomes one output, i.e. one row of the `[outputs, rows]` matrix.
+    pub fn with_layout<T: Hash + Eq>(
+        polynomials: &[Polynomial<T>],
+        basis_template: &BasisTemplate<T>,
+        layout: CoefficientLayout,
+        device: &B::Device,
+    ) -> Result<Self> {
+        let mut entries = Vec::new();
         for (poly_i, polynomial) in polynomials.iter().enumerate() {
             for component in polynomial.components() {
                 let i = basis_template
-                    .position(|row| {
-                        let operands = component.operands();
-                        row == operands
-                    })
-                    .unwrap();
+                    .row_of(component.monomial())
+                    .ok_or(PolyNeatError::MissingBasisTerm { output: poly_i })?;
+
+                entries.push((poly_i, i, component.weight()));
+            }
+        }
 
-                let coef_vec_index = (basis_template.num_rows() * poly_i) + i;
+        Ok(Self::from_entries(
+            entries,
+            polynomials.len(),
+            basis_template.num_rows(),
+            layout,
+            device,
+        ))
+    }
 
-                let val = coef_vec.get_mut(coef_vec_index).unwrap();
-                *val = component.weight();
+    fn from_entries(
+        entries: Vec<(usize, usize, f32)>,
+        num_outputs: usize,
+        num_rows: usize,
+        layout: CoefficientLayout,
+        device: &B::Device,
+    ) -> Self {
+        let density = entries.len() as f32 / (num_outputs * num_rows).max(1) as f32;
+        let sparse = match layout {
+            CoefficientLayout::Auto => density < SPARSE_DENSITY_THRESHOLD,
+            CoefficientLayout::Dense => false,
+            CoefficientLayout::Sparse => true,
+        };
+        debug!(
+            "coefficients: [{num_outputs}, {num_rows}], {} terms, density {density:.3}, sparse: {sparse}",
+            entries.len()
+        );
+
+        let storage = if sparse {
+            let outputs: Vec<i32> = entries
+                .iter()
+                .map(|(output, _, _)| *output as i32)
+                .collect();
+            let rows: Vec<i32> = entries.iter().map(|(_, row, _)| *row as i32).collect();
+            let weights: Vec<f32> = entries.iter().map(|(_, _, weight)| *weight).collect();
+            Storage::Sparse {
+                outputs: Tensor::from_data(TensorData::from(outputs.as_slice()), device),
+                rows: Tensor::from_data(TensorData::from(rows.as_slice()), device),
+                weights: Tensor::from_data(TensorData::new(weights, [entries.len(), 1]), device),
+            }
+        } else {
+            //so each polynomial will be represented as a row and conform to the basis template.
+            //the vector will be flat and then we will reshape in the tensor.
+            let mut coef_vec: Vec<f32> = vec![0.; num_outputs * num_rows];
+            let mut used_vec: Vec<f32> = vec![0.; num_outputs * num_rows];
+            for (output, row, weight) in entries.iter() {
+                coef_vec[num_rows * output + row] = *weight;
+                used_vec[num_rows * output + row] = 1.;
+            }
+            let shape = [num_outputs, num_rows];
+            Storage::Dense {
+                weights: Tensor::from_data(TensorData::new(coef_vec, shape), device),
+                used: Tensor::from_data(TensorData::new(used_vec, shape), device),
             }
+        };
+
+        Self {
+            num_outputs,
+            num_rows,
+            entries,
+            storage,
         }
+    }
+
+    /// Store the same coefficients in another layout.
+    pub fn relayout(self, layout: CoefficientLayout, device: &B::Device) -> Self {
+        Self::from_entries(
+            self.entries,
+            self.num_outputs,
+            self.num_rows,
+            layout,
+            device,
+        )
+    }
+
+    /// The layout actually in use, never [`CoefficientLayout::Auto`].
+    pub fn layout(&self) -> CoefficientLayout {
+        match self.storage {
+            Storage::Dense { .. } => CoefficientLayout::Dense,
+            Storage::Sparse { .. } => CoefficientLayout::Sparse,
+        }
+    }
 
-        println!("[");
-        for (i, coef) in coef_vec.iter().enumerate() {
-            if i % polynomials.len() == 0 {
-                if i != 0 {
-                    println!("]");
+    pub fn num_outputs(&self) -> usize {
+        self.num_outputs
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// The coefficient of every term as `(output, row, weight)`, grouped by output.
+    pub fn entries(&self) -> &[(usize, usize, f32)] {
+        &self.entries
+    }
+
+    /// The fraction of the `[outputs, rows]` matrix that holds a term.
+    pub fn density(&self) -> f32 {
+        self.entries.len() as f32 / (self.num_outputs * self.num_rows).max(1) as f32
+    }
+
+    /// `[outputs, rows] x [rows, batch]`, tolerating an empty basis.
+    ///
+    /// Both layouts give the same result, even where a basis entry isn't finite, except
+    /// that under [`NumericPolicy::Sentinel`] the dense layout only keeps such outputs
+    /// non-finite, as the policy replaces them anyway.
+    pub fn matmul(&self, basis: Tensor<B, 2>, policy: &NumericPolicy) -> Tensor<B, 2> {
+        let [_, batch] = basis.dims();
+        let device = basis.device();
+        if self.num_rows == 0 || self.entries.is_empty() {
+            return Tensor::zeros([self.num_outputs, batch], &device);
+        }
+
+        match &self.storage {
+            Storage::Dense { weights, used } => {
+                // x - x is NaN exactly when x isn't finite
+                let non_finite = (basis.clone() - basis.clone()).is_nan();
+                let sanitized = basis.clone().mask_fill(non_finite.clone(), 0.);
+                let outputs = weights.clone().matmul(sanitized);
+
+                match policy {
+                    NumericPolicy::Sentinel(_) => {
+                        let broken = used.clone().matmul(non_finite.float()).greater_elem(0.);
+                        outputs.mask_fill(broken, f32::NAN)
+                    }
+                    _ => self.repair_non_finite(outputs, basis, weights.clone(), used.clone()),
                 }
-                print!("[");
             }
-            if coef.is_sign_positive() {
-                print!(" {coef:.02},");
-            } else {
-                print!("{coef:.02},");
-            }
-            if i == coef_vec.len() - 1 {
-                println!("]");
+            Storage::Sparse {
+                outputs,
+                rows,
+                weights,
+            } => {
+                let terms = basis.select(0, rows.clone()) * weights.clone();
+                Tensor::zeros([self.num_outputs, batch], &device).select_assign(
+                    0,
+                    outputs.clone(),
+                    terms,
+                )
             }
         }
-        println!("]");
+    }
+
+    /// Give every output that uses a non-finite basis entry the IEEE sum it gets under the
+    /// sparse layout, on the device.
+    ///
+    /// Once a term is non-finite, the finite terms don't matter: the sum is NaN if a term
+    /// is NaN (including `0 * inf`) or if infinities of both signs meet, and the infinity
+    /// otherwise. Counting the terms of each kind is a matmul of 0/1 matrices, which stays
+    /// finite.
+    fn repair_non_finite(
+        &self,
+        outputs: Tensor<B, 2>,
+        basis: Tensor<B, 2>,
+        weights: Tensor<B, 2>,
+        used: Tensor<B, 2>,
+    ) -> Tensor<B, 2> {
+        let nan = basis.clone().is_nan().float();
+        let positive = basis.clone().equal_elem(f32::INFINITY).float();
+        let negative = basis.equal_elem(f32::NEG_INFINITY).float();
 
-        let data = TensorData::new(coef_vec, [polynomials.len(), basis_template.num_rows()]);
-        let tensor = Tensor::from_data(data, device);
+        let positive_weights = weights.clone().greater_elem(0.).float();
+        let negative_weights = weights.lower_elem(0.).float();
+        let zero_weights = used.clone() - positive_weights.clone() - negative_weights.clone();
 
-        Self(tensor)
+        let positive_terms = positive_weights.clone().matmul(positive.clone())
+            + negative_weights.clone().matmul(negative.clone());
+        let negative_terms =
+            positive_weights.matmul(negative.clone()) + negative_weights.matmul(positive.clone());
+        let nan_terms = used.matmul(nan)
+            + zero_weights.matmul(positive + negative)
+            + positive_terms.clone() * negative_terms.clone();
+
+        outputs
+            .mask_fill(positive_terms.greater_elem(0.), f32::INFINITY)
+            .mask_fill(negative_terms.greater_elem(0.), f32::NEG_INFINITY)
+            .mask_fill(nan_terms.greater_elem(0.), f32::NAN)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    type TestBackend = NdArray;
+
+    #[test]
+    fn layouts_agree_on_non_finite_bases() {
+        // x0 + 2x1 and 3x2^2 share no basis rows
+        let polynomials = vec![
+            Polynomial::unit(0).with_operation(2., 1, 1),
+            Polynomial::default().with_operation(3., 2, 2),
+        ];
+        let basis = BasisTemplate::new(&polynomials);
+        let device = Default::default();
+
+        let auto = Coefficients::<TestBackend>::new(&polynomials, &basis, &device).unwrap();
+        assert_eq!(auto.density(), 0.5);
+        assert_eq!(auto.layout(), CoefficientLayout::Dense);
+
+        let sparse = auto.relayout(CoefficientLayout::Sparse, &device);
+        assert_eq!(sparse.layout(), CoefficientLayout::Sparse);
+        let dense = Coefficients::<TestBackend>::with_layout(
+            &polynomials,
+            &basis,
+            CoefficientLayout::Dense,
+            &device,
+        )
+        .unwrap();
+
+        // basis rows are x0, x1, x2^2, for two samples
+        let values =
+            Tensor::<TestBackend, 2>::from_data([[1., 0.], [2., 1.], [f32::INFINITY, 4.]], &device);
+        let policy = NumericPolicy::Propagate;
+        let sparse = sparse
+            .matmul(values.clone(), &policy)
+            .to_data()
+            .to_vec::<f32>()
+            .unwrap();
+        let dense = dense
+            .matmul(values, &policy)
+            .to_data()
+            .to_vec::<f32>()
+            .unwrap();
+
+        // the infinite entry doesn't leak into the other output as 0 * inf
+        assert_eq!(sparse, vec![5., 2., f32::INFINITY, 12.]);
+        assert_eq!(dense, sparse);
+    }
+
+    #[test]
+    fn dense_repair_follows_ieee_sums() {
+        // x0 + 2x1, x0 - x1, -3x2^2 and 0x2^2 + x0
+        let polynomials = vec![
+            Polynomial::unit(0).with_operation(2., 1, 1),
+            Polynomial::unit(0).with_operation(-1., 1, 1),
+            Polynomial::default().with_operation(-3., 2, 2),
+            Polynomial::unit(0).with_operation(0., 2, 2),
+        ];
+        let basis = BasisTemplate::new(&polynomials);
+        let device = Default::default();
+        let dense = Coefficients::<TestBackend>::with_layout(
+            &polynomials,
+            &basis,
+            CoefficientLayout::Dense,
+            &device,
+        )
+        .unwrap();
+        let sparse = Coefficients::<TestBackend>::with_layout(
+            &polynomials,
+            &basis,
+            CoefficientLayout::Sparse,
+            &device,
+        )
+        .unwrap();
+        assert_eq!(dense.entries().len(), 7);
+
+        let inf = f32::INFINITY;
+        // basis rows are x0, x1, x2^2, for five samples
+        let values = Tensor::<TestBackend, 2>::from_data(
+            [
+                [1., inf, inf, f32::NAN, 2.],
+                [2., 1., inf, 1., -inf],
+                [inf, 4., 1., 1., 1.],
+            ],
+            &device,
+        );
+        let run = |coefficients: &Coefficients<TestBackend>, policy: NumericPolicy| {
+            coefficients
+                .matmul(values.clone(), &policy)
+                .to_data()
+                .to_vec::<f32>()
+                .unwrap()
+        };
+
+        let sparse_values = run(&sparse, NumericPolicy::Propagate);
+        let dense_values = run(&dense, NumericPolicy::Propagate);
+        let same = |a: f32, b: f32| a == b || (a.is_nan() && b.is_nan());
+        assert!(
+            sparse_values
+                .iter()
+                .zip(&dense_values)
+                .all(|(a, b)| same(*a, *b)),
+            "sparse {sparse_values:?}, dense {dense_values:?}"
+        );
+        // inf - inf and 0 * inf
+        assert!(dense_values[5 + 2].is_nan());
+        assert!(dense_values[3 * 5].is_nan());
+        assert_eq!(dense_values[4], -inf);
 
-    pub fn inner(&self) -> &Tensor<B, 2> {
-        &self.0
+        // the sentinel only needs to know which outputs aren't finite
+        let dense_values = run(&dense, NumericPolicy::Sentinel(0.));
+        assert!(
+            sparse_values
+                .iter()
+                .zip(&dense_values)
+                .all(|(a, b)| a.is_finite() == b.is_finite()),
+            "sparse {sparse_values:?}, dense {dense_values:?}"
+        );
     }
 }
@@ -0,0 +1,338 @@
+//! Symbolic differentiation and evaluation of expanded polynomials.
+//!
+//! When an output expands into a polynomial over its inputs, its partial derivatives are
+//! exact and cheap: each term `w * x^e * ...` differentiates to `w * e * x^(e - 1) * ...`.
+//! [`Jacobian`] collects the partial derivatives of every output with respect to every
+//! input. Outputs that raise a sum to a negative power aren't polynomials, so they have
+//! no Jacobian here.
+
+use std::{fmt::Debug, hash::Hash};
+
+use super::{PolyComponent, Polynomial};
+use crate::{error::Result, prelude::*};
+
+impl<T: Clone + Ord> PolyComponent<T> {
+    /// Partial derivative of this term with respect to `var`.
+    ///
+    /// Returns `None` if the derivative is zero, i.e. the term doesn't depend on `var`.
+    pub fn derivative(&self, var: &T) -> Option<PolyComponent<T>> {
+        let position = self.operands.position(var)?;
+        let exponent = self.operands.variables()[position].exponent();
+
+        let mut operands = self.operands.clone();
+        operands.add_exponent_at(position, -1);
+
+        Some(PolyComponent {
+            weight: self.weight * exponent as f32,
+            operands,
+        })
+    }
+}
+
+impl<T> PolyComponent<T> {
+    /// Evaluate this term, looking up the value of each variable with `value_of`.
+    pub fn evaluate_with(&self, mut value_of: impl FnMut(&T) -> f32) -> f32 {
+        self.operands().iter().fold(self.weight, |acc, operand| {
+            acc * value_of(operand.var()).powi(operand.exponent())
+        })
+    }
+}
+
+impl<T: Clone + PartialEq + PartialOrd + Ord + Hash + Debug> Polynomial<T> {
+    /// Partial derivative of this polynomial with respect to `var`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use polynomial_neat::burn_net::expander::{ExpressionFormat, Polynomial};
+    ///
+    /// // 3x0^2 + x0 x1 + 4
+    /// let poly = Polynomial::new()
+    ///     .with_operation(3., 0, 2)
+    ///     .with_operation(4., 0, 0)
+    ///     .with_polycomponent(
+    ///         polynomial_neat::burn_net::expander::PolyComponent::new()
+    ///             .with_weight(1.)
+    ///             .with_operand(0, 1)
+    ///             .with_operand(1, 1),
+    ///     );
+    ///
+    /// let d_dx0 = poly.derivative(&0);
+    /// assert_eq!(d_dx0.expression(ExpressionFormat::Text).to_string(), "6*x0 + x1");
+    /// assert_eq!(d_dx0.evaluate(&[2., 5.]), 17.);
+    /// ```
+    pub fn derivative(&self, var: &T) -> Polynomial<T> {
+        let mut result = Polynomial::with_capacity(self.ops.len());
+        for component in self.ops.iter() {
+            if let Some(derivative) = component.derivative(var) {
+                result.handle_polycomponent(derivative);
+            }
+        }
+        result
+    }
+}
+
+impl<T> Polynomial<T> {
+    /// Evaluate this polynomial, looking up the value of each variable with `value_of`.
+    pub fn evaluate_with(&self, mut value_of: impl FnMut(&T) -> f32) -> f32
+    where
+        T: Clone + PartialEq,
+    {
+        self.ops
+            .iter()
+            .map(|component| component.evaluate_with(&mut value_of))
+            .sum()
+    }
+}
+
+impl Polynomial<usize> {
+    /// Evaluate this polynomial for the given input values.
+    ///
+    /// Variables are indices into `inputs`. Any variable without a value is treated
+    /// as 0, the same way `SimplePolyNetwork` treats missing inputs.
+    pub fn evaluate(&self, inputs: &[f32]) -> f32 {
+        self.evaluate_with(|var| inputs.get(*var).copied().unwrap_or_default())
+    }
+}
+
+/// The matrix of partial derivatives of every output with respect to every input.
+///
+/// Entry `(o, i)` is the polynomial `∂y_o / ∂x_i`.
+///
+/// # Example
+///
+/// ```
+/// use polynomial_neat::prelude::*;
+/// use polynomial_neat::burn_net::expander::Jacobian;
+///
+/// let topology = PolyNetworkTopology::new(3, 2, MutationChances::new(50), &mut rand::rng());
+/// let jacobian = topology.jacobian()?;
+///
+/// let sensitivities = jacobian.evaluate(&[1.0, 0.5, -2.0]);
+/// assert_eq!(sensitivities.len(), 2);
+/// assert_eq!(sensitivities[0].len(), 3);
+/// # Ok::<(), PolyNeatError>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Jacobian {
+    num_inputs: usize,
+    // row-major, one row per output
+    entries: Vec<Polynomial<usize>>,
+}
+
+impl Jacobian {
+    /// Differentiate each output polynomial with respect to inputs `0..num_inputs`.
+    pub fn new(outputs: &[Polynomial<usize>], num_inputs: usize) -> Self {
+        let entries = outputs
+            .iter()
+            .flat_map(|output| (0..num_inputs).map(move |input| output.derivative(&input)))
+            .collect();
+
+        Self {
+            num_inputs,
+            entries,
+        }
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.entries.len().checked_div(self.num_inputs).unwrap_or(0)
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// The polynomial `∂y_output / ∂x_input`, if both indices are in range.
+    pub fn get(&self, output: usize, input: usize) -> Option<&Polynomial<usize>> {
+        if input >= self.num_inputs {
+            return None;
+        }
+        self.entries.get(output * self.num_inputs + input)
+    }
+
+    /// The gradient of a single output with respect to all inputs.
+    pub fn gradient(&self, output: usize) -> Option<&[Polynomial<usize>]> {
+        let start = output * self.num_inputs;
+        self.entries.get(start..start + self.num_inputs)
+    }
+
+    /// Evaluate every partial derivative at the given input values.
+    ///
+    /// # Returns
+    /// One row per output, each containing one value per input.
+    pub fn evaluate(&self, inputs: &[f32]) -> Vec<Vec<f32>> {
+        (0..self.num_outputs())
+            .map(|output| {
+                self.gradient(output)
+                    .unwrap()
+                    .iter()
+                    .map(|partial| partial.evaluate(inputs))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl PolyNetworkTopology {
+    /// Compute the symbolic Jacobian of all outputs with respect to all inputs.
+    ///
+    /// # Errors
+    /// Fails if the outputs can't be expanded, and with [`PolyNeatError::NotAPolynomial`]
+    /// if an output isn't a polynomial. See [`PolyNetworkTopology::output_polynomials`].
+    pub fn jacobian(&self) -> Result<Jacobian> {
+        Ok(Jacobian::new(
+            &self.output_polynomials()?,
+            self.try_info()?.num_inputs,
+        ))
+    }
+}
+
+/// Partial derivatives of each polynomial with respect to each of `vars`.
+///
+/// # Returns
+/// One row per polynomial, each containing one partial derivative per variable.
+pub fn gradients<T>(polynomials: &[Polynomial<T>], vars: &[T]) -> Vec<Vec<Polynomial<T>>>
+where
+    T: Clone + PartialEq + PartialOrd + Ord + Hash + Debug,
+{
+    polynomials
+        .iter()
+        .map(|polynomial| vars.iter().map(|var| polynomial.derivative(var)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use uuid::Uuid;
+
+    #[test]
+    fn derivative_of_power() {
+        // 2x^3 + x^-1
+        let poly = Polynomial::new()
+            .with_operation(2., 0usize, 3)
+            .with_operation(1., 0, -1);
+
+        let derivative = poly.derivative(&0);
+        // 6x^2 - x^-2
+        assert_eq!(
+            derivative,
+            Polynomial::new()
+                .with_operation(6., 0, 2)
+                .with_operation(-1., 0, -2)
+        );
+    }
+
+    #[test]
+    fn derivative_drops_constants_and_other_variables() {
+        // 4 + 3y
+        let poly = Polynomial::new()
+            .with_operation(4., 0usize, 0)
+            .with_operation(3., 1, 1);
+
+        assert!(poly.derivative(&0).components().is_empty());
+        assert_eq!(
+            poly.derivative(&1),
+            Polynomial::new().with_operation(3., 1, 0)
+        );
+    }
+
+    #[test]
+    fn jacobian_matches_finite_differences() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 3., 1),
+                PolyInputTopology::downgrade(&y, 1., 1),
+            ],
+        ));
+
+        // (3x + y)^2
+        let output_1 = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&hidden, 1., 2)],
+        ));
+        // 2(3x + y) + y^2
+        let output_2 = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&hidden, 2., 1),
+                PolyInputTopology::downgrade(&y, 1., 2),
+            ],
+        ));
+        let double = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&x, 2., 1)],
+        ));
+        // 1 / (2x) + 3y^-2
+        let output_3 = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&double, 1., -1),
+                PolyInputTopology::downgrade(&y, 3., -2),
+            ],
+        ));
+
+        let topology = PolyNetworkTopology::from_raw_parts(
+            vec![x, y, hidden, output_1, output_2, double, output_3],
+            MutationChances::none(),
+        );
+
+        let jacobian = topology.jacobian().unwrap();
+        assert_eq!(jacobian.num_outputs(), 3);
+        assert_eq!(jacobian.num_inputs(), 2);
+
+        // d/dx (3x + y)^2 = 6(3x + y), d/dy = 2(3x + y)
+        // d/dx 2(3x + y) + y^2 = 6, d/dy = 2 + 2y
+        // d/dx 1 / (2x) + 3y^-2 = -1 / (2x^2), d/dy = -6y^-3
+        let values = jacobian.evaluate(&[1., 2.]);
+        assert_eq!(
+            values,
+            vec![vec![30., 10.], vec![6., 6.], vec![-0.5, -0.75]]
+        );
+
+        let network = topology.to_simple_network();
+        let h = 1e-2;
+        for input in 0..2 {
+            let mut plus = [1., 2.];
+            let mut minus = [1., 2.];
+            plus[input] += h;
+            minus[input] -= h;
+            let plus: Vec<f32> = network.predict(&plus).collect();
+            let minus: Vec<f32> = network.predict(&minus).collect();
+            for output in 0..3 {
+                let finite_difference = (plus[output] - minus[output]) / (2. * h);
+                assert!((finite_difference - values[output][input]).abs() < 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn non_polynomial_outputs_have_no_jacobian() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let sum = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 1., 1),
+                PolyInputTopology::downgrade(&y, 1., 1),
+            ],
+        ));
+        // 1 / (x + y)
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&sum, 1., -1)],
+        ));
+        let topology =
+            PolyNetworkTopology::from_raw_parts(vec![x, y, sum, output], MutationChances::none());
+
+        assert_eq!(
+            topology.jacobian().err(),
+            Some(PolyNeatError::NotAPolynomial { terms: 2 })
+        );
+    }
+}
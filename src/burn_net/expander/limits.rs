@@ -0,0 +1,456 @@
+//! Guarding the expansion against combinatorial blow-up.
+//!
+//! Raising a sum of `t` terms to the power `e` can produce up to `C(t + e - 1, e)` terms,
+//! and every layer of a deep network feeds its expansion into the next. A genome of a
+//! few dozen neurons can therefore expand into millions of terms.
+//!
+//! [`ExpansionLimits`] caps the number of terms, the total degree and the wall time of an
+//! expansion. [`ExpansionEstimate`] is a cheap upper bound on the expanded size, computed
+//! from the topology alone, that lets callers reject a genome before any expansion work
+//! starts. When a limit is hit, the expansion stops with
+//! [`PolyNeatError::ExpansionLimit`], and the caller can fall back to an evaluator that
+//! doesn't expand, such as [`LayeredNetwork`](crate::burn_net::layered::LayeredNetwork)
+//! or [`CompiledNetwork`](crate::compiled_net::CompiledNetwork).
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::burn_net::{expander::ExpansionLimits, network::BurnNetwork};
+//! use burn::backend::NdArray;
+//!
+//! let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+//! let limits = ExpansionLimits::default()
+//!     .with_max_terms(10_000)
+//!     .with_max_degree(64);
+//!
+//! let estimate = topology.estimate_expansion()?;
+//! assert!(limits.check_estimate(&estimate).is_ok());
+//!
+//! let device = burn::backend::ndarray::NdArrayDevice::default();
+//! let network = BurnNetwork::<NdArray>::from_topology_with_limits(&topology, device, &limits)?;
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use super::Polynomial;
+use crate::{
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
+
+/// Caps on the size of an expansion. Every limit is off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExpansionLimits {
+    max_terms: Option<usize>,
+    max_degree: Option<u32>,
+    max_time: Option<Duration>,
+}
+
+impl ExpansionLimits {
+    /// The most terms any polynomial may have, including intermediate ones.
+    pub fn with_max_terms(mut self, max_terms: usize) -> Self {
+        self.max_terms = Some(max_terms);
+        self
+    }
+
+    /// The highest total degree any term may have. Negative exponents count by their
+    /// absolute value.
+    pub fn with_max_degree(mut self, max_degree: u32) -> Self {
+        self.max_degree = Some(max_degree);
+        self
+    }
+
+    /// The longest a whole expansion may take.
+    pub fn with_max_time(mut self, max_time: Duration) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    pub fn max_terms(&self) -> Option<usize> {
+        self.max_terms
+    }
+
+    pub fn max_degree(&self) -> Option<u32> {
+        self.max_degree
+    }
+
+    pub fn max_time(&self) -> Option<Duration> {
+        self.max_time
+    }
+
+    /// Check an estimate against the term and degree limits.
+    ///
+    /// The estimate is an upper bound, so this can reject a genome whose actual expansion
+    /// would have fit. In exchange it costs a single pass over the topology.
+    ///
+    /// # Errors
+    /// Returns [`ExpansionLimit::Estimate`] if the estimate exceeds a limit.
+    pub fn check_estimate(&self, estimate: &ExpansionEstimate) -> Result<()> {
+        let too_many_terms = self
+            .max_terms
+            .is_some_and(|max| estimate.terms > max as u64);
+        let too_high_degree = self
+            .max_degree
+            .is_some_and(|max| estimate.degree > u64::from(max));
+
+        if too_many_terms || too_high_degree {
+            return Err(PolyNeatError::ExpansionLimit(ExpansionLimit::Estimate(
+                *estimate,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Start timing an expansion.
+    pub(crate) fn start(&self) -> ExpansionGuard {
+        ExpansionGuard {
+            limits: *self,
+            started: Instant::now(),
+        }
+    }
+}
+
+/// Which limit an expansion ran into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExpansionLimit {
+    /// The pre-estimate already exceeded a limit, so no expansion was attempted.
+    Estimate(ExpansionEstimate),
+    /// A polynomial grew past this many terms.
+    Terms(usize),
+    /// A term grew past this total degree.
+    Degree(u32),
+    /// The expansion took longer than this.
+    Time(Duration),
+}
+
+impl fmt::Display for ExpansionLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpansionLimit::Estimate(estimate) => write!(
+                f,
+                "estimated {} terms of degree {} exceed the expansion limits",
+                estimate.terms, estimate.degree
+            ),
+            ExpansionLimit::Terms(max) => write!(f, "expansion exceeded {max} terms"),
+            ExpansionLimit::Degree(max) => write!(f, "expansion exceeded degree {max}"),
+            ExpansionLimit::Time(max) => write!(f, "expansion took longer than {max:?}"),
+        }
+    }
+}
+
+/// An upper bound on the size of a topology's expanded outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExpansionEstimate {
+    /// The most terms any single output can expand into. Saturates at `u64::MAX`.
+    pub terms: u64,
+    /// The highest total degree any term of any output can have.
+    pub degree: u64,
+}
+
+impl ExpansionEstimate {
+    /// Estimate the expansion of every output of `topology` in one pass.
+    ///
+    /// Every neuron is bounded by summing, over its live inputs, the number of terms
+    /// its input can produce when raised to the connection's exponent. Cancellation and
+    /// merging of like terms are ignored, which is what makes this an upper bound.
+    ///
+    /// That sum overcounts badly once the same terms reach a neuron along many paths, so
+    /// each neuron is also capped at the number of distinct monomials of its degree over
+    /// the inputs: `C(inputs + degree, degree)`, or more once negative exponents are
+    /// involved.
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn of(topology: &PolyNetworkTopology) -> Result<Self> {
+        let order = topology.try_topological_order()?;
+        let mut num_inputs = 0;
+        for neuron in order.iter() {
+            num_inputs += u64::from(neuron.read()?.props().is_none());
+        }
+
+        // the bound of every neuron, and whether a negative exponent reaches it
+        let mut bounds: HashMap<Uuid, (ExpansionEstimate, bool)> = HashMap::new();
+        let mut estimate = ExpansionEstimate::default();
+
+        for neuron in order {
+            let neuron = neuron.read()?;
+            let (bound, negative) = match neuron.props() {
+                None => (
+                    ExpansionEstimate {
+                        terms: 1,
+                        degree: 1,
+                    },
+                    false,
+                ),
+                Some(props) => {
+                    let mut bound = ExpansionEstimate::default();
+                    let mut negative = false;
+                    for input in props.inputs() {
+                        let Some(source) = input.neuron() else {
+                            continue;
+                        };
+                        let (source, source_negative) = bounds[&source.read()?.id()];
+                        let exponent = u64::from(input.exponent().unsigned_abs());
+
+                        bound.terms = bound
+                            .terms
+                            .saturating_add(power_terms(source.terms, exponent));
+                        bound.degree = bound.degree.max(source.degree.saturating_mul(exponent));
+                        negative |= source_negative || input.exponent() < 0;
+                    }
+                    bound.terms = bound
+                        .terms
+                        .min(monomials(num_inputs, bound.degree, negative));
+                    (bound, negative)
+                }
+            };
+
+            if neuron.is_output() {
+                estimate.terms = estimate.terms.max(bound.terms);
+                estimate.degree = estimate.degree.max(bound.degree);
+            }
+            bounds.insert(neuron.id(), (bound, negative));
+        }
+
+        Ok(estimate)
+    }
+}
+
+/// The most terms a polynomial of `terms` terms can expand into when raised to
+/// `exponent`: the number of multisets of size `exponent`, `C(terms + exponent - 1, exponent)`.
+fn power_terms(terms: u64, exponent: u64) -> u64 {
+    if exponent == 0 || terms == 0 {
+        // x^0 is a single constant, and an empty polynomial stays empty
+        return u64::from(exponent == 0);
+    }
+
+    binomial((terms - 1).saturating_add(exponent), exponent)
+}
+
+/// The number of distinct monomials over `variables` variables whose exponents sum to at
+/// most `degree` in absolute value.
+///
+/// Without negative exponents that's `C(variables + degree, degree)`. With them, a
+/// monomial picks `k` of the variables, a sign for each, and a split of at most `degree`
+/// between them into positive parts, so there are `sum_k 2^k C(variables, k) C(degree, k)`.
+fn monomials(variables: u64, degree: u64, negative: bool) -> u64 {
+    if !negative {
+        return binomial(variables.saturating_add(degree), degree);
+    }
+
+    let mut count = 0u64;
+    for k in 0..=variables.min(degree) {
+        let signs = 1u64.checked_shl(k as u32).unwrap_or(u64::MAX);
+        let with_k = binomial(variables, k)
+            .saturating_mul(binomial(degree, k))
+            .saturating_mul(signs);
+        count = count.saturating_add(with_k);
+    }
+    count
+}
+
+/// `C(n, k)`, saturating at `u64::MAX`.
+fn binomial(n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    // C(n, k) == C(n, n - k), so iterate over the smaller of the two
+    let k = k.min(n - k);
+
+    let mut count = 1f64;
+    for i in 1..=k {
+        count = count * (n - k + i) as f64 / i as f64;
+        if count >= u64::MAX as f64 {
+            return u64::MAX;
+        }
+    }
+    count.round() as u64
+}
+
+impl PolyNetworkTopology {
+    /// A cheap upper bound on the size of this topology's expanded outputs.
+    ///
+    /// See [`ExpansionEstimate::of`].
+    pub fn estimate_expansion(&self) -> Result<ExpansionEstimate> {
+        ExpansionEstimate::of(self)
+    }
+}
+
+/// Checks a running expansion against its limits.
+pub(crate) struct ExpansionGuard {
+    limits: ExpansionLimits,
+    started: Instant,
+}
+
+impl ExpansionGuard {
+    /// A guard that never trips.
+    pub(crate) fn unlimited() -> Self {
+        ExpansionLimits::default().start()
+    }
+
+    /// Check a polynomial produced during the expansion.
+    pub(crate) fn check<T>(&self, polynomial: &Polynomial<T>) -> Result<()> {
+        self.check_terms(polynomial.components().len())?;
+        self.check_degree(u64::from(polynomial.degree()))?;
+        self.check_time()
+    }
+
+    /// Check the degree a polynomial has or is about to have.
+    pub(crate) fn check_degree(&self, degree: u64) -> Result<()> {
+        match self.limits.max_degree {
+            Some(max) if degree > u64::from(max) => {
+                Err(PolyNeatError::ExpansionLimit(ExpansionLimit::Degree(max)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the term count of a polynomial that is still being built.
+    pub(crate) fn check_terms(&self, terms: usize) -> Result<()> {
+        match self.limits.max_terms {
+            Some(max) if terms > max => {
+                Err(PolyNeatError::ExpansionLimit(ExpansionLimit::Terms(max)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the time spent since the expansion started.
+    pub(crate) fn check_time(&self) -> Result<()> {
+        match self.limits.max_time {
+            Some(max) if self.started.elapsed() > max => {
+                Err(PolyNeatError::ExpansionLimit(ExpansionLimit::Time(max)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_terms_counts_multisets() {
+        assert_eq!(power_terms(5, 0), 1);
+        assert_eq!(power_terms(1, 7), 1);
+        // (a + b)^2 = a^2 + 2ab + b^2
+        assert_eq!(power_terms(2, 2), 3);
+        // (a + b + c)^3 has C(5, 3) = 10 terms
+        assert_eq!(power_terms(3, 3), 10);
+        assert_eq!(power_terms(4, 1), 4);
+        assert_eq!(power_terms(1000, 1000), u64::MAX);
+    }
+
+    #[test]
+    fn monomials_count_distinct_terms() {
+        // 1, x, y, x^2, xy, y^2
+        assert_eq!(monomials(2, 2, false), 6);
+        // 1, x, x^-1, y, y^-1
+        assert_eq!(monomials(2, 1, true), 5);
+        // 1, x^±1, x^±2
+        assert_eq!(monomials(1, 2, true), 5);
+        assert_eq!(monomials(0, 5, true), 1);
+        assert_eq!(monomials(1000, 1000, true), u64::MAX);
+    }
+
+    #[test]
+    fn estimate_is_capped_by_distinct_monomials() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let sum = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 1., 1),
+                PolyInputTopology::downgrade(&y, 1., 1),
+            ],
+        ));
+        // ten copies of (x + y)^2 still only reach 1, x, y, x^2, xy and y^2
+        let squares = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            (0..10)
+                .map(|_| PolyInputTopology::downgrade(&sum, 1., 2))
+                .collect(),
+        ));
+        let topology = PolyNetworkTopology::from_raw_parts(
+            vec![x.clone(), y, sum, squares],
+            MutationChances::none(),
+        );
+        assert_eq!(
+            topology.estimate_expansion().unwrap(),
+            ExpansionEstimate {
+                terms: 6,
+                degree: 2
+            }
+        );
+
+        // a deep chain of squares saturated the uncapped sum long before its degree did
+        let mut neurons = vec![x.clone()];
+        let mut previous = x;
+        for _ in 0..40 {
+            let next = arc(PolyNeuronTopology::hidden(
+                Uuid::new_v4(),
+                vec![
+                    PolyInputTopology::downgrade(&previous, 1., 2),
+                    PolyInputTopology::downgrade(&previous, 1., 1),
+                ],
+            ));
+            neurons.push(next.clone());
+            previous = next;
+        }
+        neurons.push(arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&previous, 1., 1)],
+        )));
+        let topology = PolyNetworkTopology::from_raw_parts(neurons, MutationChances::none());
+        let estimate = topology.estimate_expansion().unwrap();
+        assert_eq!(estimate.degree, 1 << 40);
+        // 1, x, ..., x^d
+        assert_eq!(estimate.terms, (1 << 40) + 1);
+    }
+
+    #[test]
+    fn guard_stops_intermediate_powers() {
+        // (x + y)^3 has 4 terms, and its square already has 3
+        let sum = Polynomial::unit(0).with_operation(1., 1, 1);
+        let guard = ExpansionLimits::default().with_max_terms(3).start();
+
+        let mut result = Polynomial::default();
+        assert_eq!(
+            result
+                .expand_within(sum.clone(), 1., 2, &guard)
+                .map(|p| p.components().len()),
+            Ok(3)
+        );
+        assert_eq!(
+            Polynomial::default()
+                .expand_within(sum, 1., 3, &guard)
+                .err(),
+            Some(PolyNeatError::ExpansionLimit(ExpansionLimit::Terms(3)))
+        );
+    }
+
+    #[test]
+    fn guard_stops_a_single_product_partway() {
+        // the full product would have 10^8 terms, far too many to build before checking
+        let sum = |offset: usize| {
+            (0..10_000).fold(Polynomial::new(), |sum, var| {
+                sum.with_operation(1., offset + var, 1)
+            })
+        };
+        let guard = ExpansionLimits::default().with_max_terms(100).start();
+
+        assert_eq!(
+            sum(0).mul_within(&sum(10_000), &guard).err(),
+            Some(PolyNeatError::ExpansionLimit(ExpansionLimit::Terms(100)))
+        );
+    }
+}
@@ -1,3 +1,14 @@
+//! Symbolic expansion of network topologies into closed-form polynomials.
+//!
+//! Every output of a feedforward polynomial network is itself a polynomial in the
+//! network inputs, as long as no sum of several terms is raised to a negative power.
+//! [`Polynomial`] is that closed form: a sum of weighted [`PolyComponent`]s, each a
+//! product of [`Variable`]s raised to integer exponents.
+//!
+//! Each term's variables form a canonical [`Monomial`], and every polynomial keeps a hash
+//! index from monomial to term, so merging like terms while expanding takes constant
+//! time per term instead of a scan over every term so far.
+
 use std::{
     cmp::Ordering,
     collections::HashMap,
@@ -6,12 +17,25 @@ use std::{
     ops::{Mul, MulAssign},
 };
 
+use fnv::FnvHashMap;
 use uuid::Uuid;
 
+use crate::error::{PolyNeatError, Result};
+
+mod derivative;
+mod limits;
+mod monomial;
+mod render;
+pub use derivative::{Jacobian, gradients};
+pub(crate) use limits::ExpansionGuard;
+pub use limits::{ExpansionEstimate, ExpansionLimit, ExpansionLimits};
+pub use monomial::Monomial;
+pub use render::{Expression, ExpressionFormat};
+
 #[cfg(test)]
 mod tests;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct Variable<T> {
     var: T,
     exponent: i32,
@@ -41,25 +65,31 @@ impl<T> Variable<T> {
 }
 
 impl<T: Debug + Hash + Eq> Variable<T> {
+    /// Replace the variable using `operands`.
+    ///
+    /// # Errors
+    /// Returns [`PolyNeatError::UnknownVariable`] if the variable has no mapping.
     pub fn map_operands<V: Clone + Debug, S: BuildHasher>(
         self,
         operands: &HashMap<T, V, S>,
-    ) -> Variable<V> {
+    ) -> Result<Variable<V>> {
         let Some(new_var) = operands.get(&self.var).cloned() else {
-            panic!("couldn't find {:?}\noperands: {:#?}", self.var, operands);
+            return Err(PolyNeatError::UnknownVariable {
+                variable: format!("{:?}", self.var),
+            });
         };
 
-        Variable {
+        Ok(Variable {
             var: new_var,
             exponent: self.exponent,
-        }
+        })
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PolyComponent<T> {
     weight: f32,
-    pub(crate) operands: Vec<Variable<T>>,
+    pub(crate) operands: Monomial<T>,
 }
 
 impl<T: fmt::Display> fmt::Display for PolyComponent<T> {
@@ -85,7 +115,7 @@ impl<T> Default for PolyComponent<T> {
     fn default() -> Self {
         Self {
             weight: 0.,
-            operands: Vec::new(),
+            operands: Monomial::one(),
         }
     }
 }
@@ -96,52 +126,57 @@ impl<T> PolyComponent<T> {
     }
 
     pub fn operands(&self) -> &[Variable<T>] {
+        self.operands.variables()
+    }
+
+    /// The canonical product of this term's variables.
+    pub fn monomial(&self) -> &Monomial<T> {
         &self.operands
     }
+
+    /// Raise the term to the power of -1, inverting its weight and every exponent.
+    pub fn invert(&mut self) {
+        self.weight = self.weight.recip();
+        self.operands.invert();
+    }
 }
 
 impl<T: Debug + Hash + Eq> PolyComponent<T> {
-    pub fn map_operands<V: Clone + Debug, S: BuildHasher>(
+    /// Replace every operand's variable using `operands`.
+    ///
+    /// # Errors
+    /// Returns [`PolyNeatError::UnknownVariable`] if any variable has no mapping.
+    pub fn map_operands<V: Clone + Debug + Ord, S: BuildHasher>(
         self,
         operands: &HashMap<T, V, S>,
-    ) -> PolyComponent<V> {
-        PolyComponent {
+    ) -> Result<PolyComponent<V>> {
+        let variables = self
+            .operands
+            .into_variables()
+            .into_iter()
+            .map(|var| var.map_operands(operands))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(PolyComponent {
             weight: self.weight,
-            operands: self
-                .operands
-                .into_iter()
-                .map(|var| var.map_operands(operands))
-                .collect(),
-        }
+            operands: Monomial::new(variables),
+        })
     }
 }
 
 impl<T: Ord> PolyComponent<T> {
     pub fn new() -> Self {
-        Self {
-            weight: 0.,
-            operands: Vec::new(),
-        }
+        Self::default()
     }
 
-    pub fn with_capacity(cap: usize) -> Self {
-        Self {
-            weight: 0.,
-            operands: Vec::with_capacity(cap),
-        }
+    pub fn with_capacity(_cap: usize) -> Self {
+        Self::default()
     }
 
     pub fn simple(weight: f32, var: T, exponent: i32) -> Self {
-        if exponent == 0 {
-            return Self {
-                weight,
-                operands: Vec::new(),
-            };
-        }
-
         Self {
             weight,
-            operands: vec![Variable { var, exponent }],
+            operands: Monomial::variable(var, exponent),
         }
     }
 
@@ -150,43 +185,39 @@ impl<T: Ord> PolyComponent<T> {
         self
     }
 
-    /// Adds the operand to the component. Simplifies if the operand already exists and sorts.
+    /// Multiplies the component by `var^exponent`, simplifying if the variable already exists.
     pub fn with_operand(mut self, var: T, exponent: i32) -> Self {
-        if exponent == 0 {
-            return self;
-        }
-
-        match self.operands.iter_mut().find(|op| op.var == var) {
-            Some(op) => {
-                op.exponent += exponent;
-            }
-            None => {
-                self.operands.push(Variable { var, exponent });
-                self.operands.sort();
-                return self;
-            }
-        }
-
-        self.operands.retain(|op| op.exponent != 0);
+        self.operands = self.operands.with_variable(var, exponent);
         self
     }
 
     pub fn base(weight: f32) -> Self {
         Self {
             weight,
-            operands: Vec::new(),
+            operands: Monomial::one(),
         }
     }
 
-    /// Note: does not simplify duplicates. use `with_operand` for this behavior.
-    pub fn from_raw_parts(weight: f32, mut operands: Vec<Variable<T>>) -> Self {
-        operands.sort();
-
-        Self { weight, operands }
+    /// Builds a component from a list of variables, merging duplicates into a canonical
+    /// [`Monomial`].
+    pub fn from_raw_parts(weight: f32, operands: Vec<Variable<T>>) -> Self {
+        Self {
+            weight,
+            operands: Monomial::new(operands),
+        }
     }
 
-    pub fn sort(&mut self) {
-        self.operands.sort();
+    /// Components are always canonical, so this is a no-op kept for compatibility.
+    pub fn sort(&mut self) {}
+}
+
+impl<T: Ord + Clone> PolyComponent<T> {
+    /// The product of two components, without consuming either.
+    pub fn product(&self, other: &Self) -> Self {
+        Self {
+            weight: self.weight * other.weight,
+            operands: self.operands.product(&other.operands),
+        }
     }
 }
 
@@ -197,42 +228,34 @@ impl<T> MulAssign<f32> for PolyComponent<T> {
         self.weight *= rhs;
     }
 }
-impl<T: PartialEq> MulAssign for PolyComponent<T> {
+impl<T: Ord> MulAssign for PolyComponent<T> {
     fn mul_assign(&mut self, rhs: Self) {
         self.weight *= rhs.weight;
-        for operand in rhs.operands {
-            match self.operands.iter_mut().find(|op| op.var == operand.var) {
-                Some(op) => {
-                    op.exponent += operand.exponent;
-                }
-                None => self.operands.push(operand),
-            }
-        }
+        self.operands = std::mem::take(&mut self.operands) * rhs.operands;
     }
 }
 
-impl<T: PartialEq> Mul for PolyComponent<T> {
+impl<T: Ord> Mul for PolyComponent<T> {
     type Output = PolyComponent<T>;
     fn mul(self, rhs: Self) -> Self::Output {
-        let mut new_ops = self.operands;
-        for operand in rhs.operands {
-            match new_ops.iter_mut().find(|op| op.var == operand.var) {
-                Some(op) => {
-                    op.exponent += operand.exponent;
-                }
-                None => new_ops.push(operand),
-            }
-        }
         PolyComponent {
-            operands: new_ops,
+            operands: self.operands * rhs.operands,
             weight: self.weight * rhs.weight,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Polynomial<T> {
     ops: Vec<PolyComponent<T>>,
+    // position of each monomial in `ops`
+    index: FnvHashMap<Monomial<T>, usize>,
+}
+
+impl<T: PartialEq> PartialEq for Polynomial<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ops == other.ops
+    }
 }
 
 impl<T: fmt::Display> fmt::Display for Polynomial<T> {
@@ -249,7 +272,10 @@ impl<T: fmt::Display> fmt::Display for Polynomial<T> {
 
 impl<T> Default for Polynomial<T> {
     fn default() -> Self {
-        Self { ops: Vec::new() }
+        Self {
+            ops: Vec::new(),
+            index: FnvHashMap::default(),
+        }
     }
 }
 
@@ -264,37 +290,50 @@ impl<T> Polynomial<T> {
     pub fn into_components(self) -> Vec<PolyComponent<T>> {
         self.ops
     }
+
+    /// The highest total degree of any term. Negative exponents count by their absolute
+    /// value.
+    pub fn degree(&self) -> u32 {
+        self.ops
+            .iter()
+            .map(|component| component.operands.degree())
+            .max()
+            .unwrap_or(0)
+    }
 }
 
 impl<T: Debug + Hash + Eq> Polynomial<T> {
-    pub fn map_operands<V: Debug + Clone, S: BuildHasher>(
+    /// Replace every variable of every term using `operands`.
+    ///
+    /// Terms that map onto the same monomial are merged.
+    ///
+    /// # Errors
+    /// Returns [`PolyNeatError::UnknownVariable`] if any variable has no mapping.
+    pub fn map_operands<V: Debug + Clone + Hash + Ord, S: BuildHasher>(
         self,
         operands: &HashMap<T, V, S>,
-    ) -> Polynomial<V> {
-        Polynomial {
-            ops: self
-                .ops
-                .into_iter()
-                .map(|polyc| polyc.map_operands(operands))
-                .collect(),
+    ) -> Result<Polynomial<V>> {
+        let mut mapped = Polynomial::with_capacity(self.ops.len());
+        for component in self.ops {
+            mapped.handle_polycomponent(component.map_operands(operands)?);
         }
+        Ok(mapped)
     }
 }
 
-impl<T: Clone + PartialEq + PartialOrd + Ord + std::fmt::Debug> Polynomial<T> {
+impl<T: Clone + PartialEq + PartialOrd + Ord + Hash + std::fmt::Debug> Polynomial<T> {
     pub fn new() -> Self {
-        Self { ops: Vec::new() }
+        Self::default()
     }
 
     pub fn unit(var: T) -> Self {
-        Self {
-            ops: vec![PolyComponent::simple(1., var, 1)],
-        }
+        Self::default().with_polycomponent(PolyComponent::simple(1., var, 1))
     }
 
     pub fn with_capacity(cap: usize) -> Self {
         Self {
             ops: Vec::with_capacity(cap),
+            index: FnvHashMap::with_capacity_and_hasher(cap, Default::default()),
         }
     }
     pub fn with_operation(mut self, weight: f32, variable: T, exponent: i32) -> Self {
@@ -310,88 +349,201 @@ impl<T: Clone + PartialEq + PartialOrd + Ord + std::fmt::Debug> Polynomial<T> {
     pub fn handle_operation(&mut self, weight: f32, variable: T, exponent: i32) -> &mut Self {
         self.handle_polycomponent(PolyComponent::simple(weight, variable, exponent))
     }
-    pub fn handle_polycomponent(&mut self, mut component: PolyComponent<T>) -> &mut Self {
-        component.sort();
-        match self
-            .ops
-            .iter_mut()
-            .find(|op| op.operands == component.operands)
-        {
-            Some(op) => {
-                op.weight += component.weight;
+
+    /// Adds a term, merging it into the existing term with the same monomial if any.
+    pub fn handle_polycomponent(&mut self, component: PolyComponent<T>) -> &mut Self {
+        match self.index.get(&component.operands) {
+            Some(&position) => {
+                self.ops[position].weight += component.weight;
+            }
+            None => {
+                self.index
+                    .insert(component.operands.clone(), self.ops.len());
+                self.ops.push(component);
             }
-            None => self.ops.push(component),
         }
         self
     }
 
-    pub fn sort_by_exponent(&mut self, order_on: T) {
-        self.ops.iter_mut().for_each(|op| op.sort());
+    /// The term with the given monomial, if any.
+    pub fn component(&self, monomial: &Monomial<T>) -> Option<&PolyComponent<T>> {
+        self.index
+            .get(monomial)
+            .map(|&position| &self.ops[position])
+    }
+
+    /// Rebuilds the monomial index after the terms were reordered or rewritten.
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (position, component) in self.ops.iter().enumerate() {
+            self.index.insert(component.operands.clone(), position);
+        }
+    }
 
+    pub fn sort_by_exponent(&mut self, order_on: T) {
         self.ops.sort_by(|a, b| {
-            let t_on_a = a.operands.iter().find(|op| op.var == order_on);
-            let t_on_b = b.operands.iter().find(|op| op.var == order_on);
+            let t_on_a = a.operands.exponent_of(&order_on);
+            let t_on_b = b.operands.exponent_of(&order_on);
 
             match (t_on_a, t_on_b) {
-                (Some(a), Some(b)) => a.exponent.cmp(&b.exponent),
-                (Some(_), None) => Ordering::Greater,
-                (None, Some(_)) => Ordering::Less,
-                (None, None) => a.weight.partial_cmp(&b.weight).unwrap_or(Ordering::Equal),
+                (0, 0) => a.weight.partial_cmp(&b.weight).unwrap_or(Ordering::Equal),
+                (_, 0) => Ordering::Greater,
+                (0, _) => Ordering::Less,
+                (a, b) => a.cmp(&b),
             }
         });
+        self.reindex();
     }
 
     /// raises the whole polynomial to the power of -1.
     ///
-    /// In turn, all of the exponents are multiplied by -1.
-    pub fn invert(&mut self) {
+    /// Only a single term stays a polynomial when inverted: its weight is inverted and
+    /// its exponents are multiplied by -1. A sum such as `1 / (x + y)` has no
+    /// polynomial form.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::NotAPolynomial`] unless there is exactly one term.
+    pub fn invert(&mut self) -> Result<()> {
+        self.check_invertible()?;
         for component in self.ops.iter_mut() {
-            for operand in component.operands.iter_mut() {
-                operand.exponent *= -1;
-            }
+            component.invert();
+        }
+        self.reindex();
+        Ok(())
+    }
+
+    /// Fails unless [`invert`](Self::invert) would succeed.
+    pub(crate) fn check_invertible(&self) -> Result<()> {
+        match self.ops.len() {
+            1 => Ok(()),
+            terms => Err(PolyNeatError::NotAPolynomial { terms }),
         }
     }
 
     /// FOIL
-    fn mul_expand(self, other: &Polynomial<T>) -> Polynomial<T> {
+    ///
+    /// The term count and time are checked against `guard` after every product, so an
+    /// oversized product is abandoned as soon as it outgrows the limits.
+    fn mul_expand(&self, other: &Polynomial<T>, guard: &ExpansionGuard) -> Result<Polynomial<T>> {
         let mut result =
             Polynomial::with_capacity(self.components().len().max(other.components().len()) * 2); // a guesstimate
 
-        for c1 in self.into_components() {
+        for c1 in self.components() {
             for c2 in other.components() {
-                let together = c1.clone() * c2.clone();
-                result.handle_polycomponent(together);
+                result.handle_polycomponent(c1.product(c2));
+                guard.check_terms(result.components().len())?;
+                guard.check_time()?;
             }
         }
 
-        result
+        Ok(result)
     }
 
+    /// Adds `weight * other^exponent`.
+    ///
+    /// # Panics
+    /// Panics if `exponent` is negative and `other` isn't a single term. See
+    /// [`try_expand`](Self::try_expand).
     pub fn expand(&mut self, other: Polynomial<T>, weight: f32, exponent: i32) -> &mut Self {
-        // important to clone here since mutating other will multiply the exponents.
+        self.try_expand(other, weight, exponent)
+            .expect("only a single term can be raised to a negative power")
+    }
 
+    /// Adds `weight * other^exponent`.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::NotAPolynomial`] if `exponent` is negative and `other`
+    /// isn't a single term.
+    pub fn try_expand(
+        &mut self,
+        other: Polynomial<T>,
+        weight: f32,
+        exponent: i32,
+    ) -> Result<&mut Self> {
+        self.expand_within(other, weight, exponent, &ExpansionGuard::unlimited())
+    }
+
+    /// [`expand`](Self::expand), checking every intermediate power against `guard`.
+    pub(crate) fn expand_within(
+        &mut self,
+        other: Polynomial<T>,
+        weight: f32,
+        exponent: i32,
+        guard: &ExpansionGuard,
+    ) -> Result<&mut Self> {
         if exponent == 0 {
             self.handle_polycomponent(PolyComponent::base(weight));
-            return self;
+            return Ok(self);
         }
 
-        let mut running = other.clone();
-
-        for _ in 1..exponent.abs() {
-            running = running.mul_expand(&other);
+        if exponent < 0 {
+            other.check_invertible()?;
         }
+        let power = other.power_within(exponent.unsigned_abs(), guard)?;
+        self.add_scaled(&power, weight, exponent < 0)?;
+        guard.check(self)?;
 
-        if exponent < 0 {
-            running.invert();
+        Ok(self)
+    }
+
+    /// This polynomial raised to a positive `exponent`, checking every intermediate power
+    /// against `guard`.
+    ///
+    /// The degree of the result is known up front, so it is checked before any product is
+    /// built. The power is then built by repeated squaring, in `O(log exponent)` products.
+    pub(crate) fn power_within(&self, exponent: u32, guard: &ExpansionGuard) -> Result<Self> {
+        guard.check_degree(u64::from(self.degree()) * u64::from(exponent))?;
+
+        let mut result: Option<Self> = None;
+        let mut square = self.clone();
+        let mut remaining = exponent;
+        loop {
+            if remaining & 1 == 1 {
+                result = Some(match result {
+                    Some(result) => result.mul_within(&square, guard)?,
+                    None => square.clone(),
+                });
+            }
+            remaining >>= 1;
+            if remaining == 0 {
+                break;
+            }
+            square = square.mul_within(&square, guard)?;
         }
 
-        running *= weight;
+        Ok(result.unwrap_or_else(|| Polynomial::new().with_polycomponent(PolyComponent::base(1.))))
+    }
+
+    /// The product of two polynomials, checked against `guard`.
+    pub(crate) fn mul_within(&self, other: &Polynomial<T>, guard: &ExpansionGuard) -> Result<Self> {
+        let product = self.mul_expand(other, guard)?;
+        guard.check(&product)?;
+        Ok(product)
+    }
 
-        for component in running.into_components() {
+    /// Adds `weight * power`, or `weight / power` if `invert` is set.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::NotAPolynomial`] if `invert` is set and `power` isn't a
+    /// single term.
+    pub(crate) fn add_scaled(
+        &mut self,
+        power: &Polynomial<T>,
+        weight: f32,
+        invert: bool,
+    ) -> Result<()> {
+        if invert {
+            power.check_invertible()?;
+        }
+        for component in power.components() {
+            let mut component = component.clone();
+            if invert {
+                component.invert();
+            }
+            component *= weight;
             self.handle_polycomponent(component);
         }
-
-        self
+        Ok(())
     }
 }
 
@@ -0,0 +1,222 @@
+use std::{fmt, iter::Peekable};
+
+use super::Variable;
+
+/// A product of variables raised to integer exponents, e.g. `x0^2 x3^-1`.
+///
+/// Monomials are kept canonical: variables are sorted, each appears at most once, and no
+/// exponent is zero. Two monomials are therefore equal exactly when they describe the
+/// same product, so like terms can be found by hashing instead of by scanning.
+///
+/// # Example
+///
+/// ```rust
+/// use polynomial_neat::burn_net::expander::{Monomial, Variable};
+///
+/// // x1 x0^2 x1^-1 simplifies to x0^2
+/// let monomial = Monomial::new([
+///     Variable::new(1, 1),
+///     Variable::new(0, 2),
+///     Variable::new(1, -1),
+/// ]);
+/// assert_eq!(monomial, Monomial::variable(0, 2));
+/// assert_eq!(monomial.degree(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Monomial<T>(Vec<Variable<T>>);
+
+impl<T> Default for Monomial<T> {
+    fn default() -> Self {
+        Self::one()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Monomial<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "1");
+        }
+        for variable in self.0.iter() {
+            write!(f, "{variable}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Monomial<T> {
+    /// The empty product, i.e. a constant term.
+    pub fn one() -> Self {
+        Self(Vec::new())
+    }
+
+    /// The variables of this monomial, sorted and distinct.
+    pub fn variables(&self) -> &[Variable<T>] {
+        &self.0
+    }
+
+    pub fn into_variables(self) -> Vec<Variable<T>> {
+        self.0
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The total degree. Negative exponents count by their absolute value.
+    pub fn degree(&self) -> u32 {
+        self.0
+            .iter()
+            .map(|variable| variable.exponent.unsigned_abs())
+            .sum()
+    }
+
+    /// Raise the monomial to the power of -1.
+    pub fn invert(&mut self) {
+        for variable in self.0.iter_mut() {
+            variable.exponent *= -1;
+        }
+    }
+}
+
+impl<T: Ord> Monomial<T> {
+    /// Multiply `variables` together, merging repeated variables and dropping any that
+    /// cancel out.
+    pub fn new(variables: impl IntoIterator<Item = Variable<T>>) -> Self {
+        let mut variables: Vec<Variable<T>> = variables.into_iter().collect();
+        variables.sort_by(|a, b| a.var.cmp(&b.var));
+
+        let mut canonical: Vec<Variable<T>> = Vec::with_capacity(variables.len());
+        for variable in variables {
+            match canonical.last_mut() {
+                Some(last) if last.var == variable.var => last.exponent += variable.exponent,
+                _ => {
+                    if canonical.last().is_some_and(|last| last.exponent == 0) {
+                        canonical.pop();
+                    }
+                    canonical.push(variable);
+                }
+            }
+        }
+        if canonical.last().is_some_and(|last| last.exponent == 0) {
+            canonical.pop();
+        }
+
+        Self(canonical)
+    }
+
+    /// `var^exponent` on its own.
+    pub fn variable(var: T, exponent: i32) -> Self {
+        if exponent == 0 {
+            return Self::one();
+        }
+        Self(vec![Variable { var, exponent }])
+    }
+
+    /// The exponent of `var`, 0 if it doesn't appear.
+    pub fn exponent_of(&self, var: &T) -> i32 {
+        self.position(var)
+            .map_or(0, |position| self.0[position].exponent)
+    }
+
+    /// Multiply by `var^exponent`.
+    pub fn with_variable(self, var: T, exponent: i32) -> Self {
+        self * Self::variable(var, exponent)
+    }
+
+    pub(crate) fn position(&self, var: &T) -> Option<usize> {
+        self.0
+            .binary_search_by(|variable| variable.var.cmp(var))
+            .ok()
+    }
+
+    /// Adds `exponent` to the exponent of the variable at `position`, dropping it if it
+    /// cancels out.
+    pub(crate) fn add_exponent_at(&mut self, position: usize, exponent: i32) {
+        self.0[position].exponent += exponent;
+        if self.0[position].exponent == 0 {
+            self.0.remove(position);
+        }
+    }
+}
+
+impl<T: Ord + Clone> Monomial<T> {
+    /// The product of two monomials, without consuming either.
+    pub fn product(&self, other: &Self) -> Self {
+        merge(
+            self.0.iter().cloned().peekable(),
+            other.0.iter().cloned().peekable(),
+        )
+    }
+}
+
+impl<T: Ord> std::ops::Mul for Monomial<T> {
+    type Output = Monomial<T>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        merge(self.0.into_iter().peekable(), rhs.0.into_iter().peekable())
+    }
+}
+
+/// Merges two canonical variable lists in a single pass.
+fn merge<T: Ord, A, B>(mut a: Peekable<A>, mut b: Peekable<B>) -> Monomial<T>
+where
+    A: ExactSizeIterator<Item = Variable<T>>,
+    B: ExactSizeIterator<Item = Variable<T>>,
+{
+    use std::cmp::Ordering;
+
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    loop {
+        let ordering = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) => x.var.cmp(&y.var),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        match ordering {
+            Ordering::Less => merged.extend(a.next()),
+            Ordering::Greater => merged.extend(b.next()),
+            Ordering::Equal => {
+                let (mut x, y) = (a.next().unwrap(), b.next().unwrap());
+                x.exponent += y.exponent;
+                if x.exponent != 0 {
+                    merged.push(x);
+                }
+            }
+        }
+    }
+    Monomial(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn products_stay_canonical() {
+        // x0 x2^-1 * x1^3 x2 = x0 x1^3
+        let a = Monomial::new([Variable::new(2, -1), Variable::new(0, 1)]);
+        let b = Monomial::variable(1, 3).with_variable(2, 1);
+
+        let product = a.product(&b);
+        assert_eq!(
+            product.variables(),
+            &[Variable::new(0, 1), Variable::new(1, 3)]
+        );
+        assert_eq!(product, a * b);
+        assert_eq!(product.exponent_of(&1), 3);
+        assert_eq!(product.exponent_of(&2), 0);
+
+        let mut inverse = product.clone();
+        inverse.invert();
+        assert!((product * inverse).is_constant());
+    }
+}
@@ -0,0 +1,268 @@
+//! Rendering expanded polynomials as human or machine readable formulas.
+//!
+//! The expander produces a flat sum of weighted monomials. [`Expression`] writes that sum
+//! out as plain text, LaTeX, a Rust expression or a Python expression. Variables are
+//! input indices and are named `x0..xn` unless labels are supplied.
+
+use std::{cmp::Ordering, fmt};
+
+use super::{PolyComponent, Polynomial};
+
+/// The syntax an [`Expression`] is rendered in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ExpressionFormat {
+    /// `2.5*x0^2*x1 - x1^-1`
+    #[default]
+    Text,
+    /// `2.5 x_{0}^{2} x_{1} - x_{1}^{-1}`
+    Latex,
+    /// `2.5_f32 * x0.powi(2) * x1 - x1.powi(-1)`
+    Rust,
+    /// `2.5 * x0**2 * x1 - x1**-1`
+    Python,
+}
+
+/// A polynomial over input indices, ready to be rendered in some [`ExpressionFormat`].
+///
+/// Terms are written in order of descending total degree, so the output is stable no
+/// matter what order the expander produced them in.
+///
+/// # Example
+///
+/// ```
+/// use polynomial_neat::burn_net::expander::{ExpressionFormat, Polynomial};
+///
+/// // 3x0^2 - x1
+/// let poly = Polynomial::new()
+///     .with_operation(3., 0, 2)
+///     .with_operation(-1., 1, 1);
+///
+/// assert_eq!(poly.expression(ExpressionFormat::Text).to_string(), "3*x0^2 - x1");
+/// assert_eq!(
+///     poly.expression(ExpressionFormat::Python)
+///         .with_labels(&["speed", "mass"])
+///         .to_string(),
+///     "3.0 * speed**2 - mass"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Expression<'a, L = String> {
+    polynomial: &'a Polynomial<usize>,
+    format: ExpressionFormat,
+    labels: &'a [L],
+}
+
+impl Polynomial<usize> {
+    /// Prepare this polynomial for rendering in the given format.
+    pub fn expression(&self, format: ExpressionFormat) -> Expression<'_> {
+        Expression {
+            polynomial: self,
+            format,
+            labels: &[],
+        }
+    }
+}
+
+impl<'a, L: AsRef<str>> Expression<'a, L> {
+    /// Name variables with the given labels instead of `x0..xn`.
+    ///
+    /// Any variable without a corresponding label falls back to `x{index}`.
+    pub fn with_labels<N: AsRef<str>>(self, labels: &'a [N]) -> Expression<'a, N> {
+        Expression {
+            polynomial: self.polynomial,
+            format: self.format,
+            labels,
+        }
+    }
+
+    pub fn format(&self) -> ExpressionFormat {
+        self.format
+    }
+
+    fn write_variable(&self, f: &mut fmt::Formatter<'_>, var: usize) -> fmt::Result {
+        match self.labels.get(var) {
+            Some(label) => write!(f, "{}", label.as_ref()),
+            None => match self.format {
+                ExpressionFormat::Latex => write!(f, "x_{{{var}}}"),
+                _ => write!(f, "x{var}"),
+            },
+        }
+    }
+
+    fn write_number(&self, f: &mut fmt::Formatter<'_>, value: f32) -> fmt::Result {
+        match self.format {
+            ExpressionFormat::Text | ExpressionFormat::Latex => {
+                if value.is_infinite() && self.format == ExpressionFormat::Latex {
+                    write!(f, "\\infty")
+                } else {
+                    write!(f, "{value}")
+                }
+            }
+            ExpressionFormat::Rust => {
+                if value.is_nan() {
+                    write!(f, "f32::NAN")
+                } else if value.is_infinite() {
+                    write!(f, "f32::INFINITY")
+                } else {
+                    write!(f, "{value:?}_f32")
+                }
+            }
+            ExpressionFormat::Python => {
+                if value.is_nan() {
+                    write!(f, "float('nan')")
+                } else if value.is_infinite() {
+                    write!(f, "float('inf')")
+                } else {
+                    write!(f, "{value:?}")
+                }
+            }
+        }
+    }
+
+    /// Writes a single term without its sign.
+    fn write_term(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        component: &PolyComponent<usize>,
+    ) -> fmt::Result {
+        let weight = component.weight().abs();
+        let operands = component.operands();
+
+        let separator = match self.format {
+            ExpressionFormat::Text => "*",
+            ExpressionFormat::Latex => " ",
+            ExpressionFormat::Rust | ExpressionFormat::Python => " * ",
+        };
+
+        let mut first = true;
+        if operands.is_empty() || weight != 1. {
+            self.write_number(f, weight)?;
+            first = false;
+        }
+
+        for operand in operands {
+            if !first {
+                write!(f, "{separator}")?;
+            }
+            first = false;
+            self.write_variable(f, *operand.var())?;
+            let exponent = operand.exponent();
+            if exponent == 1 {
+                continue;
+            }
+            match self.format {
+                ExpressionFormat::Text => write!(f, "^{exponent}")?,
+                ExpressionFormat::Latex => write!(f, "^{{{exponent}}}")?,
+                ExpressionFormat::Rust => write!(f, ".powi({exponent})")?,
+                ExpressionFormat::Python => write!(f, "**{exponent}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn degree(component: &PolyComponent<usize>) -> i32 {
+    component.operands().iter().map(|op| op.exponent()).sum()
+}
+
+impl<L: AsRef<str>> fmt::Display for Expression<'_, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut components: Vec<&PolyComponent<usize>> = self
+            .polynomial
+            .components()
+            .iter()
+            .filter(|component| component.weight() != 0.)
+            .collect();
+
+        if components.is_empty() {
+            return match self.format {
+                ExpressionFormat::Rust => write!(f, "0.0_f32"),
+                ExpressionFormat::Python => write!(f, "0.0"),
+                _ => write!(f, "0"),
+            };
+        }
+
+        components.sort_by(|a, b| match degree(b).cmp(&degree(a)) {
+            Ordering::Equal => a.operands().cmp(b.operands()),
+            ordering => ordering,
+        });
+
+        for (i, component) in components.into_iter().enumerate() {
+            let negative = component.weight().is_sign_negative();
+            match (i, negative) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            self.write_term(f, component)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // 0.5x0^2x1 - 3x1^-1 + 2
+    fn polynomial() -> Polynomial<usize> {
+        Polynomial::new()
+            .with_operation(2., 0, 0)
+            .with_operation(-3., 1, -1)
+            .with_polycomponent(
+                PolyComponent::new()
+                    .with_weight(0.5)
+                    .with_operand(0, 2)
+                    .with_operand(1, 1),
+            )
+    }
+
+    #[test]
+    fn render_text() {
+        assert_eq!(
+            polynomial().expression(ExpressionFormat::Text).to_string(),
+            "0.5*x0^2*x1 + 2 - 3*x1^-1"
+        );
+    }
+
+    #[test]
+    fn render_latex() {
+        assert_eq!(
+            polynomial().expression(ExpressionFormat::Latex).to_string(),
+            "0.5 x_{0}^{2} x_{1} + 2 - 3 x_{1}^{-1}"
+        );
+    }
+
+    #[test]
+    fn render_rust() {
+        assert_eq!(
+            polynomial().expression(ExpressionFormat::Rust).to_string(),
+            "0.5_f32 * x0.powi(2) * x1 + 2.0_f32 - 3.0_f32 * x1.powi(-1)"
+        );
+    }
+
+    #[test]
+    fn render_python_with_labels() {
+        assert_eq!(
+            polynomial()
+                .expression(ExpressionFormat::Python)
+                .with_labels(&["a"])
+                .to_string(),
+            "0.5 * a**2 * x1 + 2.0 - 3.0 * x1**-1"
+        );
+    }
+
+    #[test]
+    fn render_empty() {
+        let empty = Polynomial::<usize>::new();
+        assert_eq!(empty.expression(ExpressionFormat::Text).to_string(), "0");
+        assert_eq!(
+            empty.expression(ExpressionFormat::Rust).to_string(),
+            "0.0_f32"
+        );
+    }
+}
@@ -3,9 +3,10 @@ use std::fmt;
 use crate::burn_net::expander::Variable;
 
 use super::{PolyComponent, Polynomial};
+use crate::error::PolyNeatError;
 use pretty_assertions::{assert_eq, assert_ne};
 
-#[derive(Clone, Copy, PartialOrd, Ord, Debug, PartialEq, Default, Eq)]
+#[derive(Clone, Copy, PartialOrd, Ord, Debug, PartialEq, Default, Eq, Hash)]
 struct X;
 impl fmt::Display for X {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -158,7 +159,7 @@ pub fn exponentiate_complex_polynomial() {
     assert_eq!(components[6], PolyComponent::simple(1., X, 6),);
 }
 
-#[derive(Clone, Copy, PartialOrd, Ord, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialOrd, Ord, Debug, PartialEq, Eq, Hash)]
 enum V {
     X,
     Y,
@@ -221,17 +222,25 @@ pub fn neg_exponentiate_binomial() {
         .with_operation(1., X, 2)
         .with_operation(1., X, 1);
 
-    //2 * (f(x))^-2
+    //2 * (f(x))^-2 has no polynomial form
     let mut flattened = Polynomial::default();
-    flattened.expand(binomial, 2., -2).sort_by_exponent(X);
+    assert_eq!(
+        flattened.try_expand(binomial, 2., -2).err(),
+        Some(PolyNeatError::NotAPolynomial { terms: 2 })
+    );
+    assert!(flattened.components().is_empty());
+}
 
-    let components = flattened.components();
+#[test]
+pub fn neg_exponentiate_weighted_monomial() {
+    //2x^2
+    let monome = Polynomial::default().with_operation(2., X, 2);
 
-    assert!(components.len() == 3);
+    //3 * (f(x))^-2 = 3 / (4x^4)
+    let mut flattened = Polynomial::default();
+    flattened.expand(monome, 3., -2);
 
-    assert_eq!(components[0], PolyComponent::simple(2., X, -4));
-    assert_eq!(components[1], PolyComponent::simple(4., X, -3));
-    assert_eq!(components[2], PolyComponent::simple(2., X, -2));
+    assert_eq!(flattened.components(), [PolyComponent::simple(0.75, X, -4)]);
 }
 
 #[test]
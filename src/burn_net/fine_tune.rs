@@ -0,0 +1,523 @@
+//! Gradient-based fine-tuning of connection weights.
+//!
+//! Mutation is good at finding structure but slow at settling real-valued weights.
+//! [`fine_tune`] runs a short local search on a user dataset: it evaluates the network
+//! neuron by neuron on burn's [`Autodiff`] backend, so gradients flow through the
+//! network structure itself rather than through the expanded polynomial coefficients,
+//! and then takes plain gradient descent steps on the mean squared error.
+//!
+//! The tuned weights are either written back into the genome ([`Inheritance::Lamarckian`])
+//! or only reported so they can inform fitness ([`Inheritance::Baldwinian`]).
+//!
+//! # Example
+//!
+//! ```
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::burn_net::fine_tune::{FineTuneConfig, Inheritance};
+//!
+//! let mut topology =
+//!     PolyNetworkTopology::new_thoroughly_connected(1, 1, MutationChances::none(), &mut rand::rng());
+//!
+//! // y = 2x
+//! let inputs = [[0.0], [0.5], [1.0], [1.5]];
+//! let targets = [[0.0], [1.0], [2.0], [3.0]];
+//!
+//! let config = FineTuneConfig::default()
+//!     .with_epochs(50)
+//!     .with_inheritance(Inheritance::Lamarckian);
+//! let result = topology.fine_tune(&inputs, &targets, &config)?;
+//! assert!(result.final_loss <= result.initial_loss || !result.initial_loss.is_finite());
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use burn::{
+    backend::{Autodiff, NdArray, ndarray::NdArrayDevice},
+    prelude::*,
+    tensor::ElementConversion,
+};
+use uuid::Uuid;
+
+use crate::{
+    core::validation::PredictError,
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
+
+type TuneBackend = Autodiff<NdArray>;
+
+/// What happens to the tuned weights once fine-tuning finishes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Inheritance {
+    /// Tuned weights are written back into the genome and passed on to offspring.
+    #[default]
+    Lamarckian,
+    /// The genome is left untouched. Only the tuned loss is reported, so learning can
+    /// shape fitness without being inherited.
+    Baldwinian,
+}
+
+/// Settings for [`fine_tune`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FineTuneConfig {
+    learning_rate: f32,
+    epochs: usize,
+    inheritance: Inheritance,
+}
+
+impl Default for FineTuneConfig {
+    fn default() -> Self {
+        Self {
+            learning_rate: 0.01,
+            epochs: 100,
+            inheritance: Inheritance::default(),
+        }
+    }
+}
+
+impl FineTuneConfig {
+    /// Step size of each gradient descent update.
+    pub fn with_learning_rate(mut self, learning_rate: f32) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    /// Number of full-batch gradient descent steps.
+    pub fn with_epochs(mut self, epochs: usize) -> Self {
+        self.epochs = epochs;
+        self
+    }
+
+    /// Whether the tuned weights are written back into the genome.
+    pub fn with_inheritance(mut self, inheritance: Inheritance) -> Self {
+        self.inheritance = inheritance;
+        self
+    }
+
+    pub fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    pub fn epochs(&self) -> usize {
+        self.epochs
+    }
+
+    pub fn inheritance(&self) -> Inheritance {
+        self.inheritance
+    }
+}
+
+/// Outcome of a [`fine_tune`] run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FineTuneResult {
+    /// Mean squared error before any update.
+    pub initial_loss: f32,
+    /// Lowest mean squared error seen during the run.
+    pub final_loss: f32,
+    /// The weights that achieved `final_loss`, one per live connection in
+    /// topological order.
+    pub weights: Vec<f32>,
+}
+
+/// A live connection of the topology, addressed by its position in a neuron's inputs.
+struct Connection {
+    neuron: Arc<RwLock<PolyNeuronTopology>>,
+    input_index: usize,
+}
+
+/// The input column and output position of every neuron that has one.
+struct Layout {
+    input_columns: HashMap<Uuid, usize>,
+    outputs: Vec<Uuid>,
+}
+
+impl Layout {
+    fn of(topology: &PolyNetworkTopology) -> Result<Self> {
+        let mut input_columns = HashMap::new();
+        let mut outputs = Vec::new();
+        for neuron in topology.neurons() {
+            let neuron = neuron.read()?;
+            if neuron.is_input() {
+                input_columns.insert(neuron.id(), input_columns.len());
+            } else if neuron.is_output() {
+                outputs.push(neuron.id());
+            }
+        }
+        Ok(Self {
+            input_columns,
+            outputs,
+        })
+    }
+}
+
+/// Fine-tune the connection weights of `topology` on a dataset by gradient descent.
+///
+/// Each row of `inputs` is one sample, ordered like the arguments of `predict`, and the
+/// matching row of `targets` holds the expected outputs. Training stops early if the
+/// loss or a gradient becomes non-finite; the best weights seen are kept either way.
+///
+/// # Errors
+///
+/// Fails with [`PolyNeatError::RowCount`] if `inputs` and `targets` have a different
+/// number of rows, with [`PredictError::InputLength`] or
+/// [`PolyNeatError::TargetLength`] if a row doesn't have one value per input or output,
+/// and with [`PolyNeatError::PoisonedLock`] if a neuron lock is poisoned.
+pub fn fine_tune<I, T>(
+    topology: &mut PolyNetworkTopology,
+    inputs: &[I],
+    targets: &[T],
+    config: &FineTuneConfig,
+) -> Result<FineTuneResult>
+where
+    I: AsRef<[f32]>,
+    T: AsRef<[f32]>,
+{
+    if inputs.len() != targets.len() {
+        return Err(PolyNeatError::RowCount {
+            inputs: inputs.len(),
+            targets: targets.len(),
+        });
+    }
+
+    let device = NdArrayDevice::default();
+    let order = topology.try_topological_order()?;
+    let layout = Layout::of(topology)?;
+
+    let mut connections = Vec::new();
+    let mut initial_weights = Vec::new();
+    for neuron in order.iter() {
+        let read = neuron.read()?;
+        let Some(props) = read.props() else {
+            continue;
+        };
+        for (input_index, input) in props.inputs().iter().enumerate() {
+            if input.neuron().is_none() {
+                continue;
+            }
+            connections.push(Connection {
+                neuron: Arc::clone(neuron),
+                input_index,
+            });
+            initial_weights.push(input.weight());
+        }
+    }
+
+    let batch = inputs.len();
+    let (x, y) = dataset_tensors(
+        inputs,
+        targets,
+        layout.input_columns.len(),
+        layout.outputs.len(),
+        &device,
+    )?;
+
+    let mut weights: Tensor<TuneBackend, 1> = Tensor::from_data(
+        TensorData::new(initial_weights.clone(), [initial_weights.len()]),
+        &device,
+    )
+    .require_grad();
+
+    let mut best_loss = f32::INFINITY;
+    let mut best_weights = initial_weights;
+    let mut initial_loss = f32::NAN;
+
+    for epoch in 0..=config.epochs {
+        let predictions = forward(&layout, &order, &weights, x.clone(), batch, &device)?;
+        let loss = (predictions - y.clone()).powf_scalar(2.).mean();
+        let loss_value: f32 = loss.clone().into_scalar().elem();
+
+        if epoch == 0 {
+            initial_loss = loss_value;
+        }
+        if loss_value.is_finite() && loss_value < best_loss {
+            best_loss = loss_value;
+            best_weights = weights.to_data().to_vec::<f32>().unwrap();
+        }
+        if epoch == config.epochs || !loss_value.is_finite() || connections.is_empty() {
+            break;
+        }
+
+        let gradients = loss.backward();
+        let Some(gradient) = weights.grad(&gradients) else {
+            break;
+        };
+        let gradient_values = gradient.to_data().to_vec::<f32>().unwrap();
+        if gradient_values.iter().any(|g| !g.is_finite()) {
+            break;
+        }
+
+        let updated = weights.inner() - gradient.mul_scalar(config.learning_rate);
+        weights = Tensor::from_inner(updated).require_grad();
+    }
+
+    if !best_loss.is_finite() {
+        best_loss = initial_loss;
+    }
+
+    if config.inheritance == Inheritance::Lamarckian {
+        for (connection, weight) in connections.iter().zip(best_weights.iter()) {
+            let mut neuron = connection.neuron.write()?;
+            if let Some(props) = neuron.props_mut() {
+                props.inputs_mut()[connection.input_index].set_weight(*weight);
+            }
+        }
+    }
+
+    Ok(FineTuneResult {
+        initial_loss,
+        final_loss: best_loss,
+        weights: best_weights,
+    })
+}
+
+impl PolyNetworkTopology {
+    /// Fine-tune this topology's connection weights on a dataset.
+    ///
+    /// See [`fine_tune`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the dataset doesn't fit the network or a neuron lock is poisoned.
+    pub fn fine_tune<I, T>(
+        &mut self,
+        inputs: &[I],
+        targets: &[T],
+        config: &FineTuneConfig,
+    ) -> Result<FineTuneResult>
+    where
+        I: AsRef<[f32]>,
+        T: AsRef<[f32]>,
+    {
+        fine_tune(self, inputs, targets, config)
+    }
+}
+
+/// Builds `[batch, num_inputs]` and `[batch, num_outputs]` tensors.
+///
+/// Fails if a row doesn't have exactly `num_inputs` or `num_outputs` values.
+fn dataset_tensors<I, T>(
+    inputs: &[I],
+    targets: &[T],
+    num_inputs: usize,
+    num_outputs: usize,
+    device: &NdArrayDevice,
+) -> Result<(Tensor<TuneBackend, 2>, Tensor<TuneBackend, 2>)>
+where
+    I: AsRef<[f32]>,
+    T: AsRef<[f32]>,
+{
+    for row in inputs.iter().map(AsRef::as_ref) {
+        if row.len() != num_inputs {
+            return Err(PredictError::InputLength {
+                expected: num_inputs,
+                actual: row.len(),
+            }
+            .into());
+        }
+    }
+    for (index, row) in targets.iter().map(AsRef::as_ref).enumerate() {
+        if row.len() != num_outputs {
+            return Err(PolyNeatError::TargetLength {
+                row: index,
+                expected: num_outputs,
+                actual: row.len(),
+            });
+        }
+    }
+
+    let flatten = |rows: Vec<&[f32]>| rows.concat();
+    let x = TensorData::new(
+        flatten(inputs.iter().map(AsRef::as_ref).collect()),
+        [inputs.len(), num_inputs],
+    );
+    let y = TensorData::new(
+        flatten(targets.iter().map(AsRef::as_ref).collect()),
+        [targets.len(), num_outputs],
+    );
+    Ok((Tensor::from_data(x, device), Tensor::from_data(y, device)))
+}
+
+/// Evaluates the network on a whole batch, returning `[batch, num_outputs]`.
+///
+/// `weights` holds one entry per live connection, in the same order they were collected.
+fn forward(
+    layout: &Layout,
+    order: &[Arc<RwLock<PolyNeuronTopology>>],
+    weights: &Tensor<TuneBackend, 1>,
+    x: Tensor<TuneBackend, 2>,
+    batch: usize,
+    device: &NdArrayDevice,
+) -> Result<Tensor<TuneBackend, 2>> {
+    let mut values: HashMap<Uuid, Tensor<TuneBackend, 1>> = HashMap::with_capacity(order.len());
+    let mut weight_index = 0;
+
+    for neuron in order {
+        let neuron = neuron.read()?;
+        let value = match neuron.props() {
+            None => match layout.input_columns.get(&neuron.id()) {
+                Some(column) => x
+                    .clone()
+                    .slice([0..batch, *column..*column + 1])
+                    .reshape([batch]),
+                // inputs that aren't part of the network never receive a value
+                None => Tensor::zeros([batch], device),
+            },
+            Some(props) => {
+                let mut sum: Tensor<TuneBackend, 1> = Tensor::zeros([batch], device);
+                for input in props.inputs() {
+                    let Some(input_neuron) = input.neuron() else {
+                        continue;
+                    };
+                    let weight = weights.clone().narrow(0, weight_index, 1);
+                    weight_index += 1;
+
+                    let term = if input.exponent() == 0 {
+                        Tensor::ones([batch], device) * weight
+                    } else {
+                        let source = values[&input_neuron.read()?.id()].clone();
+                        source.powi_scalar(input.exponent()) * weight
+                    };
+                    sum = sum + term;
+                }
+                sum
+            }
+        };
+        values.insert(neuron.id(), value);
+    }
+
+    if layout.outputs.is_empty() {
+        return Ok(Tensor::zeros([batch, 0], device));
+    }
+
+    let columns = layout
+        .outputs
+        .iter()
+        .map(|id| values[id].clone().reshape([batch, 1]))
+        .collect::<Vec<_>>();
+    Ok(Tensor::cat(columns, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// y = w * x, starting at w = 0.5
+    fn linear_topology() -> PolyNetworkTopology {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&x, 0.5, 1)],
+        ));
+        PolyNetworkTopology::from_raw_parts(vec![x, output], MutationChances::none())
+    }
+
+    fn output_weight(topology: &PolyNetworkTopology) -> f32 {
+        let output = topology.neurons()[1].read().unwrap();
+        output.props().unwrap().inputs()[0].weight()
+    }
+
+    const INPUTS: [[f32; 1]; 4] = [[0.], [0.5], [1.], [1.5]];
+    const TARGETS: [[f32; 1]; 4] = [[0.], [1.], [2.], [3.]];
+
+    #[test]
+    fn lamarckian_writes_weights_back() {
+        let mut topology = linear_topology();
+        let config = FineTuneConfig::default()
+            .with_learning_rate(0.1)
+            .with_epochs(200);
+
+        let result = topology.fine_tune(&INPUTS, &TARGETS, &config).unwrap();
+
+        assert!(result.final_loss < result.initial_loss);
+        assert!(result.final_loss < 1e-4, "loss: {}", result.final_loss);
+        assert!((output_weight(&topology) - 2.).abs() < 1e-2);
+        assert_eq!(result.weights, vec![output_weight(&topology)]);
+    }
+
+    #[test]
+    fn baldwinian_leaves_genome_untouched() {
+        let mut topology = linear_topology();
+        let config = FineTuneConfig::default()
+            .with_learning_rate(0.1)
+            .with_inheritance(Inheritance::Baldwinian);
+
+        let result = topology.fine_tune(&INPUTS, &TARGETS, &config).unwrap();
+
+        assert!(result.final_loss < result.initial_loss);
+        assert_eq!(output_weight(&topology), 0.5);
+    }
+
+    #[test]
+    fn gradients_flow_through_hidden_neurons() {
+        // y = w2 * (w1 * x)^2, fit to y = x^2
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&x, 0.5, 1)],
+        ));
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&hidden, 0.5, 2)],
+        ));
+        let mut topology =
+            PolyNetworkTopology::from_raw_parts(vec![x, hidden, output], MutationChances::none());
+
+        let targets = INPUTS.map(|[x]| [x * x]);
+        let config = FineTuneConfig::default()
+            .with_learning_rate(0.05)
+            .with_epochs(500);
+        let result = topology.fine_tune(&INPUTS, &targets, &config).unwrap();
+
+        assert_eq!(result.weights.len(), 2);
+        assert!(result.final_loss < 1e-3, "loss: {}", result.final_loss);
+
+        let network = topology.to_simple_network();
+        let prediction: Vec<f32> = network.predict(&[2.]).collect();
+        assert!((prediction[0] - 4.).abs() < 0.1);
+    }
+
+    #[test]
+    fn mismatched_datasets_are_errors() {
+        let mut topology = linear_topology();
+        let config = FineTuneConfig::default();
+
+        let error = topology
+            .fine_tune(&INPUTS, &TARGETS[..3], &config)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            PolyNeatError::RowCount {
+                inputs: 4,
+                targets: 3
+            }
+        );
+
+        let error = topology
+            .fine_tune(&[[0., 1.]], &[[1.]], &config)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            PolyNeatError::Predict(PredictError::InputLength {
+                expected: 1,
+                actual: 2
+            })
+        );
+
+        let error = topology
+            .fine_tune(&[[0.], [1.]], &[vec![1.], vec![]], &config)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            PolyNeatError::TargetLength {
+                row: 1,
+                expected: 1,
+                actual: 0
+            }
+        );
+        assert_eq!(output_weight(&topology), 0.5);
+    }
+}
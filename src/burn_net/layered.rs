@@ -0,0 +1,515 @@
+//! Evaluating a network level by level, without expanding it.
+//!
+//! [`BurnNetwork`](super::network::BurnNetwork) expands every output into a single
+//! polynomial, which is intractable for deep genomes (see
+//! [`ExpansionLimits`](super::expander::ExpansionLimits)). [`LayeredNetwork`] keeps the
+//! network structure instead. Neurons are grouped by their depth
+//! ([`PolyNetworkTopology::depths`]), and each level is evaluated for the whole batch at
+//! once:
+//!
+//! 1. gather the values of every connection's source neuron,
+//! 2. raise them to the connection's exponent, one `powi` per distinct exponent,
+//! 3. scale them by the connection weights and sum them into their target neurons.
+//!
+//! The cost grows with the number of connections rather than the number of expanded
+//! terms. Since the per-neuron sums are materialised, anything that can't be expanded
+//! into a polynomial can be applied to them: [`LayeredNetwork::with_activation`] and
+//! [`LayeredNetwork::with_bias`] are the hooks for that.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::burn_net::layered::LayeredNetwork;
+//! use burn::backend::NdArray;
+//!
+//! let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+//! let device = burn::backend::ndarray::NdArrayDevice::default();
+//! let network = LayeredNetwork::<NdArray>::from_topology(&topology, device)?;
+//!
+//! let outputs = network.predict_batch_rows(&[[0.0, 1.0], [1.0, 0.0]]);
+//! assert_eq!(outputs.dims(), [1, 2]);
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use burn::prelude::*;
+use uuid::Uuid;
+
+use super::apply_numeric_policy;
+use crate::{
+    core::{
+        numeric::{NumericFlag, NumericPolicy},
+        validation::{InputValidation, PredictError},
+    },
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
+
+/// A function applied to the summed inputs of every non-input neuron.
+///
+/// It receives a `[neurons, batch]` tensor holding one level of the network.
+pub type Activation<B> = Arc<dyn Fn(Tensor<B, 2>) -> Tensor<B, 2> + Send + Sync>;
+
+/// A network evaluated one depth level at a time.
+pub struct LayeredNetwork<B: Backend> {
+    // listed inputs come first, in input order, followed by inputs that are no longer
+    // listed but still referenced. Those always read as 0.
+    num_inputs: usize,
+    num_unlisted_inputs: usize,
+    levels: Vec<Level<B>>,
+    // slot of each output, in topology order
+    output_slots: Vec<usize>,
+    // level and row of every non-input neuron, used to place biases
+    positions: HashMap<Uuid, (usize, usize)>,
+    activation: Option<Activation<B>>,
+    disconnected_outputs: Vec<usize>,
+    numeric_policy: NumericPolicy,
+    // shared with the topology this was built from
+    numeric_flag: Arc<NumericFlag>,
+    device: B::Device,
+}
+
+/// All neurons of one depth, with the connections feeding them.
+struct Level<B: Backend> {
+    // sum of the weights of exponent 0 connections plus the bias, per neuron
+    constants: Vec<f32>,
+    constant_tensor: Tensor<B, 2>,
+    groups: Vec<ConnectionGroup<B>>,
+}
+
+/// The connections into one level that share an exponent.
+struct ConnectionGroup<B: Backend> {
+    exponent: i32,
+    // slots of the source neurons
+    sources: Tensor<B, 1, Int>,
+    // rows within the level of the target neurons
+    targets: Tensor<B, 1, Int>,
+    // [connections, 1]
+    weights: Tensor<B, 2>,
+}
+
+/// A [`ConnectionGroup`] while it is being collected on the host.
+#[derive(Default)]
+struct PendingGroup {
+    sources: Vec<i32>,
+    targets: Vec<i32>,
+    weights: Vec<f32>,
+}
+
+impl<B: Backend> LayeredNetwork<B> {
+    /// Build a layered network from a topology.
+    ///
+    /// Every reachable neuron is placed in the level of its depth. Input neurons that are
+    /// no longer listed in the topology read as 0, matching [`SimplePolyNetwork`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if a neuron lock is poisoned.
+    pub fn from_topology(topology: &PolyNetworkTopology, device: B::Device) -> Result<Self> {
+        let depths = topology.try_depths()?;
+        let order = topology.try_topological_order()?;
+
+        let mut listed_inputs = Vec::new();
+        let mut output_ids = Vec::new();
+        for neuron in topology.neurons() {
+            let neuron = neuron.read()?;
+            if neuron.is_input() {
+                listed_inputs.push(neuron.id());
+            } else if neuron.is_output() {
+                output_ids.push(neuron.id());
+            }
+        }
+
+        // level 0 holds the inputs, listed ones first
+        let mut slots: HashMap<Uuid, usize> = HashMap::with_capacity(order.len());
+        for id in listed_inputs.iter() {
+            slots.insert(*id, slots.len());
+        }
+        let mut by_depth: BTreeMap<usize, Vec<Uuid>> = BTreeMap::new();
+        for neuron in order.iter() {
+            let id = neuron.read()?.id();
+            match depths.get(&id).copied().unwrap_or(0) {
+                0 => {
+                    if !slots.contains_key(&id) {
+                        slots.insert(id, slots.len());
+                    }
+                }
+                depth => by_depth.entry(depth).or_default().push(id),
+            }
+        }
+        let num_unlisted_inputs = slots.len() - listed_inputs.len();
+
+        let neurons: HashMap<Uuid, _> = order
+            .iter()
+            .map(|neuron| Ok((neuron.read()?.id(), Arc::clone(neuron))))
+            .collect::<Result<_>>()?;
+        let slot_of = |id: Uuid, slots: &HashMap<Uuid, usize>| {
+            slots
+                .get(&id)
+                .copied()
+                .ok_or_else(|| PolyNeatError::UnknownNeuron { id: id.to_string() })
+        };
+
+        let mut levels = Vec::with_capacity(by_depth.len());
+        let mut positions = HashMap::new();
+        for ids in by_depth.into_values() {
+            let mut constants = vec![0.; ids.len()];
+            let mut connections: BTreeMap<i32, PendingGroup> = BTreeMap::new();
+
+            for (row, id) in ids.iter().enumerate() {
+                positions.insert(*id, (levels.len(), row));
+                let neuron = neurons[id].read()?;
+                let Some(props) = neuron.props() else {
+                    continue;
+                };
+                for input in props.inputs() {
+                    let Some(source) = input.neuron() else {
+                        continue;
+                    };
+                    if input.exponent() == 0 {
+                        // x^0 = 1, so the source doesn't matter
+                        constants[row] += input.weight();
+                        continue;
+                    }
+                    let source = slot_of(source.read()?.id(), &slots)?;
+                    let group = connections.entry(input.exponent()).or_default();
+                    group.sources.push(source as i32);
+                    group.targets.push(row as i32);
+                    group.weights.push(input.weight());
+                }
+            }
+
+            // the level's neurons get slots only after their connections were resolved
+            for id in ids.iter() {
+                slots.insert(*id, slots.len());
+            }
+
+            let groups = connections
+                .into_iter()
+                .map(|(exponent, group)| ConnectionGroup {
+                    exponent,
+                    sources: Tensor::from_data(TensorData::from(group.sources.as_slice()), &device),
+                    targets: Tensor::from_data(TensorData::from(group.targets.as_slice()), &device),
+                    weights: Tensor::from_data(
+                        TensorData::new(group.weights.clone(), [group.weights.len(), 1]),
+                        &device,
+                    ),
+                })
+                .collect();
+
+            levels.push(Level {
+                constant_tensor: constant_tensor(&constants, &device),
+                constants,
+                groups,
+            });
+        }
+
+        let output_slots = output_ids
+            .into_iter()
+            .map(|id| slot_of(id, &slots))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            num_inputs: listed_inputs.len(),
+            num_unlisted_inputs,
+            levels,
+            output_slots,
+            positions,
+            activation: None,
+            disconnected_outputs: topology.disconnected_outputs()?,
+            numeric_policy: NumericPolicy::default(),
+            numeric_flag: Arc::clone(topology.numeric_flag()),
+            device,
+        })
+    }
+
+    /// Set how non-finite values are handled. See [`NumericPolicy`].
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Self {
+        self.numeric_policy = policy;
+        self
+    }
+
+    /// Apply `activation` to the summed inputs of every hidden and output neuron.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use polynomial_neat::prelude::*;
+    /// # use polynomial_neat::burn_net::layered::LayeredNetwork;
+    /// # use burn::backend::NdArray;
+    /// use std::sync::Arc;
+    /// use burn::tensor::activation::tanh;
+    ///
+    /// # let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+    /// # let device = burn::backend::ndarray::NdArrayDevice::default();
+    /// let network = LayeredNetwork::<NdArray>::from_topology(&topology, device)?
+    ///     .with_activation(Arc::new(tanh));
+    ///
+    /// assert!(network.predict(&[3.0, -4.0])[0].abs() <= 1.0);
+    /// # Ok::<(), PolyNeatError>(())
+    /// ```
+    pub fn with_activation(mut self, activation: Activation<B>) -> Self {
+        self.activation = Some(activation);
+        self
+    }
+
+    /// Add a constant to the summed inputs of a neuron, before the activation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PolyNeatError::UnknownNeuron`] if `neuron` isn't a hidden or output
+    /// neuron of this network.
+    pub fn with_bias(mut self, neuron: Uuid, bias: f32) -> Result<Self> {
+        let Some((level, row)) = self.positions.get(&neuron).copied() else {
+            return Err(PolyNeatError::UnknownNeuron {
+                id: neuron.to_string(),
+            });
+        };
+        let level = &mut self.levels[level];
+        level.constants[row] += bias;
+        level.constant_tensor = constant_tensor(&level.constants, &self.device);
+        Ok(self)
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.output_slots.len()
+    }
+
+    /// The number of levels evaluated after the inputs, i.e. the depth of the network.
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Perform a forward pass for a single sample.
+    ///
+    /// # Note
+    /// Like [`SimplePolyNetwork::predict`], extra inputs are ignored and missing inputs
+    /// are treated as 0.
+    pub fn predict(&self, inputs: &[f32]) -> Vec<f32> {
+        self.predict_batch_rows(&[inputs])
+            .to_data()
+            .to_vec::<f32>()
+            .unwrap()
+    }
+
+    /// Perform a forward pass after checking `inputs` against `validation`.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Predict`] describing the first failed check.
+    pub fn try_predict(&self, inputs: &[f32], validation: &InputValidation) -> Result<Vec<f32>> {
+        validation.validate(inputs, self.num_inputs, &self.disconnected_outputs)?;
+        Ok(self.predict(inputs))
+    }
+
+    /// Evaluate the network on a `[batch, num_inputs]` tensor of samples.
+    ///
+    /// Missing input columns are treated as 0, extra columns are ignored.
+    ///
+    /// # Returns
+    ///
+    /// A `[num_outputs, batch]` tensor: column `b` holds the outputs for sample `b`.
+    pub fn predict_batch(&self, inputs: Tensor<B, 2>) -> Tensor<B, 2> {
+        let [batch, columns] = inputs.dims();
+        let inputs = inputs.to_device(&self.device).transpose();
+
+        let mut rows = Vec::with_capacity(2);
+        if columns > 0 && self.num_inputs > 0 {
+            rows.push(inputs.narrow(0, 0, columns.min(self.num_inputs)));
+        }
+        let missing = self.num_inputs.saturating_sub(columns) + self.num_unlisted_inputs;
+        if missing > 0 {
+            rows.push(Tensor::zeros([missing, batch], &self.device));
+        }
+
+        self.evaluate(rows, batch)
+    }
+
+    /// Evaluate the network on a batch of samples given as rows of input values.
+    ///
+    /// # Returns
+    ///
+    /// A `[num_outputs, batch]` tensor: column `b` holds the outputs for `inputs[b]`.
+    pub fn predict_batch_rows<R: AsRef<[f32]>>(&self, inputs: &[R]) -> Tensor<B, 2> {
+        let batch = inputs.len();
+        let num_slots = self.num_inputs + self.num_unlisted_inputs;
+
+        let mut values = vec![0.; num_slots * batch];
+        for (sample, row) in inputs.iter().enumerate() {
+            for (input, value) in row.as_ref().iter().take(self.num_inputs).enumerate() {
+                values[input * batch + sample] = *value;
+            }
+        }
+
+        let level = Tensor::from_data(TensorData::new(values, [num_slots, batch]), &self.device);
+        self.evaluate(vec![level], batch)
+    }
+
+    /// Evaluate every level on top of the input rows, then gather the outputs.
+    fn evaluate(&self, mut values: Vec<Tensor<B, 2>>, batch: usize) -> Tensor<B, 2> {
+        for level in self.levels.iter() {
+            let evaluated = Tensor::cat(values.clone(), 0);
+
+            let mut sums = level.constant_tensor.clone().repeat_dim(1, batch);
+            for group in level.groups.iter() {
+                let sources = evaluated.clone().select(0, group.sources.clone());
+                let powered = self.power(sources, group.exponent);
+                sums =
+                    sums.select_assign(0, group.targets.clone(), powered * group.weights.clone());
+            }
+
+            if let Some(activation) = self.activation.as_ref() {
+                sums = activation(sums);
+            }
+            values.push(sums);
+        }
+
+        if self.output_slots.is_empty() {
+            return Tensor::zeros([0, batch], &self.device);
+        }
+
+        let indices: Vec<i32> = self.output_slots.iter().map(|slot| *slot as i32).collect();
+        let outputs = Tensor::cat(values, 0).select(
+            0,
+            Tensor::from_data(TensorData::from(indices.as_slice()), &self.device),
+        );
+
+        let (outputs, flagged) = apply_numeric_policy(&self.numeric_policy, outputs);
+        self.numeric_flag.raise(flagged.into_iter().sum());
+        outputs
+    }
+
+    fn power(&self, base: Tensor<B, 2>, exponent: i32) -> Tensor<B, 2> {
+        match (self.numeric_policy, exponent) {
+            (_, 1) => base,
+            (NumericPolicy::Epsilon(epsilon), exponent) if exponent < 0 => {
+                let zero = base.clone().equal_elem(0.);
+                base.mask_fill(zero, epsilon).powi_scalar(exponent)
+            }
+            (_, exponent) => base.powi_scalar(exponent),
+        }
+    }
+}
+
+fn constant_tensor<B: Backend>(constants: &[f32], device: &B::Device) -> Tensor<B, 2> {
+    Tensor::from_data(
+        TensorData::new(constants.to_vec(), [constants.len(), 1]),
+        device,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    type TestBackend = NdArray;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            let tolerance = 1e-3 * expected.abs().max(1.);
+            assert!(
+                actual == expected
+                    || (actual.is_nan() && expected.is_nan())
+                    || (actual - expected).abs() <= tolerance,
+                "{actual} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn evaluates_level_by_level() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+
+        // 3x + y
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 3., 1),
+                PolyInputTopology::downgrade(&y, 1., 1),
+            ],
+        ));
+        // (3x + y)^2 + 4 - x
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&hidden, 1., 2),
+                PolyInputTopology::downgrade(&x, 4., 0),
+                PolyInputTopology::downgrade(&x, -1., 1),
+            ],
+        ));
+        let output_id = output.read().unwrap().id();
+
+        let topology = PolyNetworkTopology::from_raw_parts(
+            vec![x, y, output, hidden],
+            MutationChances::none(),
+        );
+        let device = Default::default();
+        let network = LayeredNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
+
+        assert_eq!(network.num_levels(), 2);
+        assert_eq!(network.predict(&[1., 2.]), vec![28.]);
+        // missing inputs are 0, extra inputs are ignored
+        assert_eq!(network.predict(&[1.]), vec![12.]);
+        assert_eq!(network.predict(&[1., 2., 100.]), vec![28.]);
+
+        let network = network
+            .with_bias(output_id, 0.5)
+            .unwrap()
+            .with_activation(Arc::new(|sums: Tensor<TestBackend, 2>| sums * 2.));
+        // the hidden neuron is doubled before squaring, the output after the bias
+        assert_eq!(
+            network.predict(&[1., 2.]),
+            vec![2. * (100. + 4. - 1. + 0.5)]
+        );
+    }
+
+    #[test]
+    fn matches_compiled_network() {
+        let mut rng = StdRng::seed_from_u64(3939);
+        let device = Default::default();
+
+        for _ in 0..20 {
+            let mut topology = PolyNetworkTopology::new(3, 2, MutationChances::new(70), &mut rng);
+            for _ in 0..15 {
+                topology = topology.replicate(&mut rng);
+            }
+
+            let compiled = topology.to_compiled_network().unwrap();
+            let layered = LayeredNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
+
+            let samples = [[1., 0.5, -2.], [0.25, -1., 3.], [0., 0., 0.]];
+            let batch = layered.predict_batch_rows(&samples);
+            let flat: Vec<f32> = samples.iter().flatten().copied().collect();
+            let from_tensor = layered.predict_batch(Tensor::from_data(
+                TensorData::new(flat, [samples.len(), 3]),
+                &device,
+            ));
+
+            let batch = batch.to_data().to_vec::<f32>().unwrap();
+            let from_tensor = from_tensor.to_data().to_vec::<f32>().unwrap();
+            for (b, sample) in samples.iter().enumerate() {
+                let expected = compiled.predict(sample);
+                let column = |values: &[f32]| -> Vec<f32> {
+                    (0..expected.len())
+                        .map(|o| values[o * samples.len() + b])
+                        .collect()
+                };
+                assert_close(&layered.predict(sample), &expected);
+                assert_close(&column(&batch), &expected);
+                assert_close(&column(&from_tensor), &expected);
+            }
+        }
+    }
+}
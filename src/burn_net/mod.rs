@@ -4,58 +4,241 @@
 #![allow(unused_variables)]
 use std::sync::{Arc, RwLock};
 
-use crate::prelude::*;
+use crate::{
+    core::numeric::NumericPolicy,
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
 use burn::prelude::*;
-use expander::Polynomial;
+use expander::{ExpansionGuard, ExpansionLimits, PolyComponent, Polynomial};
 use fnv::FnvHashMap;
 use network::BurnNetwork;
 use uuid::Uuid;
 
 mod basis_prime;
 mod coeff;
-mod expander;
+pub mod expander;
+pub mod fine_tune;
+pub mod layered;
 pub mod network;
+pub mod population;
+
+pub use coeff::{CoefficientLayout, SPARSE_DENSITY_THRESHOLD};
 
 #[cfg(test)]
 mod tests;
 
-fn get_topology_polynomials(topology: &PolyNetworkTopology) -> Vec<Polynomial<Uuid>> {
-    let mut neurons = Vec::with_capacity(topology.neurons().len());
+/// Expand every output of a topology into its closed-form polynomial.
+///
+/// Variables are input indices: `0` is the first input neuron of the topology, in the
+/// same order inputs are passed to `predict`. Outputs are returned in topology order.
+///
+/// # Errors
+///
+/// Fails if a neuron lock is poisoned, or if an output depends on an input neuron that
+/// is no longer listed in the topology. Fails with [`PolyNeatError::NotAPolynomial`] if a
+/// sum of several terms is raised to a negative power: `1 / (2x)` expands to `0.5x^-1`,
+/// but `1 / (x + y)` has no polynomial form.
+///
+/// # Example
+///
+/// ```rust
+/// use polynomial_neat::prelude::*;
+/// use polynomial_neat::burn_net::{expander::ExpressionFormat, output_polynomials};
+///
+/// let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+///
+/// for polynomial in output_polynomials(&topology)? {
+///     println!("y = {}", polynomial.expression(ExpressionFormat::Latex));
+/// }
+/// # Ok::<(), PolyNeatError>(())
+/// ```
+pub fn output_polynomials(topology: &PolyNetworkTopology) -> Result<Vec<Polynomial<usize>>> {
+    output_polynomials_with_limits(topology, &ExpansionLimits::default())
+}
+
+/// [`output_polynomials`], giving up once the expansion exceeds `limits`.
+///
+/// The topology is checked against [`ExpansionLimits::check_estimate`] before any
+/// expansion work starts.
+///
+/// # Errors
+///
+/// Additionally fails with [`PolyNeatError::ExpansionLimit`] if a limit is hit.
+pub fn output_polynomials_with_limits(
+    topology: &PolyNetworkTopology,
+    limits: &ExpansionLimits,
+) -> Result<Vec<Polynomial<usize>>> {
+    let inputs = input_indices(topology)?;
+
+    expand_topology(topology, limits)?
+        .into_iter()
+        .map(|poly| poly.map_operands(&inputs))
+        .collect()
+}
+
+/// Maps the id of every input neuron to its position among the inputs.
+fn input_indices(topology: &PolyNetworkTopology) -> Result<FnvHashMap<Uuid, usize>> {
+    let mut indices = FnvHashMap::default();
+    for neuron in topology.neurons() {
+        let neuron = neuron.read()?;
+        if neuron.is_input() {
+            indices.insert(neuron.id(), indices.len());
+        }
+    }
+    Ok(indices)
+}
+
+impl PolyNetworkTopology {
+    /// Expand every output into its closed-form polynomial over the inputs.
+    ///
+    /// See [`output_polynomials`].
+    pub fn output_polynomials(&self) -> Result<Vec<Polynomial<usize>>> {
+        output_polynomials(self)
+    }
+}
+
+fn get_topology_polynomials(topology: &PolyNetworkTopology) -> Result<Vec<Polynomial<Uuid>>> {
+    expand_topology(topology, &ExpansionLimits::default())
+}
+
+/// Expand every output over input neuron ids, after checking the estimate against
+/// `limits`.
+fn expand_topology(
+    topology: &PolyNetworkTopology,
+    limits: &ExpansionLimits,
+) -> Result<Vec<Polynomial<Uuid>>> {
+    if limits != &ExpansionLimits::default() {
+        limits.check_estimate(&topology.estimate_expansion()?)?;
+    }
+
+    let guard = limits.start();
+    let mut cache = ExpansionCache::default();
+    let mut polynomials = Vec::with_capacity(topology.neurons().len());
 
-    for output in topology.neurons().iter().filter_map(|neuron| {
-        let neuron = neuron.read().unwrap();
+    for neuron in topology.neurons() {
+        let neuron = neuron.read()?;
         if neuron.is_output() {
-            Some(neuron)
-        } else {
-            None
+            let polynomial = cache.polynomial(&neuron, &guard)?;
+            polynomials.push(Polynomial::clone(&polynomial));
         }
-    }) {
-        let poly = create_polynomial(&output);
-        neurons.push(poly)
     }
 
-    neurons
+    Ok(polynomials)
 }
 
-fn create_polynomial(top: &PolyNeuronTopology) -> Polynomial<Uuid> {
-    let Some(props) = top.props() else {
-        //this is an input
-        return Polynomial::unit(top.id());
-    };
+/// The polynomials expanded so far by one [`expand_topology`] call.
+///
+/// A hidden neuron feeding several others is expanded once and shared, as is every power
+/// of it a connection asks for, so the cost follows the number of connections rather than
+/// the number of paths through the network.
+#[derive(Default)]
+struct ExpansionCache {
+    // the polynomial of each neuron, by id
+    neurons: FnvHashMap<Uuid, Arc<Polynomial<Uuid>>>,
+    // positive powers of those polynomials, by id and exponent
+    powers: FnvHashMap<(Uuid, u32), Arc<Polynomial<Uuid>>>,
+}
 
-    let mut running_polynomial = Polynomial::default();
-    for input in props.inputs() {
-        let Some(neuron) = input.neuron() else {
-            continue;
-        };
-        let Ok(neuron) = neuron.read() else {
-            panic!("can't read neuron")
+impl ExpansionCache {
+    fn polynomial(
+        &mut self,
+        top: &PolyNeuronTopology,
+        guard: &ExpansionGuard,
+    ) -> Result<Arc<Polynomial<Uuid>>> {
+        if let Some(polynomial) = self.neurons.get(&top.id()) {
+            return Ok(Arc::clone(polynomial));
+        }
+
+        let polynomial = match top.props() {
+            //this is an input
+            None => Polynomial::unit(top.id()),
+            Some(props) => {
+                let mut running_polynomial = Polynomial::default();
+                for input in props.inputs() {
+                    let Some(neuron) = input.neuron() else {
+                        continue;
+                    };
+                    if input.exponent() == 0 {
+                        // x^0 is 1, whatever x expands to
+                        running_polynomial
+                            .handle_polycomponent(PolyComponent::base(input.weight()));
+                        continue;
+                    }
+
+                    let neuron = neuron.read()?;
+                    if input.exponent() < 0 {
+                        // fail before raising a sum to a power it can't be inverted from
+                        self.polynomial(&neuron, guard)?.check_invertible()?;
+                    }
+                    let power = self.power(&neuron, input.exponent().unsigned_abs(), guard)?;
+                    running_polynomial.add_scaled(&power, input.weight(), input.exponent() < 0)?;
+                    guard.check(&running_polynomial)?;
+                }
+                running_polynomial
+            }
         };
 
-        let neuron_polynomial = create_polynomial(&neuron);
+        let polynomial = Arc::new(polynomial);
+        self.neurons.insert(top.id(), Arc::clone(&polynomial));
+        Ok(polynomial)
+    }
 
-        running_polynomial.expand(neuron_polynomial, input.weight(), input.exponent());
+    /// The polynomial of `top` raised to `exponent`. Only the requested power is cached,
+    /// not the ones it is built from.
+    fn power(
+        &mut self,
+        top: &PolyNeuronTopology,
+        exponent: u32,
+        guard: &ExpansionGuard,
+    ) -> Result<Arc<Polynomial<Uuid>>> {
+        if exponent == 1 {
+            return self.polynomial(top, guard);
+        }
+        if let Some(power) = self.powers.get(&(top.id(), exponent)) {
+            return Ok(Arc::clone(power));
+        }
+
+        let base = self.polynomial(top, guard)?;
+        let power = Arc::new(base.power_within(exponent, guard)?);
+        self.powers.insert((top.id(), exponent), Arc::clone(&power));
+        Ok(power)
     }
+}
 
-    running_polynomial
+/// Apply `policy` to a `[outputs, batch]` tensor of outputs.
+///
+/// # Returns
+/// The outputs, and under [`NumericPolicy::Sentinel`] the number of replaced values in
+/// each output row. The counts are empty under every other policy, so the tensor is only
+/// read back to the host when flags have to be raised.
+fn apply_numeric_policy<B: Backend>(
+    policy: &NumericPolicy,
+    outputs: Tensor<B, 2>,
+) -> (Tensor<B, 2>, Vec<usize>) {
+    match policy {
+        NumericPolicy::Propagate | NumericPolicy::Epsilon(_) => (outputs, Vec::new()),
+        NumericPolicy::Clamp => {
+            let nan = outputs.clone().is_nan();
+            (
+                outputs.mask_fill(nan, 0.).clamp(-f32::MAX, f32::MAX),
+                Vec::new(),
+            )
+        }
+        NumericPolicy::Sentinel(sentinel) => {
+            // x - x is NaN exactly when x isn't finite
+            let non_finite = (outputs.clone() - outputs.clone()).is_nan();
+            let counts = non_finite
+                .clone()
+                .float()
+                .sum_dim(1)
+                .to_data()
+                .to_vec::<f32>()
+                .unwrap()
+                .into_iter()
+                .map(|count| count as usize)
+                .collect();
+            (outputs.mask_fill(non_finite, *sentinel), counts)
+        }
+    }
 }
@@ -1,9 +1,19 @@
-use super::{basis_prime::BasisTemplate, coeff::Coefficients, get_topology_polynomials};
+use super::{
+    apply_numeric_policy,
+    basis_prime::BasisTemplate,
+    coeff::{CoefficientLayout, Coefficients},
+    expand_topology, input_indices,
+};
 use crate::{
     burn_net::{
         basis_prime::basis_from_poly_list,
-        expander::{Polynomial, Variable},
+        expander::{ExpansionLimits, Polynomial, Variable},
+    },
+    core::{
+        numeric::{NumericFlag, NumericPolicy},
+        validation::{InputValidation, PredictError},
     },
+    error::Result,
     prelude::*,
 };
 use burn::prelude::*;
@@ -12,7 +22,7 @@ use rayon::iter::{
     IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator,
     ParallelIterator as _,
 };
-use std::f32::consts::E;
+use std::{f32::consts::E, sync::Arc};
 use tracing::info;
 use uuid::Uuid;
 
@@ -47,16 +57,22 @@ use uuid::Uuid;
 ///
 /// // Create network using CPU backend for testing
 /// let device = burn::backend::ndarray::NdArrayDevice::default();
-/// let network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+/// let network = BurnNetwork::<NdArray>::from_topology(&topology, device)?;
 ///
 /// // Run inference
 /// let outputs = network.predict(&[1.0, 2.0, 3.0]);
 /// assert_eq!(outputs.len(), 2); // Two output neurons
+/// # Ok::<(), PolyNeatError>(())
 /// ```
 pub struct BurnNetwork<B: Backend> {
     coeff_tensor: Coefficients<B>,
     basis_template: BasisTemplate<usize>,
     device: B::Device,
+    num_inputs: usize,
+    disconnected_outputs: Vec<usize>,
+    numeric_policy: NumericPolicy,
+    // shared with the topology this was built from
+    numeric_flag: Arc<NumericFlag>,
 }
 
 impl<B: Backend> BurnNetwork<B> {
@@ -74,6 +90,13 @@ impl<B: Backend> BurnNetwork<B> {
     ///
     /// A new `BurnNetwork` ready for inference on the specified device
     ///
+    /// # Errors
+    ///
+    /// Fails if a neuron lock is poisoned, or if an output depends on an input neuron
+    /// that is no longer listed in the topology. Fails with
+    /// [`PolyNeatError::NotAPolynomial`] if a sum is raised to a negative power, since
+    /// the output then has no closed form to evaluate.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -87,20 +110,57 @@ impl<B: Backend> BurnNetwork<B> {
     ///
     /// // Create network on CPU backend
     /// let device = burn::backend::ndarray::NdArrayDevice::default();
-    /// let network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    /// let network = BurnNetwork::<NdArray>::from_topology(&topology, device)?;
+    /// # Ok::<(), PolyNeatError>(())
     /// ```
-    pub fn from_topology(topology: &PolyNetworkTopology, device: B::Device) -> Self {
-        let inputs: FnvHashMap<Uuid, usize> = topology
-            .neuron_ids()
-            .into_iter()
-            .enumerate()
-            .map(|(v, k)| (k, v))
-            .collect();
+    pub fn from_topology(topology: &PolyNetworkTopology, device: B::Device) -> Result<Self> {
+        Self::from_topology_with_limits(topology, device, &ExpansionLimits::default())
+    }
+
+    /// Create a network from a topology, giving up once the expansion exceeds `limits`.
+    ///
+    /// The topology's [`ExpansionEstimate`](crate::burn_net::expander::ExpansionEstimate)
+    /// is checked first, so genomes that are obviously too large are rejected without
+    /// expanding anything.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`from_topology`](Self::from_topology), and with
+    /// [`PolyNeatError::ExpansionLimit`] if a limit is hit. Such a genome can still be
+    /// evaluated without expanding it, e.g. with [`PolyNetworkTopology::to_compiled_network`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use polynomial_neat::prelude::*;
+    /// # use polynomial_neat::burn_net::network::BurnNetwork;
+    /// use polynomial_neat::burn_net::expander::{ExpansionLimit, ExpansionLimits};
+    /// use burn::backend::NdArray;
+    ///
+    /// # let topology = PolyNetworkTopology::new(3, 2, MutationChances::new(50), &mut rand::rng());
+    /// let device = burn::backend::ndarray::NdArrayDevice::default();
+    /// let limits = ExpansionLimits::default().with_max_terms(1);
+    ///
+    /// match BurnNetwork::<NdArray>::from_topology_with_limits(&topology, device, &limits) {
+    ///     Ok(network) => println!("{:?}", network.predict(&[1., 2., 3.])),
+    ///     Err(PolyNeatError::ExpansionLimit(_)) => {
+    ///         println!("{:?}", topology.to_compiled_network()?.predict(&[1., 2., 3.]))
+    ///     }
+    ///     Err(error) => return Err(error),
+    /// }
+    /// # Ok::<(), PolyNeatError>(())
+    /// ```
+    pub fn from_topology_with_limits(
+        topology: &PolyNetworkTopology,
+        device: B::Device,
+        limits: &ExpansionLimits,
+    ) -> Result<Self> {
+        let inputs = input_indices(topology)?;
 
-        let mut output_polynomials = get_topology_polynomials(topology)
+        let mut output_polynomials = expand_topology(topology, limits)?
             .into_par_iter()
             .map(|poly| poly.map_operands(&inputs))
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>>>()?;
         output_polynomials
             .par_iter_mut()
             .for_each(|poly| poly.sort_by_exponent(0));
@@ -115,13 +175,45 @@ impl<B: Backend> BurnNetwork<B> {
         let basis_template = BasisTemplate::from_raw(variable_basis);
 
         info!("Basis:\n{basis_template}");
-        let coeff_tensor = Coefficients::new(&output_polynomials, &basis_template, &device);
+        let coeff_tensor = Coefficients::new(&output_polynomials, &basis_template, &device)?;
 
-        Self {
+        Ok(Self {
             coeff_tensor,
             basis_template,
             device,
+            num_inputs: inputs.len(),
+            disconnected_outputs: topology.disconnected_outputs()?,
+            numeric_policy: NumericPolicy::default(),
+            numeric_flag: Arc::clone(topology.numeric_flag()),
+        })
+    }
+
+    /// Set how non-finite values are handled. See [`NumericPolicy`].
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::UnsupportedPolicy`] under [`NumericPolicy::Epsilon`].
+    /// The hidden neurons are expanded away, so there are no hidden sums left to replace,
+    /// and this network would disagree with the CPU evaluators.
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Result<Self> {
+        if let NumericPolicy::Epsilon(_) = policy {
+            return Err(PolyNeatError::UnsupportedPolicy { policy });
         }
+        self.numeric_policy = policy;
+        Ok(self)
+    }
+
+    /// Store the coefficients in `layout` instead of the one picked from their density.
+    ///
+    /// See [`CoefficientLayout`].
+    pub fn with_coefficient_layout(mut self, layout: CoefficientLayout) -> Self {
+        self.coeff_tensor = self.coeff_tensor.relayout(layout, &self.device);
+        self
+    }
+
+    /// The layout the coefficients are stored in, either
+    /// [`Dense`](CoefficientLayout::Dense) or [`Sparse`](CoefficientLayout::Sparse).
+    pub fn coefficient_layout(&self) -> CoefficientLayout {
+        self.coeff_tensor.layout()
     }
 
     /// Perform a forward pass through the network with the given inputs.
@@ -137,9 +229,10 @@ impl<B: Backend> BurnNetwork<B> {
     ///
     /// A vector containing the output values from the network's output neurons.
     ///
-    /// # Panics
+    /// # Note
     ///
-    /// Panics if the number of inputs doesn't match the network's expected input size.
+    /// Like [`SimplePolyNetwork::predict`], extra inputs are ignored and missing inputs
+    /// are treated as 0. Use [`try_predict`](Self::try_predict) to refuse them instead.
     ///
     /// # Example
     ///
@@ -151,24 +244,135 @@ impl<B: Backend> BurnNetwork<B> {
     /// # let mutations = MutationChances::new(50);
     /// # let topology = PolyNetworkTopology::new(2, 1, mutations, &mut rand::rng());
     /// # let device = burn::backend::ndarray::NdArrayDevice::default();
-    /// # let network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    /// # let network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
     /// // Predict with two inputs
     /// let outputs = network.predict(&[1.0, 0.5]);
     /// assert_eq!(outputs.len(), 1); // One output neuron
     /// ```
     pub fn predict(&self, inputs: &[f32]) -> Vec<f32> {
+        let inputs = &inputs[..inputs.len().min(self.num_inputs)];
         let basis = self.basis_template.make_tensor::<B>(
             inputs.iter().enumerate().map(|(p, v)| (p, *v)),
+            &self.numeric_policy,
             &self.device,
         );
 
-        let result = self.coeff_tensor.inner().clone().matmul(basis);
+        let result = self.coeff_tensor.matmul(basis, &self.numeric_policy);
 
         // Flatten and convert to Vec<f32>
         let shape = result.shape();
         let flattened = result.reshape([shape.dims[0] * shape.dims[1]]);
         let data = flattened.to_data();
-        data.as_slice::<f32>().unwrap().to_vec()
+        let mut outputs = data.as_slice::<f32>().unwrap().to_vec();
+        self.numeric_policy.apply(&mut outputs, &self.numeric_flag);
+        outputs
+    }
+
+    /// Perform a forward pass after checking `inputs` against `validation`.
+    ///
+    /// If the length check is disabled, missing inputs are treated as 0 and extra inputs
+    /// are ignored, the same way [`predict`](Self::predict) handles them.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Predict`] describing the first failed check.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use polynomial_neat::prelude::*;
+    /// # use polynomial_neat::burn_net::network::BurnNetwork;
+    /// # use burn::backend::NdArray;
+    /// use polynomial_neat::core::validation::{InputValidation, PredictError};
+    ///
+    /// # let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+    /// # let device = burn::backend::ndarray::NdArrayDevice::default();
+    /// let network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
+    ///
+    /// let result = network.try_predict(&[1.0], &InputValidation::default());
+    /// assert_eq!(
+    ///     result,
+    ///     Err(PolyNeatError::Predict(PredictError::InputLength { expected: 2, actual: 1 }))
+    /// );
+    ///
+    /// let lenient = InputValidation::default().with_exact_length(false);
+    /// assert!(network.try_predict(&[1.0], &lenient).is_ok());
+    /// ```
+    pub fn try_predict(&self, inputs: &[f32], validation: &InputValidation) -> Result<Vec<f32>> {
+        validation.validate(inputs, self.num_inputs, &self.disconnected_outputs)?;
+        Ok(self.predict(inputs))
+    }
+
+    /// Evaluate the network on a whole batch of samples in a single matmul.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - A `[batch, num_inputs]` tensor, one sample per row. Missing input
+    ///   columns are treated as 0.
+    ///
+    /// # Returns
+    ///
+    /// A `[num_outputs, batch]` tensor: column `b` holds the outputs for sample `b`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use polynomial_neat::prelude::*;
+    /// # use polynomial_neat::burn_net::network::BurnNetwork;
+    /// use burn::backend::NdArray;
+    /// use burn::prelude::*;
+    ///
+    /// # let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+    /// let device = burn::backend::ndarray::NdArrayDevice::default();
+    /// let network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
+    ///
+    /// let inputs = Tensor::<NdArray, 2>::from_data([[1.0, 0.5], [2.0, -1.0], [0.0, 3.0]], &device);
+    /// let outputs = network.predict_batch(inputs);
+    /// assert_eq!(outputs.dims(), [1, 3]);
+    /// ```
+    pub fn predict_batch(&self, inputs: Tensor<B, 2>) -> Tensor<B, 2> {
+        let basis = self
+            .basis_template
+            .make_batch_tensor(inputs.to_device(&self.device), &self.numeric_policy);
+        self.apply_coefficients(basis)
+    }
+
+    /// Evaluate the network on a batch of samples given as rows of input values.
+    ///
+    /// The basis is built on the host in one pass and uploaded once, so this is the
+    /// cheapest way to evaluate a dataset that isn't already on the device.
+    ///
+    /// # Returns
+    ///
+    /// A `[num_outputs, batch]` tensor: column `b` holds the outputs for `inputs[b]`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use polynomial_neat::prelude::*;
+    /// # use polynomial_neat::burn_net::network::BurnNetwork;
+    /// # use burn::backend::NdArray;
+    /// # let topology = PolyNetworkTopology::new(2, 2, MutationChances::new(50), &mut rand::rng());
+    /// # let device = burn::backend::ndarray::NdArrayDevice::default();
+    /// # let network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
+    /// let dataset = vec![vec![1.0, 0.5], vec![2.0, -1.0]];
+    /// let outputs = network.predict_batch_rows(&dataset);
+    /// assert_eq!(outputs.dims(), [2, 2]);
+    /// ```
+    pub fn predict_batch_rows<R: AsRef<[f32]>>(&self, inputs: &[R]) -> Tensor<B, 2> {
+        let basis = self.basis_template.make_batch_tensor_from_rows::<B, R>(
+            inputs,
+            &self.numeric_policy,
+            &self.device,
+        );
+        self.apply_coefficients(basis)
+    }
+
+    fn apply_coefficients(&self, basis: Tensor<B, 2>) -> Tensor<B, 2> {
+        let outputs = self.coeff_tensor.matmul(basis, &self.numeric_policy);
+
+        let (outputs, flagged) = apply_numeric_policy(&self.numeric_policy, outputs);
+        self.numeric_flag.raise(flagged.into_iter().sum());
+        outputs
     }
 }
 
@@ -217,7 +421,7 @@ mod tests {
         );
 
         let device = burn::backend::ndarray::NdArrayDevice::default();
-        let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device);
+        let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
 
         let res = burn_net.predict(&[3.0, 2.0]);
         println!("burn_net result: {:?}", res);
@@ -233,9 +437,152 @@ mod tests {
 
         println!("here 1");
         let device = burn::backend::ndarray::NdArrayDevice::default();
-        let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device);
+        let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
 
         let res = burn_net.predict(&[3.0, 2.0]);
         println!("burn_net result: {:?}", res);
     }
+
+    #[test]
+    fn inputs_are_indexed_among_inputs_only() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&x, 1., 2)],
+        ));
+        // 2x^2 + y
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&hidden, 2., 1),
+                PolyInputTopology::downgrade(&y, 1., 1),
+            ],
+        ));
+
+        // the inputs are not listed first, so their position among all neurons is not
+        // the position of their value in the sample
+        let topology = PolyNetworkTopology::from_raw_parts(
+            vec![hidden, x, output, y],
+            MutationChances::none(),
+        );
+
+        let device = burn::backend::ndarray::NdArrayDevice::default();
+        let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
+        assert_eq!(burn_net.predict(&[3., 1.]), vec![19.]);
+        let simple: Vec<f32> = topology.to_simple_network().predict(&[3., 1.]).collect();
+        assert_eq!(simple, vec![19.]);
+    }
+
+    #[test]
+    fn predict_batch_matches_predict() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(82);
+        let mut topology = PolyNetworkTopology::new(3, 2, MutationChances::new(60), &mut rng);
+        for _ in 0..10 {
+            topology = topology.replicate(&mut rng);
+        }
+
+        let device = burn::backend::ndarray::NdArrayDevice::default();
+        let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
+
+        let samples = vec![
+            vec![1.0, 0.5, -2.0],
+            vec![0.25, -1.0, 3.0],
+            vec![2.0, 2.0, 0.5],
+            vec![-0.5, 0.0, 1.5],
+        ];
+
+        let from_rows = burn_net.predict_batch_rows(&samples);
+        let flat: Vec<f32> = samples.iter().flatten().copied().collect();
+        let tensor = Tensor::<TestBackend, 2>::from_data(TensorData::new(flat, [4, 3]), &device);
+        let from_tensor = burn_net.predict_batch(tensor);
+
+        assert_eq!(from_rows.dims(), [2, 4]);
+        assert_eq!(from_tensor.dims(), [2, 4]);
+
+        let from_rows = from_rows.to_data().to_vec::<f32>().unwrap();
+        let from_tensor = from_tensor.to_data().to_vec::<f32>().unwrap();
+
+        for (b, sample) in samples.iter().enumerate() {
+            for (o, expected) in burn_net.predict(sample).into_iter().enumerate() {
+                let tolerance = 1e-3 * expected.abs().max(1.);
+                assert!((from_rows[o * 4 + b] - expected).abs() <= tolerance);
+                assert!((from_tensor[o * 4 + b] - expected).abs() <= tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn disjoint_outputs_pick_sparse_layout() {
+        use uuid::Uuid;
+
+        // five outputs, each reading only its own input, fill a fifth of the matrix
+        let inputs: Vec<_> = (0..5)
+            .map(|_| arc(PolyNeuronTopology::input(Uuid::new_v4())))
+            .collect();
+        let outputs: Vec<_> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                arc(PolyNeuronTopology::output(
+                    Uuid::new_v4(),
+                    vec![PolyInputTopology::downgrade(input, i as f32 + 1., 2)],
+                ))
+            })
+            .collect();
+        let topology = PolyNetworkTopology::from_raw_parts(
+            inputs.into_iter().chain(outputs).collect(),
+            MutationChances::none(),
+        );
+
+        let device = burn::backend::ndarray::NdArrayDevice::default();
+        let sparse = BurnNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
+        assert_eq!(sparse.coefficient_layout(), CoefficientLayout::Sparse);
+        let dense = BurnNetwork::<TestBackend>::from_topology(&topology, device)
+            .unwrap()
+            .with_coefficient_layout(CoefficientLayout::Dense);
+        assert_eq!(dense.coefficient_layout(), CoefficientLayout::Dense);
+
+        let samples = [[1., 2., 3., 4., 5.], [0.5, -1., 0., 2., -3.]];
+        assert_eq!(sparse.predict(&samples[0]), vec![1., 8., 27., 64., 125.]);
+        assert_eq!(
+            sparse.predict_batch_rows(&samples).to_data(),
+            dense.predict_batch_rows(&samples).to_data()
+        );
+    }
+
+    #[test]
+    fn layouts_agree_on_division_by_zero() {
+        use uuid::Uuid;
+
+        // 1 / x and 2y fill half of the matrix, so the auto layout is dense
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let reciprocal = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&x, 1., -1)],
+        ));
+        let linear = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&y, 2., 1)],
+        ));
+        let topology = PolyNetworkTopology::from_raw_parts(
+            vec![x, y, reciprocal, linear],
+            MutationChances::none(),
+        );
+
+        let device = burn::backend::ndarray::NdArrayDevice::default();
+        let dense = BurnNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
+        assert_eq!(dense.coefficient_layout(), CoefficientLayout::Dense);
+        let sparse = BurnNetwork::<TestBackend>::from_topology(&topology, device)
+            .unwrap()
+            .with_coefficient_layout(CoefficientLayout::Sparse);
+
+        for network in [dense, sparse] {
+            assert_eq!(network.predict(&[0., 3.]), vec![f32::INFINITY, 6.]);
+        }
+    }
 }
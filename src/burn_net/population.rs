@@ -0,0 +1,367 @@
+//! Evaluating a whole population in one tensor program.
+//!
+//! Building a [`BurnNetwork`](super::network::BurnNetwork) per genome means one basis and
+//! one matmul per genome. [`PopulationNetwork`] instead expands every genome up front,
+//! takes the union of all their basis rows, and stacks every output's coefficient row
+//! into a single `[total_outputs, rows]` matrix. Evaluating the population on a dataset
+//! is then one basis build and one matmul, no matter how many genomes there are.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::burn_net::population::PopulationNetwork;
+//! use burn::backend::NdArray;
+//!
+//! let population: Vec<_> = (0..8)
+//!     .map(|_| PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng()))
+//!     .collect();
+//!
+//! let device = burn::backend::ndarray::NdArrayDevice::default();
+//! let network = PopulationNetwork::<NdArray>::from_topologies(&population, device)?;
+//!
+//! let dataset = vec![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+//! let outputs = network.predict_batch_rows(&dataset);
+//!
+//! assert_eq!(outputs.len(), 8);
+//! assert_eq!(outputs.genome(3).dims(), [1, 4]);
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{ops::Range, sync::Arc};
+
+use burn::prelude::*;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator as _};
+
+use super::{
+    apply_numeric_policy,
+    basis_prime::{BasisTemplate, basis_from_poly_list},
+    coeff::{CoefficientLayout, Coefficients},
+    expander::{ExpansionLimits, Polynomial},
+    output_polynomials_with_limits,
+};
+use crate::{
+    core::numeric::{NumericFlag, NumericPolicy},
+    error::Result,
+    prelude::*,
+};
+
+/// Every genome of a population stacked against a shared basis.
+pub struct PopulationNetwork<B: Backend> {
+    coeff_tensor: Coefficients<B>,
+    basis_template: BasisTemplate<usize>,
+    // the output rows belonging to each genome
+    genomes: Vec<Range<usize>>,
+    numeric_policy: NumericPolicy,
+    // shared with each genome's topology
+    numeric_flags: Vec<Arc<NumericFlag>>,
+    device: B::Device,
+}
+
+impl<B: Backend> PopulationNetwork<B> {
+    /// Expand every topology and stack their coefficients against the union basis.
+    ///
+    /// Genomes may have different numbers of inputs and outputs. Input `i` of every
+    /// genome reads column `i` of the dataset.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any genome can't be expanded. See [`PolyNetworkTopology::output_polynomials`].
+    pub fn from_topologies(topologies: &[PolyNetworkTopology], device: B::Device) -> Result<Self> {
+        Self::from_topologies_with_limits(topologies, device, &ExpansionLimits::default())
+    }
+
+    /// [`from_topologies`](Self::from_topologies), applying `limits` to every genome.
+    ///
+    /// # Errors
+    ///
+    /// Additionally fails with [`PolyNeatError::ExpansionLimit`] if any genome hits a
+    /// limit. Filter genomes with [`PolyNetworkTopology::estimate_expansion`] beforehand
+    /// to keep one oversized genome from failing the whole population.
+    pub fn from_topologies_with_limits(
+        topologies: &[PolyNetworkTopology],
+        device: B::Device,
+        limits: &ExpansionLimits,
+    ) -> Result<Self> {
+        let per_genome: Vec<Vec<Polynomial<usize>>> = topologies
+            .par_iter()
+            .map(|topology| output_polynomials_with_limits(topology, limits))
+            .collect::<Result<_>>()?;
+
+        let mut genomes = Vec::with_capacity(per_genome.len());
+        let mut start = 0;
+        for outputs in per_genome.iter() {
+            genomes.push(start..start + outputs.len());
+            start += outputs.len();
+        }
+
+        let polynomials: Vec<Polynomial<usize>> = per_genome.into_iter().flatten().collect();
+        let basis_template = BasisTemplate::from_raw(basis_from_poly_list(&polynomials));
+        let coeff_tensor = Coefficients::new(&polynomials, &basis_template, &device)?;
+
+        Ok(Self {
+            coeff_tensor,
+            basis_template,
+            genomes,
+            numeric_policy: NumericPolicy::default(),
+            numeric_flags: topologies
+                .iter()
+                .map(|topology| Arc::clone(topology.numeric_flag()))
+                .collect(),
+            device,
+        })
+    }
+
+    /// Set how non-finite values are handled. See [`NumericPolicy`].
+    ///
+    /// Under [`NumericPolicy::Sentinel`], only the genomes whose outputs were replaced get
+    /// flagged.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::UnsupportedPolicy`] under [`NumericPolicy::Epsilon`],
+    /// like [`BurnNetwork::with_numeric_policy`](super::BurnNetwork::with_numeric_policy).
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Result<Self> {
+        if let NumericPolicy::Epsilon(_) = policy {
+            return Err(PolyNeatError::UnsupportedPolicy { policy });
+        }
+        self.numeric_policy = policy;
+        Ok(self)
+    }
+
+    /// Store the coefficients in `layout` instead of the one picked from their density.
+    ///
+    /// Populations are usually sparse, since each genome only uses its own basis rows.
+    /// See [`CoefficientLayout`].
+    pub fn with_coefficient_layout(mut self, layout: CoefficientLayout) -> Self {
+        self.coeff_tensor = self.coeff_tensor.relayout(layout, &self.device);
+        self
+    }
+
+    /// The layout the coefficients are stored in.
+    pub fn coefficient_layout(&self) -> CoefficientLayout {
+        self.coeff_tensor.layout()
+    }
+
+    /// The number of genomes in the population.
+    pub fn len(&self) -> usize {
+        self.genomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genomes.is_empty()
+    }
+
+    /// The number of rows in the shared basis.
+    pub fn basis_rows(&self) -> usize {
+        self.basis_template.num_rows()
+    }
+
+    /// Evaluate every genome on a `[batch, num_inputs]` tensor of samples.
+    pub fn predict_batch(&self, inputs: Tensor<B, 2>) -> PopulationOutputs<B> {
+        let basis = self
+            .basis_template
+            .make_batch_tensor(inputs.to_device(&self.device), &self.numeric_policy);
+        self.apply_coefficients(basis)
+    }
+
+    /// Evaluate every genome on a batch of samples given as rows of input values.
+    pub fn predict_batch_rows<R: AsRef<[f32]>>(&self, inputs: &[R]) -> PopulationOutputs<B> {
+        let basis = self.basis_template.make_batch_tensor_from_rows::<B, R>(
+            inputs,
+            &self.numeric_policy,
+            &self.device,
+        );
+        self.apply_coefficients(basis)
+    }
+
+    fn apply_coefficients(&self, basis: Tensor<B, 2>) -> PopulationOutputs<B> {
+        // one genome dividing by zero can't poison another's outputs in either layout
+        let outputs = self.coeff_tensor.matmul(basis, &self.numeric_policy);
+
+        let (outputs, flagged) = apply_numeric_policy(&self.numeric_policy, outputs);
+        if !flagged.is_empty() {
+            for (rows, flag) in self.genomes.iter().zip(self.numeric_flags.iter()) {
+                flag.raise(flagged[rows.clone()].iter().sum());
+            }
+        }
+
+        PopulationOutputs {
+            outputs,
+            genomes: self.genomes.clone(),
+        }
+    }
+}
+
+/// The outputs of a whole population, stacked as `[total_outputs, batch]`.
+pub struct PopulationOutputs<B: Backend> {
+    outputs: Tensor<B, 2>,
+    genomes: Vec<Range<usize>>,
+}
+
+impl<B: Backend> PopulationOutputs<B> {
+    pub fn len(&self) -> usize {
+        self.genomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genomes.is_empty()
+    }
+
+    /// All outputs of all genomes, one row per output.
+    pub fn stacked(&self) -> &Tensor<B, 2> {
+        &self.outputs
+    }
+
+    /// The `[num_outputs, batch]` outputs of a single genome.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn genome(&self, index: usize) -> Tensor<B, 2> {
+        let rows = self.genomes[index].clone();
+        let [_, batch] = self.outputs.dims();
+        self.outputs.clone().slice([rows, 0..batch])
+    }
+
+    /// Copy the outputs back to the host.
+    ///
+    /// # Returns
+    ///
+    /// One entry per genome, each holding one row per output with one value per sample.
+    pub fn to_vecs(&self) -> Vec<Vec<Vec<f32>>> {
+        let [_, batch] = self.outputs.dims();
+        let values = self.outputs.to_data().to_vec::<f32>().unwrap();
+
+        self.genomes
+            .iter()
+            .map(|rows| {
+                rows.clone()
+                    .map(|row| values[row * batch..(row + 1) * batch].to_vec())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+    use rand::{SeedableRng, rngs::StdRng};
+    use uuid::Uuid;
+
+    type TestBackend = NdArray;
+
+    #[test]
+    fn population_matches_expanded_polynomials() {
+        let mut rng = StdRng::seed_from_u64(1234);
+        let population: Vec<PolyNetworkTopology> = (0..12)
+            .map(|i| {
+                let mut topology = PolyNetworkTopology::new(
+                    2 + i % 2,
+                    1 + i % 3,
+                    MutationChances::new(60),
+                    &mut rng,
+                );
+                for _ in 0..i {
+                    topology = topology.replicate(&mut rng);
+                }
+                topology
+            })
+            .collect();
+
+        let samples = vec![
+            vec![1.0, 0.5, -1.0],
+            vec![-0.5, 2.0, 0.25],
+            vec![0.0, 1.0, 1.0],
+        ];
+
+        let device = burn::backend::ndarray::NdArrayDevice::default();
+        let network =
+            PopulationNetwork::<TestBackend>::from_topologies(&population, device).unwrap();
+        let outputs = network.predict_batch_rows(&samples);
+        assert_eq!(outputs.len(), population.len());
+
+        let values = outputs.to_vecs();
+        for (genome, topology) in population.iter().enumerate() {
+            let polynomials = topology.output_polynomials().unwrap();
+
+            assert_eq!(
+                outputs.genome(genome).dims(),
+                [topology.info().num_outputs, samples.len()]
+            );
+
+            for (b, sample) in samples.iter().enumerate() {
+                for (o, polynomial) in polynomials.iter().enumerate() {
+                    let expected = polynomial.evaluate(sample);
+                    let actual = values[genome][o][b];
+                    let tolerance = 1e-3 * expected.abs().max(1.);
+                    assert!(
+                        actual == expected
+                            || (actual.is_nan() && expected.is_nan())
+                            || (actual - expected).abs() <= tolerance,
+                        "genome {genome} output {o} sample {b}: {actual} != {expected}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn non_finite_terms_stay_in_their_genome() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        // 1 / x
+        let reciprocal = PolyNetworkTopology::from_raw_parts(
+            vec![
+                x.clone(),
+                arc(PolyNeuronTopology::output(
+                    Uuid::new_v4(),
+                    vec![PolyInputTopology::downgrade(&x, 1., -1)],
+                )),
+            ],
+            MutationChances::none(),
+        );
+
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        // 2y
+        let linear = PolyNetworkTopology::from_raw_parts(
+            vec![
+                y.clone(),
+                arc(PolyNeuronTopology::output(
+                    Uuid::new_v4(),
+                    vec![PolyInputTopology::downgrade(&y, 2., 1)],
+                )),
+            ],
+            MutationChances::none(),
+        );
+
+        let device = burn::backend::ndarray::NdArrayDevice::default();
+        let population = [reciprocal, linear];
+        for layout in [CoefficientLayout::Dense, CoefficientLayout::Sparse] {
+            let network = PopulationNetwork::<TestBackend>::from_topologies(&population, device)
+                .unwrap()
+                .with_coefficient_layout(layout);
+            assert_eq!(network.coefficient_layout(), layout);
+            let values = network.predict_batch_rows(&[[0.0], [2.0]]).to_vecs();
+
+            assert_eq!(values[0], vec![vec![f32::INFINITY, 0.5]], "{layout:?}");
+            assert_eq!(values[1], vec![vec![0.0, 4.0]], "{layout:?}");
+        }
+    }
+
+    #[test]
+    fn shared_basis_is_a_union() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let population: Vec<PolyNetworkTopology> = (0..4)
+            .map(|_| PolyNetworkTopology::new(2, 1, MutationChances::none(), &mut rng))
+            .collect();
+
+        let device = burn::backend::ndarray::NdArrayDevice::default();
+        let network =
+            PopulationNetwork::<TestBackend>::from_topologies(&population, device).unwrap();
+
+        // initial genomes connect inputs directly with exponents 0..=2, so the union can
+        // only hold x0, x0^2, x1, x1^2 and a constant
+        assert!(network.basis_rows() <= 5);
+    }
+}
@@ -1,5 +1,8 @@
-use super::{BurnNetwork, create_polynomial, expander::Polynomial, get_topology_polynomials};
-use crate::{burn_net::expander::PolyComponent, prelude::*};
+use super::{BurnNetwork, expander::Polynomial, get_topology_polynomials};
+use crate::{
+    burn_net::expander::{ExpansionLimit, ExpansionLimits, ExpressionFormat, PolyComponent},
+    prelude::*,
+};
 use burn::backend::NdArray;
 use burn::prelude::*;
 use fnv::FnvHashMap;
@@ -25,7 +28,7 @@ pub fn simple_network() {
     let topology =
         PolyNetworkTopology::from_raw_parts(vec![input, output], MutationChances::none());
 
-    let polynomials = get_topology_polynomials(&topology);
+    let polynomials = get_topology_polynomials(&topology).unwrap();
 
     assert!(polynomials.len() == 1);
     let poly = &polynomials[0];
@@ -86,7 +89,7 @@ pub fn two_input_network() {
         MutationChances::none(),
     );
 
-    let polynomials = get_topology_polynomials(&topology);
+    let polynomials = get_topology_polynomials(&topology).unwrap();
     assert_eq!(polynomials.len(), 1);
     let output_polynomial = polynomials.first().unwrap();
 
@@ -156,7 +159,7 @@ fn map_inputs_to_outputs() {
 
     println!("network topology ids: \n{:#?}", topology.neuron_ids());
 
-    let output_polynomials = get_topology_polynomials(&topology);
+    let output_polynomials = get_topology_polynomials(&topology).unwrap();
     let inputs: FnvHashMap<Uuid, usize> = topology
         .neuron_ids()
         .into_iter()
@@ -167,7 +170,7 @@ fn map_inputs_to_outputs() {
     let mapped_output_polynomials: Vec<Polynomial<usize>> = output_polynomials
         .clone()
         .into_iter()
-        .map(|polynomial| polynomial.map_operands(&inputs))
+        .map(|polynomial| polynomial.map_operands(&inputs).unwrap())
         .collect();
 
     for (o, m) in output_polynomials
@@ -177,9 +180,11 @@ fn map_inputs_to_outputs() {
         assert_eq!(o.parts().len(), m.parts().len());
         for (op, mp) in o.parts().iter().zip(m.parts()) {
             assert_eq!(op.weight(), mp.weight());
-            for (opo, mpo) in op.operands().iter().zip(mp.operands()) {
-                assert_eq!(opo.exponent(), mpo.exponent());
-                assert_eq!(mpo.var(), inputs.get(opo.var()).unwrap());
+            // mapped monomials are re-sorted by their new variables
+            assert_eq!(op.operands().len(), mp.operands().len());
+            for opo in op.operands() {
+                let mapped = inputs.get(opo.var()).unwrap();
+                assert_eq!(mp.monomial().exponent_of(mapped), opo.exponent());
             }
         }
     }
@@ -212,9 +217,211 @@ fn test_burn_network_functionality() {
     );
 
     let device = burn::backend::ndarray::NdArrayDevice::default();
-    let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device);
+    let burn_net = BurnNetwork::<TestBackend>::from_topology(&topology, device).unwrap();
 
     let res = burn_net.predict(&[3.0, 2.0]);
     // (3*3 + 2)^2 = 11^2 = 121
     assert_eq!(res[0], 121.0);
 }
+
+#[test]
+fn output_polynomials_use_input_indices() {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+
+    // 2x^2 + y
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&x, 2., 2),
+            PolyInputTopology::downgrade(&y, 1., 1),
+        ],
+    ));
+
+    // outputs listed first must not shift the input indices
+    let topology = PolyNetworkTopology::from_raw_parts(vec![output, x, y], MutationChances::none());
+
+    let polynomials = topology.output_polynomials().unwrap();
+    assert_eq!(polynomials.len(), 1);
+    assert_eq!(
+        polynomials[0]
+            .expression(ExpressionFormat::Text)
+            .to_string(),
+        "2*x0^2 + x1"
+    );
+    assert_eq!(
+        polynomials[0]
+            .expression(ExpressionFormat::Latex)
+            .with_labels(&["v", "m"])
+            .to_string(),
+        "2 v^{2} + m"
+    );
+}
+
+#[test]
+fn unlisted_input_is_an_error() {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&x, 2., 1),
+            PolyInputTopology::downgrade(&y, 1., 1),
+        ],
+    ));
+
+    // `y` is still alive, but no longer part of the topology
+    let topology = PolyNetworkTopology::from_raw_parts(vec![x, output], MutationChances::none());
+
+    assert!(matches!(
+        topology.output_polynomials(),
+        Err(PolyNeatError::UnknownVariable { .. })
+    ));
+    assert!(matches!(
+        BurnNetwork::<TestBackend>::from_topology(&topology, Default::default()),
+        Err(PolyNeatError::UnknownVariable { .. })
+    ));
+    // the CPU evaluators keep treating it as 0
+    assert_eq!(
+        topology.to_compiled_network().unwrap().predict(&[3.]),
+        vec![6.]
+    );
+}
+
+#[test]
+fn shared_subgraphs_expand_once() {
+    // a ladder of 30 layers, each neuron averaging both neurons of the layer before,
+    // has 2^30 paths but only 60 connections
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let mut neurons = vec![x.clone(), y.clone()];
+
+    let mut layer = [x, y];
+    for _ in 0..30 {
+        let next = [(); 2].map(|_| {
+            arc(PolyNeuronTopology::hidden(
+                Uuid::new_v4(),
+                vec![
+                    PolyInputTopology::downgrade(&layer[0], 0.5, 1),
+                    PolyInputTopology::downgrade(&layer[1], 0.5, 1),
+                ],
+            ))
+        });
+        neurons.extend(next.iter().cloned());
+        layer = next;
+    }
+
+    // the same square is used twice, and cached the second time
+    neurons.push(arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&layer[0], 1., 2),
+            PolyInputTopology::downgrade(&layer[0], 3., 2),
+            PolyInputTopology::downgrade(&layer[1], 2., 1),
+        ],
+    )));
+    let topology = PolyNetworkTopology::from_raw_parts(neurons, MutationChances::none());
+
+    let polynomials = topology.output_polynomials().unwrap();
+    // 4((x + y) / 2)^2 + 2(x + y) / 2
+    assert_eq!(polynomials[0].components().len(), 5);
+
+    let inputs = [0.5, 2.];
+    let expected = topology.to_compiled_network().unwrap().predict(&inputs)[0];
+    assert!((polynomials[0].evaluate(&inputs) - expected).abs() < 1e-4);
+}
+
+#[test]
+fn large_exponents_are_squared() {
+    // x^1_000_000 would take a million products, and as many stack frames, one at a time
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let hidden = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&x, 1., 1)],
+    ));
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&hidden, 1., 1_000_000)],
+    ));
+    let topology =
+        PolyNetworkTopology::from_raw_parts(vec![x, hidden, output], MutationChances::none());
+
+    let polynomials = topology.output_polynomials().unwrap();
+    assert_eq!(polynomials[0].degree(), 1_000_000);
+    assert_eq!(polynomials[0].components().len(), 1);
+
+    // the degree is refused before any product is built
+    let limits = ExpansionLimits::default().with_max_degree(10);
+    let guard = limits.start();
+    let sum = Polynomial::unit(0).with_operation(1., 1, 1);
+    assert_eq!(
+        sum.power_within(11, &guard).err(),
+        Some(PolyNeatError::ExpansionLimit(ExpansionLimit::Degree(10)))
+    );
+    // (x + y)^5, squared twice and multiplied once
+    let power = sum.power_within(5, &guard).unwrap();
+    assert_eq!(power.components().len(), 6);
+    assert_eq!(power.evaluate(&[1., 1.]), 32.);
+}
+
+#[test]
+fn negative_exponents_invert_single_terms_only() {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let double = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&x, 2., 1)],
+    ));
+    let sum = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&x, 1., 1),
+            PolyInputTopology::downgrade(&y, 1., 1),
+        ],
+    ));
+    // 1 / (2x)
+    let reciprocal = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&double, 1., -1)],
+    ));
+    let topology = PolyNetworkTopology::from_raw_parts(
+        vec![x.clone(), y.clone(), double, reciprocal],
+        MutationChances::none(),
+    );
+
+    let polynomials = topology.output_polynomials().unwrap();
+    assert_eq!(
+        polynomials[0]
+            .expression(ExpressionFormat::Text)
+            .to_string(),
+        "0.5*x0^-1"
+    );
+    let inputs = [4., 3.];
+    let expected: Vec<f32> = topology.to_simple_network().predict(&inputs).collect();
+    assert_eq!(expected, vec![0.125]);
+    assert_eq!(polynomials[0].evaluate(&inputs), expected[0]);
+    let network = BurnNetwork::<TestBackend>::from_topology(&topology, Default::default()).unwrap();
+    assert_eq!(network.predict(&inputs), expected);
+
+    // 1 / (x + y)
+    let reciprocal = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&sum, 1., -1)],
+    ));
+    let topology =
+        PolyNetworkTopology::from_raw_parts(vec![x, y, sum, reciprocal], MutationChances::none());
+
+    let not_a_polynomial = PolyNeatError::NotAPolynomial { terms: 2 };
+    assert_eq!(
+        topology.output_polynomials().err(),
+        Some(not_a_polynomial.clone())
+    );
+    assert_eq!(
+        BurnNetwork::<TestBackend>::from_topology(&topology, Default::default()).err(),
+        Some(not_a_polynomial)
+    );
+    // the graph evaluators still run it
+    let outputs: Vec<f32> = topology.to_simple_network().predict(&inputs).collect();
+    assert_eq!(outputs, vec![1. / 7.]);
+}
@@ -0,0 +1,415 @@
+//! Rust source generation for evolved networks.
+//!
+//! [`RustCodegen`] turns a [`PolyNetworkTopology`] into a standalone Rust function of
+//! the form `fn(&[f32; N]) -> [f32; M]`. The generated code only uses `f32` arithmetic
+//! from `core`, so a champion network can be deployed to targets that can't carry this
+//! crate or any of its dependencies. Powers are written out as repeated multiplication,
+//! and negative powers as its reciprocal, since `f32::powi` needs `std`.
+//!
+//! Two styles are available:
+//!
+//! - [`CodegenStyle::Unrolled`] evaluates the network neuron by neuron in topological
+//!   order. Its size is linear in the number of connections.
+//! - [`CodegenStyle::Expanded`] emits the closed-form polynomial of each output, as
+//!   produced by the expander. It is often shorter for shallow networks, but can grow
+//!   combinatorially for deep ones, and isn't available for outputs that raise a sum to
+//!   a negative power.
+//!
+//! # Example
+//!
+//! ```
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::codegen::{CodegenStyle, RustCodegen};
+//!
+//! let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+//!
+//! let source = RustCodegen::new("champion")
+//!     .with_style(CodegenStyle::Unrolled)
+//!     .generate(&topology)?;
+//! assert!(source.contains("pub fn champion(inputs: &[f32; 2]) -> [f32; 1]"));
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{collections::HashMap, fmt::Write as _};
+
+use uuid::Uuid;
+
+use crate::{
+    burn_net::expander::{PolyComponent, Polynomial},
+    error::Result,
+    prelude::*,
+};
+
+/// How a network is written out by [`RustCodegen`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CodegenStyle {
+    /// One `let` binding per neuron, in topological order.
+    #[default]
+    Unrolled,
+    /// One expanded polynomial per output.
+    Expanded,
+}
+
+/// Generates dependency-free Rust source for a network topology.
+#[derive(Clone, Debug)]
+pub struct RustCodegen {
+    fn_name: String,
+    style: CodegenStyle,
+}
+
+impl RustCodegen {
+    /// Create a generator for a function with the given name.
+    pub fn new(fn_name: impl Into<String>) -> Self {
+        Self {
+            fn_name: fn_name.into(),
+            style: CodegenStyle::default(),
+        }
+    }
+
+    /// Choose how the network body is written out.
+    pub fn with_style(mut self, style: CodegenStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Generate the source of a single public function evaluating `topology`.
+    ///
+    /// The generated function matches [`SimplePolyNetwork::predict`]: connections with an
+    /// exponent of 0 contribute their weight regardless of their source, and a neuron
+    /// without inputs evaluates to 0.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::InvalidIdentifier`] if the function name isn't a Rust
+    /// identifier, if a neuron lock is poisoned, or if the expanded style can't expand an
+    /// output. See [`PolyNetworkTopology::output_polynomials`].
+    pub fn generate(&self, topology: &PolyNetworkTopology) -> Result<String> {
+        if !is_identifier(&self.fn_name) {
+            return Err(PolyNeatError::InvalidIdentifier {
+                name: self.fn_name.clone(),
+            });
+        }
+        let info = topology.try_info()?;
+
+        let mut source = String::new();
+        writeln!(
+            source,
+            "// Generated by polynomial-neat. Evaluates a network with {} inputs, {} hidden neurons and {} outputs.",
+            info.num_inputs, info.num_hidden, info.num_outputs
+        )
+        .unwrap();
+        writeln!(source, "#[allow(unused_variables, clippy::all)]").unwrap();
+        writeln!(
+            source,
+            "pub fn {}(inputs: &[f32; {}]) -> [f32; {}] {{",
+            self.fn_name, info.num_inputs, info.num_outputs
+        )
+        .unwrap();
+
+        match self.style {
+            CodegenStyle::Unrolled => write_unrolled(&mut source, topology)?,
+            CodegenStyle::Expanded => write_expanded(&mut source, topology)?,
+        }
+
+        source.push_str("}\n");
+        Ok(source)
+    }
+}
+
+impl PolyNetworkTopology {
+    /// Generate an unrolled, dependency-free Rust function evaluating this topology.
+    ///
+    /// See [`RustCodegen`] for more options.
+    pub fn to_rust_source(&self, fn_name: &str) -> Result<String> {
+        RustCodegen::new(fn_name).generate(self)
+    }
+}
+
+fn literal(value: f32) -> String {
+    if value.is_nan() {
+        "f32::NAN".to_string()
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            "f32::INFINITY".to_string()
+        } else {
+            "f32::NEG_INFINITY".to_string()
+        }
+    } else {
+        format!("{value:?}_f32")
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+    "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && name != "_"
+        && !KEYWORDS.contains(&name)
+}
+
+/// `base` raised to `exponent` by repeated multiplication, e.g. `1.0_f32 / (x * x)` for
+/// an exponent of -2.
+fn power(base: &str, exponent: i32) -> String {
+    let product = vec![base; exponent.unsigned_abs() as usize].join(" * ");
+    match exponent {
+        0 => "1.0_f32".to_string(),
+        -1 => format!("1.0_f32 / {base}"),
+        e if e < 0 => format!("1.0_f32 / ({product})"),
+        _ => product,
+    }
+}
+
+fn write_unrolled(source: &mut String, topology: &PolyNetworkTopology) -> Result<()> {
+    let mut names: HashMap<Uuid, String> = HashMap::new();
+    let mut input_index = 0;
+    for neuron in topology.neurons() {
+        let neuron = neuron.read()?;
+        if neuron.is_input() {
+            names.insert(neuron.id(), format!("i{input_index}"));
+            input_index += 1;
+        }
+    }
+
+    let mut outputs = Vec::new();
+    let mut hidden_index = 0;
+    let mut listed_outputs: Vec<Uuid> = Vec::new();
+    for neuron in topology.neurons() {
+        let neuron = neuron.read()?;
+        if neuron.is_output() {
+            listed_outputs.push(neuron.id());
+        }
+    }
+
+    for neuron in topology.try_topological_order()? {
+        let neuron = neuron.read()?;
+        let id = neuron.id();
+
+        let Some(props) = neuron.props() else {
+            match names.get(&id) {
+                Some(name) => {
+                    let index = &name[1..];
+                    writeln!(source, "    let {name} = inputs[{index}];").unwrap();
+                }
+                None => {
+                    // an input that is no longer part of the network never receives a value
+                    let name = format!("d{}", names.len());
+                    writeln!(source, "    let {name} = 0.0_f32;").unwrap();
+                    names.insert(id, name);
+                }
+            }
+            continue;
+        };
+
+        let name = if neuron.is_output() && listed_outputs.contains(&id) {
+            let name = format!("o{}", listed_outputs.iter().position(|o| *o == id).unwrap());
+            outputs.push((name.clone(), id));
+            name
+        } else {
+            hidden_index += 1;
+            format!("h{}", hidden_index - 1)
+        };
+
+        let mut terms = Vec::with_capacity(props.num_inputs());
+        for input in props.inputs() {
+            let Some(input_neuron) = input.neuron() else {
+                continue;
+            };
+            let weight = literal(input.weight());
+            match input.exponent() {
+                0 => terms.push(weight),
+                exponent => {
+                    let source_name = &names[&input_neuron.read()?.id()];
+                    terms.push(format!("{weight} * {}", power(source_name, exponent)));
+                }
+            }
+        }
+
+        if terms.is_empty() {
+            writeln!(source, "    let {name} = 0.0_f32;").unwrap();
+        } else {
+            writeln!(source, "    let {name} = {};", terms.join(" + ")).unwrap();
+        }
+        names.insert(id, name);
+    }
+
+    outputs.sort_by_key(|(_, id)| listed_outputs.iter().position(|o| o == id));
+    let outputs: Vec<&str> = outputs.iter().map(|(name, _)| name.as_str()).collect();
+    writeln!(source, "    [{}]", outputs.join(", ")).unwrap();
+    Ok(())
+}
+
+fn write_expanded(source: &mut String, topology: &PolyNetworkTopology) -> Result<()> {
+    let outputs: Vec<String> = topology
+        .output_polynomials()?
+        .iter()
+        .map(expanded_output)
+        .collect();
+
+    writeln!(source, "    [").unwrap();
+    for output in outputs {
+        writeln!(source, "        {output},").unwrap();
+    }
+    writeln!(source, "    ]").unwrap();
+    Ok(())
+}
+
+fn degree(component: &PolyComponent<usize>) -> i32 {
+    component.operands().iter().map(|op| op.exponent()).sum()
+}
+
+/// Writes the terms of `polynomial` in order of descending degree, like
+/// [`Polynomial::expression`] does.
+fn expanded_output(polynomial: &Polynomial<usize>) -> String {
+    let mut components: Vec<&PolyComponent<usize>> = polynomial
+        .components()
+        .iter()
+        .filter(|component| component.weight() != 0.)
+        .collect();
+    if components.is_empty() {
+        return "0.0_f32".to_string();
+    }
+
+    components.sort_by(|a, b| {
+        degree(b)
+            .cmp(&degree(a))
+            .then_with(|| a.operands().cmp(b.operands()))
+    });
+
+    let terms: Vec<String> = components
+        .into_iter()
+        .map(|component| {
+            let mut term = literal(component.weight());
+            for operand in component.operands() {
+                let base = format!("inputs[{}]", operand.var());
+                write!(term, " * {}", power(&base, operand.exponent())).unwrap();
+            }
+            term
+        })
+        .collect();
+    terms.join(" + ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn topology() -> PolyNetworkTopology {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+
+        // 3x + 1
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 3., 1),
+                PolyInputTopology::downgrade(&y, 1., 0),
+            ],
+        ));
+
+        // 0.5(3x + 1)^2 - y
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&hidden, 0.5, 2),
+                PolyInputTopology::downgrade(&y, -1., 1),
+            ],
+        ));
+
+        // listed before the hidden neuron it depends on
+        PolyNetworkTopology::from_raw_parts(vec![x, y, output, hidden], MutationChances::none())
+    }
+
+    #[test]
+    fn generate_unrolled() {
+        let source = RustCodegen::new("net").generate(&topology()).unwrap();
+
+        assert_eq!(
+            source.lines().skip(2).collect::<Vec<_>>(),
+            vec![
+                "pub fn net(inputs: &[f32; 2]) -> [f32; 1] {",
+                "    let i0 = inputs[0];",
+                "    let i1 = inputs[1];",
+                "    let h0 = 3.0_f32 * i0 + 1.0_f32;",
+                "    let o0 = 0.5_f32 * h0 * h0 + -1.0_f32 * i1;",
+                "    [o0]",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn generate_expanded() {
+        let source = RustCodegen::new("net")
+            .with_style(CodegenStyle::Expanded)
+            .generate(&topology())
+            .unwrap();
+
+        // 4.5x^2 + 3x + 0.5 - y
+        assert!(source.contains(
+            "        4.5_f32 * inputs[0] * inputs[0] + 3.0_f32 * inputs[0] + -1.0_f32 * inputs[1] + 0.5_f32,\n"
+        ));
+    }
+
+    #[test]
+    fn negative_exponents_take_the_reciprocal() {
+        assert_eq!(power("h0", 3), "h0 * h0 * h0");
+        assert_eq!(power("h0", -1), "1.0_f32 / h0");
+        assert_eq!(power("h0", -2), "1.0_f32 / (h0 * h0)");
+        assert_eq!(power("h0", 0), "1.0_f32");
+    }
+
+    #[test]
+    fn expanded_style_refuses_non_polynomial_outputs() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let sum = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 1., 1),
+                PolyInputTopology::downgrade(&y, 1., 1),
+            ],
+        ));
+        // 1 / (x + y)
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&sum, 1., -1)],
+        ));
+        let topology =
+            PolyNetworkTopology::from_raw_parts(vec![x, y, sum, output], MutationChances::none());
+
+        assert_eq!(
+            RustCodegen::new("net")
+                .with_style(CodegenStyle::Expanded)
+                .generate(&topology)
+                .err(),
+            Some(PolyNeatError::NotAPolynomial { terms: 2 })
+        );
+        assert!(RustCodegen::new("net").generate(&topology).is_ok());
+    }
+
+    #[test]
+    fn function_names_must_be_identifiers() {
+        for name in ["", "_", "fn", "2net", "my-net", "net()"] {
+            assert_eq!(
+                RustCodegen::new(name).generate(&topology()).err(),
+                Some(PolyNeatError::InvalidIdentifier {
+                    name: name.to_string()
+                })
+            );
+        }
+        for name in ["net", "_net", "net_2", "Net"] {
+            assert!(RustCodegen::new(name).generate(&topology()).is_ok());
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! A lock-free CPU evaluator for polynomial networks.
+//!
+//! [`SimplePolyNetwork`] mirrors the topology's graph of `Arc<RwLock<..>>` neurons and
+//! walks it recursively on every call. For the small networks NEAT produces, the locks
+//! and rayon hand-offs cost far more than the arithmetic.
+//!
+//! [`CompiledNetwork`] sorts the neurons topologically once and flattens every
+//! connection into plain `source`/`weight`/`exponent` arrays. Evaluation is a single
+//! linear pass over those arrays that writes each neuron's value into a buffer slot.
+//! The network itself is immutable, so it can be shared freely between threads.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::prelude::*;
+//!
+//! let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+//! let network = topology.to_compiled_network()?;
+//!
+//! let outputs = network.predict(&[1.0, 0.5]);
+//! assert_eq!(outputs.len(), 1);
+//!
+//! // reuse a buffer across calls to avoid allocating
+//! let mut buffer = Vec::new();
+//! let mut outputs = [0.0; 1];
+//! network.predict_into(&[1.0, 0.5], &mut buffer, &mut outputs);
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use uuid::Uuid;
+
+use crate::{
+    core::{
+        numeric::{NumericFlag, NumericPolicy},
+        validation::InputValidation,
+    },
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
+
+/// A network flattened into arrays and evaluated in topological order.
+#[derive(Clone, Debug)]
+pub struct CompiledNetwork {
+    // buffer slot of each listed input, in the order inputs are passed to `predict`
+    input_slots: Vec<usize>,
+    // buffer slot of each output, in topology order
+    output_slots: Vec<usize>,
+    // neurons with inputs, in evaluation order
+    neurons: Vec<CompiledNeuron>,
+    // one entry per live connection, grouped by target neuron
+    sources: Vec<usize>,
+    weights: Vec<f32>,
+    exponents: Vec<i32>,
+    num_slots: usize,
+    // indices into output_slots of outputs without live inputs
+    disconnected_outputs: Vec<usize>,
+    numeric_policy: NumericPolicy,
+    // shared with the topology this was compiled from
+    numeric_flag: Arc<NumericFlag>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CompiledNeuron {
+    slot: usize,
+    connections: Range<usize>,
+}
+
+impl CompiledNetwork {
+    /// Compile a topology.
+    ///
+    /// Every reachable neuron gets a buffer slot equal to its position in
+    /// [`PolyNetworkTopology::topological_order`]. Input neurons that are no longer listed
+    /// in the topology keep a slot that stays 0, matching [`SimplePolyNetwork`].
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn from_topology(topology: &PolyNetworkTopology) -> Result<Self> {
+        let order = topology.try_topological_order()?;
+        let mut slots: HashMap<Uuid, usize> = HashMap::with_capacity(order.len());
+        for (slot, neuron) in order.iter().enumerate() {
+            slots.insert(neuron.read()?.id(), slot);
+        }
+        let slot_of = |id: Uuid| {
+            slots
+                .get(&id)
+                .copied()
+                .ok_or_else(|| PolyNeatError::UnknownNeuron { id: id.to_string() })
+        };
+
+        let mut input_slots = Vec::new();
+        let mut output_slots = Vec::new();
+        for neuron in topology.neurons() {
+            let neuron = neuron.read()?;
+            if neuron.is_input() {
+                input_slots.push(slot_of(neuron.id())?);
+            } else if neuron.is_output() {
+                output_slots.push(slot_of(neuron.id())?);
+            }
+        }
+
+        let mut neurons = Vec::new();
+        let mut sources = Vec::new();
+        let mut weights = Vec::new();
+        let mut exponents = Vec::new();
+
+        for (slot, neuron) in order.iter().enumerate() {
+            let neuron = neuron.read()?;
+            let Some(props) = neuron.props() else {
+                continue;
+            };
+
+            let start = sources.len();
+            for input in props.inputs() {
+                let Some(source) = input.neuron() else {
+                    continue;
+                };
+                sources.push(slot_of(source.read()?.id())?);
+                weights.push(input.weight());
+                exponents.push(input.exponent());
+            }
+
+            neurons.push(CompiledNeuron {
+                slot,
+                connections: start..sources.len(),
+            });
+        }
+
+        let disconnected_outputs = output_slots
+            .iter()
+            .enumerate()
+            .filter_map(|(output, slot)| {
+                neurons
+                    .iter()
+                    .find(|neuron| neuron.slot == *slot)
+                    .is_none_or(|neuron| neuron.connections.is_empty())
+                    .then_some(output)
+            })
+            .collect();
+
+        Ok(Self {
+            input_slots,
+            output_slots,
+            neurons,
+            sources,
+            weights,
+            exponents,
+            num_slots: order.len(),
+            disconnected_outputs,
+            numeric_policy: NumericPolicy::default(),
+            numeric_flag: Arc::clone(topology.numeric_flag()),
+        })
+    }
+
+    /// Set how non-finite values are handled. See [`NumericPolicy`].
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Self {
+        self.numeric_policy = policy;
+        self
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.input_slots.len()
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.output_slots.len()
+    }
+
+    /// The number of live connections that are evaluated on every pass.
+    pub fn num_connections(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// The buffer length [`predict_into`](Self::predict_into) works with.
+    pub fn num_slots(&self) -> usize {
+        self.num_slots
+    }
+
+    /// Perform a forward pass, allocating a fresh buffer.
+    ///
+    /// # Note
+    /// Like [`SimplePolyNetwork::predict`], extra inputs are ignored and missing inputs
+    /// are treated as 0.
+    pub fn predict(&self, inputs: &[f32]) -> Vec<f32> {
+        let mut buffer = Vec::with_capacity(self.num_slots);
+        let mut outputs = vec![0.; self.output_slots.len()];
+        self.predict_into(inputs, &mut buffer, &mut outputs);
+        outputs
+    }
+
+    /// Perform a forward pass after checking `inputs` against `validation`.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Predict`] describing the first failed check.
+    pub fn try_predict(&self, inputs: &[f32], validation: &InputValidation) -> Result<Vec<f32>> {
+        validation.validate(inputs, self.num_inputs(), &self.disconnected_outputs)?;
+        Ok(self.predict(inputs))
+    }
+
+    /// Perform a forward pass using a caller-provided buffer.
+    ///
+    /// `buffer` is resized as needed and can be reused between calls, even across
+    /// different networks. Only the first `outputs.len()` outputs are written.
+    pub fn predict_into(&self, inputs: &[f32], buffer: &mut Vec<f32>, outputs: &mut [f32]) {
+        buffer.clear();
+        buffer.resize(self.num_slots, 0.);
+
+        for (slot, value) in self.input_slots.iter().zip(inputs) {
+            buffer[*slot] = *value;
+        }
+
+        for neuron in self.neurons.iter() {
+            let mut sum = 0.;
+            for connection in neuron.connections.clone() {
+                let weight = self.weights[connection];
+                sum += match self.exponents[connection] {
+                    // x^0 = 1, so the source doesn't matter
+                    0 => weight,
+                    1 => buffer[self.sources[connection]] * weight,
+                    exponent => {
+                        self.numeric_policy
+                            .powi(buffer[self.sources[connection]], exponent)
+                            * weight
+                    }
+                };
+            }
+            buffer[neuron.slot] = sum;
+        }
+
+        let written = outputs.len().min(self.output_slots.len());
+        for (output, slot) in outputs.iter_mut().zip(self.output_slots.iter()) {
+            *output = buffer[*slot];
+        }
+        self.numeric_policy
+            .apply(&mut outputs[..written], &self.numeric_flag);
+    }
+}
+
+impl PolyNetworkTopology {
+    /// Compile this topology into a lock-free [`CompiledNetwork`].
+    ///
+    /// See [`CompiledNetwork::from_topology`].
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn to_compiled_network(&self) -> Result<CompiledNetwork> {
+        CompiledNetwork::from_topology(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            let tolerance = 1e-4 * expected.abs().max(1.);
+            assert!(
+                actual == expected
+                    || (actual.is_nan() && expected.is_nan())
+                    || (actual - expected).abs() <= tolerance,
+                "{actual} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn evaluates_in_topological_order() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+
+        // 3x + y
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 3., 1),
+                PolyInputTopology::downgrade(&y, 1., 1),
+            ],
+        ));
+        // (3x + y)^2 + 4
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&hidden, 1., 2),
+                PolyInputTopology::downgrade(&x, 4., 0),
+            ],
+        ));
+
+        // the output is listed before the hidden neuron it depends on
+        let topology = PolyNetworkTopology::from_raw_parts(
+            vec![x, y, output, hidden],
+            MutationChances::none(),
+        );
+        let network = topology.to_compiled_network().unwrap();
+
+        assert_eq!(network.num_inputs(), 2);
+        assert_eq!(network.num_outputs(), 1);
+        assert_eq!(network.num_connections(), 4);
+        assert_eq!(network.predict(&[1., 2.]), vec![29.]);
+        // missing inputs are 0, extra inputs are ignored
+        assert_eq!(network.predict(&[1.]), vec![13.]);
+        assert_eq!(network.predict(&[1., 2., 100.]), vec![29.]);
+    }
+
+    #[test]
+    fn matches_simple_network() {
+        let mut rng = StdRng::seed_from_u64(4412);
+        let mut buffer = Vec::new();
+
+        for _ in 0..20 {
+            let mut topology = PolyNetworkTopology::new(3, 2, MutationChances::new(70), &mut rng);
+            for _ in 0..15 {
+                topology = topology.replicate(&mut rng);
+            }
+
+            let simple = topology.to_simple_network();
+            let compiled = topology.to_compiled_network().unwrap();
+
+            for inputs in [[1., 0.5, -2.], [0.25, -1., 3.], [0., 0., 0.]] {
+                let expected: Vec<f32> = simple.predict(&inputs).collect();
+                let mut outputs = vec![0.; compiled.num_outputs()];
+                compiled.predict_into(&inputs, &mut buffer, &mut outputs);
+                assert_close(&outputs, &expected);
+            }
+        }
+    }
+}
@@ -179,6 +179,24 @@ impl<I> PolyInput<I> {
         self.weight += by;
     }
 
+    /// Replaces the connection weight.
+    ///
+    /// This is used by local search steps that compute new weights directly,
+    /// such as gradient-based fine-tuning.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use polynomial_neat::core::input::PolyInput;
+    ///
+    /// let mut input = PolyInput::new(1, 0.5, 1);
+    /// input.set_weight(-2.0);
+    /// assert_eq!(input.weight(), -2.0);
+    /// ```
+    pub fn set_weight(&mut self, weight: f32) {
+        self.weight = weight;
+    }
+
     /// Returns the exponent applied to the input value.
     ///
     /// # Example
@@ -2,3 +2,5 @@ pub mod activation;
 pub mod input;
 //pub mod neuron;
 pub mod neuron_type;
+pub mod numeric;
+pub mod validation;
@@ -67,6 +67,10 @@ impl<I> PolyProps<I> {
         self.inputs.as_slice()
     }
 
+    pub fn inputs_mut(&mut self) -> &mut [PolyInput<I>] {
+        self.inputs.as_mut_slice()
+    }
+
     pub fn props_type(&self) -> PropsType {
         self.props_type
     }
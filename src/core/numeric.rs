@@ -0,0 +1,193 @@
+//! What to do when a network produces infinities or NaN.
+//!
+//! Negative exponents are allowed, so `0^-n = inf` is a legitimate result of a genome
+//! (see `ensure_inf_possible`). Left alone, such values poison fitness scores and make the
+//! CPU and Burn evaluators disagree in ways that are hard to tell apart from real bugs.
+//!
+//! A [`NumericPolicy`] is set on an evaluator with `with_numeric_policy` and decides how
+//! those values are handled:
+//!
+//! * [`Propagate`](NumericPolicy::Propagate) keeps plain IEEE semantics. This is the
+//!   default.
+//! * [`Clamp`](NumericPolicy::Clamp) clamps infinite outputs to `±f32::MAX` and maps NaN
+//!   outputs to 0.
+//! * [`Epsilon`](NumericPolicy::Epsilon) replaces a zero base with the given epsilon
+//!   before raising it to a negative exponent. Only the evaluators that keep their hidden
+//!   neurons support it; `BurnNetwork` and `PopulationNetwork` refuse it with
+//!   [`UnsupportedPolicy`](crate::error::PolyNeatError::UnsupportedPolicy).
+//! * [`Sentinel`](NumericPolicy::Sentinel) replaces non-finite outputs with the given
+//!   value and raises the [`NumericFlag`] of the genome.
+//!
+//! The flag lives on the [`PolyNetworkTopology`] and is shared with every evaluator built
+//! from it, so a fitness driver can penalise the genome after the fact without having to
+//! inspect the outputs itself.
+//!
+//! # Example
+//!
+//! ```
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::core::numeric::NumericPolicy;
+//! use uuid::Uuid;
+//!
+//! // 1 / x
+//! let x = PolyNeuronTopology::new_arc(Uuid::new_v4(), None);
+//! let output = PolyNeuronTopology::new_arc(
+//!     Uuid::new_v4(),
+//!     Some(PolyNeuronPropsTopology::output(vec![
+//!         PolyInputTopology::downgrade(&x, 1., -1),
+//!     ])),
+//! );
+//! let topology = PolyNetworkTopology::from_raw_parts(vec![x, output], MutationChances::none());
+//!
+//! let network = topology
+//!     .to_compiled_network()?
+//!     .with_numeric_policy(NumericPolicy::Sentinel(-1.));
+//!
+//! assert_eq!(network.predict(&[0.5]), vec![2.]);
+//! assert!(!topology.numeric_flag().is_raised());
+//!
+//! assert_eq!(network.predict(&[0.]), vec![-1.]);
+//! assert!(topology.numeric_flag().is_raised());
+//! assert_eq!(topology.numeric_flag().penalize(0.8, 0.5), 0.3);
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(doc)]
+use crate::prelude::*;
+
+/// How an evaluator handles non-finite values.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NumericPolicy {
+    /// Keep IEEE semantics: infinities and NaN flow through to the outputs.
+    #[default]
+    Propagate,
+    /// Clamp infinite outputs to `±f32::MAX`, and map NaN outputs to 0.
+    Clamp,
+    /// Use this value instead of a zero base raised to a negative exponent.
+    ///
+    /// It applies to every connection, so it needs the hidden sums. The expanded Burn
+    /// evaluators have none left and refuse it.
+    Epsilon(f32),
+    /// Replace non-finite outputs with this value and raise the genome's [`NumericFlag`].
+    Sentinel(f32),
+}
+
+impl NumericPolicy {
+    /// Raise `base` to `exponent`, replacing a zero base under
+    /// [`Epsilon`](Self::Epsilon).
+    pub fn powi(&self, base: f32, exponent: i32) -> f32 {
+        match self {
+            NumericPolicy::Epsilon(epsilon) if base == 0. && exponent < 0 => epsilon.powi(exponent),
+            _ => base.powi(exponent),
+        }
+    }
+
+    /// Apply the policy to a single output value.
+    ///
+    /// # Returns
+    /// The value to report, and whether it should raise the genome's flag.
+    pub fn output(&self, value: f32) -> (f32, bool) {
+        if value.is_finite() {
+            return (value, false);
+        }
+        match self {
+            NumericPolicy::Propagate | NumericPolicy::Epsilon(_) => (value, false),
+            NumericPolicy::Clamp if value.is_nan() => (0., false),
+            NumericPolicy::Clamp => (value.clamp(-f32::MAX, f32::MAX), false),
+            NumericPolicy::Sentinel(sentinel) => (*sentinel, true),
+        }
+    }
+
+    /// Apply the policy to a set of outputs in place, raising `flag` for every value
+    /// that was replaced by a sentinel.
+    pub fn apply(&self, outputs: &mut [f32], flag: &NumericFlag) {
+        let mut flagged = 0;
+        for value in outputs.iter_mut() {
+            let (applied, raise) = self.output(*value);
+            *value = applied;
+            flagged += raise as usize;
+        }
+        flag.raise(flagged);
+    }
+}
+
+/// Marks a genome whose outputs had to be replaced by a [`NumericPolicy::Sentinel`].
+///
+/// The flag counts the replaced outputs. It is shared between a topology and every
+/// evaluator built from it, and can be raised concurrently.
+#[derive(Debug, Default)]
+pub struct NumericFlag(AtomicUsize);
+
+impl NumericFlag {
+    /// Record `count` replaced outputs.
+    pub fn raise(&self, count: usize) {
+        if count > 0 {
+            self.0.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_raised(&self) -> bool {
+        self.count() > 0
+    }
+
+    /// The number of outputs replaced so far.
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    /// Subtract `penalty` from `fitness` if the flag is raised.
+    pub fn penalize(&self, fitness: f32, penalty: f32) -> f32 {
+        if self.is_raised() {
+            fitness - penalty
+        } else {
+            fitness
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies_handle_non_finite_outputs() {
+        let values = [1.5, f32::INFINITY, f32::NEG_INFINITY, f32::NAN];
+
+        let apply = |policy: NumericPolicy| {
+            let flag = NumericFlag::default();
+            let mut outputs = values;
+            policy.apply(&mut outputs, &flag);
+            (outputs, flag.count())
+        };
+
+        let (outputs, flagged) = apply(NumericPolicy::Propagate);
+        assert_eq!(outputs[..3], values[..3]);
+        assert!(outputs[3].is_nan());
+        assert_eq!(flagged, 0);
+
+        assert_eq!(
+            apply(NumericPolicy::Clamp),
+            ([1.5, f32::MAX, -f32::MAX, 0.], 0)
+        );
+        assert_eq!(
+            apply(NumericPolicy::Sentinel(-7.)),
+            ([1.5, -7., -7., -7.], 3)
+        );
+    }
+
+    #[test]
+    fn epsilon_only_replaces_zero_bases_of_negative_exponents() {
+        let policy = NumericPolicy::Epsilon(0.5);
+
+        assert_eq!(policy.powi(0., -2), 4.);
+        assert_eq!(policy.powi(0., 2), 0.);
+        assert_eq!(policy.powi(2., -1), 0.5);
+        assert_eq!(NumericPolicy::Propagate.powi(0., -1), f32::INFINITY);
+    }
+}
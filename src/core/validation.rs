@@ -0,0 +1,209 @@
+//! Checked evaluation of networks.
+//!
+//! The plain `predict` methods are lenient: extra inputs are ignored and missing inputs
+//! read as 0. That is convenient during evolution, but it hides wrongly shaped data.
+//! Every evaluator also offers a `try_predict` that checks its inputs against an
+//! [`InputValidation`] first and reports problems as a [`PredictError`], wrapped in
+//! [`PolyNeatError::Predict`].
+//!
+//! # Example
+//!
+//! ```
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::core::validation::{InputValidation, PredictError};
+//!
+//! let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+//! let network = topology.to_simple_network();
+//! let validation = InputValidation::default().with_finite_inputs(true);
+//!
+//! assert_eq!(
+//!     network.try_predict(&[1.0], &validation),
+//!     Err(PolyNeatError::Predict(PredictError::InputLength { expected: 2, actual: 1 }))
+//! );
+//! assert!(matches!(
+//!     network.try_predict(&[1.0, f32::NAN], &validation),
+//!     Err(PolyNeatError::Predict(PredictError::NonFiniteInput { index: 1, .. }))
+//! ));
+//! ```
+
+use std::fmt;
+
+use crate::{error::Result, prelude::*};
+
+/// Why a checked prediction was refused.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PredictError {
+    /// The number of inputs doesn't match the number of input neurons.
+    InputLength { expected: usize, actual: usize },
+    /// An input is NaN or infinite, and [`InputValidation::with_finite_inputs`] is set.
+    NonFiniteInput { index: usize, value: f32 },
+    /// An output neuron has no live inputs, so it can only ever produce 0.
+    DisconnectedOutput { output: usize },
+}
+
+impl fmt::Display for PredictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PredictError::InputLength { expected, actual } => {
+                write!(f, "expected {expected} inputs, got {actual}")
+            }
+            PredictError::NonFiniteInput { index, value } => {
+                write!(f, "input {index} is not finite ({value})")
+            }
+            PredictError::DisconnectedOutput { output } => {
+                write!(f, "output {output} has no inputs")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PredictError {}
+
+/// Which checks a `try_predict` call performs.
+///
+/// By default the input length and disconnected outputs are checked, and non-finite
+/// inputs are allowed, since exponents of a network can legitimately produce and
+/// consume infinities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputValidation {
+    exact_length: bool,
+    finite_inputs: bool,
+    connected_outputs: bool,
+}
+
+impl Default for InputValidation {
+    fn default() -> Self {
+        Self {
+            exact_length: true,
+            finite_inputs: false,
+            connected_outputs: true,
+        }
+    }
+}
+
+impl InputValidation {
+    /// Perform no checks at all. `try_predict` then behaves like `predict`.
+    pub fn none() -> Self {
+        Self {
+            exact_length: false,
+            finite_inputs: false,
+            connected_outputs: false,
+        }
+    }
+
+    /// Require exactly one input per input neuron.
+    pub fn with_exact_length(mut self, check: bool) -> Self {
+        self.exact_length = check;
+        self
+    }
+
+    /// Refuse NaN and infinite inputs.
+    pub fn with_finite_inputs(mut self, check: bool) -> Self {
+        self.finite_inputs = check;
+        self
+    }
+
+    /// Refuse to evaluate a network with an output that has no live inputs.
+    pub fn with_connected_outputs(mut self, check: bool) -> Self {
+        self.connected_outputs = check;
+        self
+    }
+
+    /// Run the configured checks.
+    ///
+    /// # Arguments
+    /// * `inputs` - The values about to be fed to the network
+    /// * `num_inputs` - The number of input neurons of the network
+    /// * `disconnected_outputs` - Indices of outputs without live inputs
+    pub fn validate(
+        &self,
+        inputs: &[f32],
+        num_inputs: usize,
+        disconnected_outputs: &[usize],
+    ) -> Result<(), PredictError> {
+        if self.exact_length && inputs.len() != num_inputs {
+            return Err(PredictError::InputLength {
+                expected: num_inputs,
+                actual: inputs.len(),
+            });
+        }
+
+        if self.finite_inputs
+            && let Some((index, value)) = inputs.iter().enumerate().find(|(_, v)| !v.is_finite())
+        {
+            return Err(PredictError::NonFiniteInput {
+                index,
+                value: *value,
+            });
+        }
+
+        if self.connected_outputs
+            && let Some(output) = disconnected_outputs.first()
+        {
+            return Err(PredictError::DisconnectedOutput { output: *output });
+        }
+
+        Ok(())
+    }
+}
+
+impl PolyNetworkTopology {
+    /// Indices of the outputs that have no live inputs.
+    ///
+    /// Such an output evaluates to 0 no matter what the network is fed.
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn disconnected_outputs(&self) -> Result<Vec<usize>> {
+        let mut disconnected = Vec::new();
+        let mut output = 0;
+        for neuron in self.neurons() {
+            let neuron = neuron.read()?;
+            if !neuron.is_output() {
+                continue;
+            }
+            if neuron
+                .props()
+                .is_none_or(|props| props.inputs().iter().all(|i| i.neuron().is_none()))
+            {
+                disconnected.push(output);
+            }
+            output += 1;
+        }
+        Ok(disconnected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_run_in_order() {
+        let validation = InputValidation::default().with_finite_inputs(true);
+
+        assert_eq!(validation.validate(&[1., 2.], 2, &[]), Ok(()));
+        assert_eq!(
+            validation.validate(&[1., 2., 3.], 2, &[0]),
+            Err(PredictError::InputLength {
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            validation.validate(&[1., f32::INFINITY], 2, &[0]),
+            Err(PredictError::NonFiniteInput {
+                index: 1,
+                value: f32::INFINITY
+            })
+        );
+        assert_eq!(
+            validation.validate(&[1., 2.], 2, &[1]),
+            Err(PredictError::DisconnectedOutput { output: 1 })
+        );
+        assert_eq!(
+            InputValidation::none().validate(&[f32::NAN], 2, &[1]),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,530 @@
+//! Differential testing between evaluators.
+//!
+//! A topology can be run by [`SimplePolyNetwork`], [`CompiledNetwork`], its expanded
+//! [`Polynomial`](crate::burn_net::expander::Polynomial)s, [`BurnNetwork`],
+//! [`LayeredNetwork`] and [`PopulationNetwork`]. They are supposed to agree, and
+//! [`cross_check`] checks that they do: it runs every evaluator on the same inputs and
+//! compares each output against [`SimplePolyNetwork`], the reference.
+//!
+//! When any evaluator disagrees, the topology is shrunk while the disagreement persists,
+//! by dropping outputs, hidden neurons and connections one at a time. The smallest
+//! topology that still disagrees is kept as [`CrossCheckReport::reproducer`].
+//!
+//! Evaluators living outside this crate, e.g. generated code or an experimental kernel,
+//! can be checked alongside the built-in ones with [`cross_check_including`].
+//!
+//! The report doubles as a test assertion for downstream crates:
+//!
+//! ```rust
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::cross_check::cross_check;
+//!
+//! let mut rng = rand::rng();
+//! let mut topology = PolyNetworkTopology::new(2, 2, MutationChances::new(50), &mut rng);
+//! for _ in 0..5 {
+//!     topology = topology.replicate(&mut rng);
+//! }
+//!
+//! let report = cross_check(&topology, &[0.5, 2.0], 1e-3)?;
+//! println!("{report}");
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{fmt, slice};
+
+use burn::{backend::NdArray, prelude::Backend};
+
+use crate::{
+    burn_net::{
+        layered::LayeredNetwork, network::BurnNetwork, output_polynomials,
+        population::PopulationNetwork,
+    },
+    error::Result,
+    prelude::*,
+};
+
+/// An evaluator compared by [`cross_check`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Evaluator {
+    /// [`SimplePolyNetwork`], the reference every other evaluator is compared against.
+    Simple,
+    /// [`CompiledNetwork`].
+    Compiled,
+    /// The expanded output polynomials, evaluated on the host.
+    Polynomial,
+    /// [`BurnNetwork`].
+    Burn,
+    /// [`LayeredNetwork`].
+    Layered,
+    /// [`PopulationNetwork`], holding just this genome.
+    Population,
+    /// A [`CustomEvaluator`] passed to [`cross_check_including`], by name.
+    Custom(&'static str),
+}
+
+impl Evaluator {
+    /// Every built-in evaluator, reference first.
+    pub const ALL: [Evaluator; 6] = [
+        Evaluator::Simple,
+        Evaluator::Compiled,
+        Evaluator::Polynomial,
+        Evaluator::Burn,
+        Evaluator::Layered,
+        Evaluator::Population,
+    ];
+}
+
+impl fmt::Display for Evaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Evaluator::Simple => "simple",
+            Evaluator::Compiled => "compiled",
+            Evaluator::Polynomial => "polynomial",
+            Evaluator::Burn => "burn",
+            Evaluator::Layered => "layered",
+            Evaluator::Population => "population",
+            Evaluator::Custom(name) => name,
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// An evaluator outside this crate, compared by [`cross_check_including`].
+///
+/// The function gets the topology and the padded inputs and returns one value per output.
+/// If it fails, the evaluator is listed in [`CrossCheckReport::skipped`].
+pub type CustomEvaluator<'a> = (
+    &'static str,
+    &'a dyn Fn(&PolyNetworkTopology, &[f32]) -> Result<Vec<f32>>,
+);
+
+/// One output on which an evaluator disagrees with the reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mismatch {
+    pub evaluator: Evaluator,
+    /// Index of the output, in topology order.
+    pub output: usize,
+    /// The reference value.
+    pub expected: f32,
+    /// The evaluator's value, `None` if it produced fewer outputs than the reference.
+    pub actual: Option<f32>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.actual {
+            Some(actual) => write!(
+                f,
+                "{} output {}: expected {}, got {actual}",
+                self.evaluator, self.output, self.expected
+            ),
+            None => write!(
+                f,
+                "{} output {}: expected {}, got nothing",
+                self.evaluator, self.output, self.expected
+            ),
+        }
+    }
+}
+
+/// The outcome of [`cross_check`].
+#[derive(Debug)]
+pub struct CrossCheckReport {
+    inputs: Vec<f32>,
+    tolerance: f32,
+    outputs: Vec<(Evaluator, Vec<f32>)>,
+    skipped: Vec<(Evaluator, PolyNeatError)>,
+    mismatches: Vec<Mismatch>,
+    reproducer: Option<PolyNetworkTopology>,
+}
+
+impl CrossCheckReport {
+    /// Whether every evaluator that could be built agreed with the reference.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    /// The inputs every evaluator was run on, padded with zeros to the number of inputs.
+    pub fn inputs(&self) -> &[f32] {
+        &self.inputs
+    }
+
+    /// The outputs of every evaluator that could be built.
+    pub fn outputs(&self) -> &[(Evaluator, Vec<f32>)] {
+        &self.outputs
+    }
+
+    /// The outputs of `evaluator`, if it could be built.
+    pub fn outputs_of(&self, evaluator: Evaluator) -> Option<&[f32]> {
+        self.outputs
+            .iter()
+            .find(|(e, _)| *e == evaluator)
+            .map(|(_, outputs)| outputs.as_slice())
+    }
+
+    /// Evaluators that couldn't be built, and why.
+    ///
+    /// The expansion-based evaluators reject topologies whose outputs depend on an
+    /// unlisted input neuron, which the graph evaluators read as 0, and outputs that
+    /// raise a sum to a negative power, which have no polynomial form. Those are
+    /// documented differences rather than mismatches.
+    pub fn skipped(&self) -> &[(Evaluator, PolyNeatError)] {
+        &self.skipped
+    }
+
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    /// The smallest topology found that still produces a mismatch on the same inputs.
+    ///
+    /// `None` if the topology was consistent.
+    pub fn reproducer(&self) -> Option<&PolyNetworkTopology> {
+        self.reproducer.as_ref()
+    }
+
+    /// Panic with the full report if any evaluator disagreed.
+    ///
+    /// # Panics
+    /// Panics if the report has mismatches.
+    #[track_caller]
+    pub fn assert_consistent(&self) {
+        assert!(self.is_consistent(), "evaluators disagree\n{self}");
+    }
+}
+
+impl fmt::Display for CrossCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "inputs {:?}, tolerance {}", self.inputs, self.tolerance)?;
+        for (evaluator, outputs) in self.outputs.iter() {
+            writeln!(f, "  {evaluator:>10}: {outputs:?}")?;
+        }
+        for (evaluator, error) in self.skipped.iter() {
+            writeln!(f, "  {evaluator:>10}: skipped, {error}")?;
+        }
+
+        if self.mismatches.is_empty() {
+            return write!(f, "all evaluators agree");
+        }
+        writeln!(f, "{} mismatches:", self.mismatches.len())?;
+        for mismatch in self.mismatches.iter() {
+            writeln!(f, "  {mismatch}")?;
+        }
+        if let Some(reproducer) = &self.reproducer {
+            if let Ok(info) = reproducer.try_info() {
+                writeln!(
+                    f,
+                    "reproducer: {} inputs, {} hidden, {} outputs",
+                    info.num_inputs, info.num_hidden, info.num_outputs
+                )?;
+            }
+            write!(
+                f,
+                "{}",
+                reproducer.to_dot(&crate::topology::dot::DotOptions::default())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every evaluator on `inputs` and compare them against [`SimplePolyNetwork`], using
+/// the NdArray backend for the Burn evaluators.
+///
+/// See [`cross_check_with`].
+///
+/// # Errors
+/// Fails if the reference network can't be built.
+pub fn cross_check(
+    topology: &PolyNetworkTopology,
+    inputs: &[f32],
+    tolerance: f32,
+) -> Result<CrossCheckReport> {
+    cross_check_with::<NdArray>(topology, inputs, tolerance, Default::default())
+}
+
+/// Run every evaluator on `inputs` and compare them against [`SimplePolyNetwork`].
+///
+/// Values agree if they are both NaN, equal, or within `tolerance` relative to the
+/// reference, with an absolute floor of `tolerance` for values below 1. Missing inputs are
+/// treated as 0. If any evaluator disagrees, the topology is minimised into
+/// [`CrossCheckReport::reproducer`].
+///
+/// # Arguments
+/// * `topology` - The genome to check. It is only read, never modified.
+/// * `inputs` - One sample of input values
+/// * `tolerance` - The largest relative difference still considered equal
+/// * `device` - Where the Burn evaluators run
+///
+/// # Errors
+/// Fails if the reference network can't be built, e.g. because a neuron lock is poisoned.
+/// Other evaluators that can't be built are listed in [`CrossCheckReport::skipped`].
+pub fn cross_check_with<B: Backend>(
+    topology: &PolyNetworkTopology,
+    inputs: &[f32],
+    tolerance: f32,
+    device: B::Device,
+) -> Result<CrossCheckReport> {
+    cross_check_including::<B>(topology, inputs, tolerance, device, &[])
+}
+
+/// Like [`cross_check_with`], but also compares each of the `custom` evaluators against
+/// [`SimplePolyNetwork`], and keeps them when minimising a disagreement.
+///
+/// # Example
+///
+/// ```rust
+/// use burn::backend::NdArray;
+/// use polynomial_neat::prelude::*;
+/// use polynomial_neat::cross_check::{Evaluator, cross_check_including};
+///
+/// let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+///
+/// // an evaluator that always answers 42
+/// let constant = |_: &PolyNetworkTopology, _: &[f32]| Ok(vec![42.]);
+/// let report = cross_check_including::<NdArray>(
+///     &topology,
+///     &[0.5, 2.0],
+///     1e-3,
+///     Default::default(),
+///     &[("constant", &constant)],
+/// )?;
+/// assert_eq!(report.outputs_of(Evaluator::Custom("constant")), Some(&[42.][..]));
+/// # Ok::<(), PolyNeatError>(())
+/// ```
+///
+/// # Errors
+/// Fails if the reference network can't be built.
+pub fn cross_check_including<B: Backend>(
+    topology: &PolyNetworkTopology,
+    inputs: &[f32],
+    tolerance: f32,
+    device: B::Device,
+    custom: &[CustomEvaluator<'_>],
+) -> Result<CrossCheckReport> {
+    let mut report = compare::<B>(topology, inputs, tolerance, &device, custom)?;
+    if !report.is_consistent() {
+        report.reproducer = Some(minimise::<B>(
+            topology,
+            &report.inputs,
+            tolerance,
+            &device,
+            custom,
+        )?);
+    }
+    Ok(report)
+}
+
+fn compare<B: Backend>(
+    topology: &PolyNetworkTopology,
+    inputs: &[f32],
+    tolerance: f32,
+    device: &B::Device,
+    custom: &[CustomEvaluator<'_>],
+) -> Result<CrossCheckReport> {
+    let mut inputs = inputs.to_vec();
+    inputs.resize(inputs.len().max(topology.try_info()?.num_inputs), 0.);
+
+    let reference: Vec<f32> = SimplePolyNetwork::from_topology(topology)?
+        .predict(&inputs)
+        .collect();
+
+    let mut report = CrossCheckReport {
+        inputs,
+        tolerance,
+        outputs: vec![(Evaluator::Simple, reference.clone())],
+        skipped: Vec::new(),
+        mismatches: Vec::new(),
+        reproducer: None,
+    };
+
+    let evaluators = Evaluator::ALL
+        .into_iter()
+        .skip(1)
+        .chain(custom.iter().map(|(name, _)| Evaluator::Custom(name)));
+    for evaluator in evaluators {
+        let outputs = match evaluate::<B>(evaluator, topology, &report.inputs, device, custom) {
+            Ok(outputs) => outputs,
+            Err(error) => {
+                report.skipped.push((evaluator, error));
+                continue;
+            }
+        };
+
+        for (output, expected) in reference.iter().copied().enumerate() {
+            let actual = outputs.get(output).copied();
+            if !actual.is_some_and(|actual| agrees(expected, actual, tolerance)) {
+                report.mismatches.push(Mismatch {
+                    evaluator,
+                    output,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        report.outputs.push((evaluator, outputs));
+    }
+
+    Ok(report)
+}
+
+fn evaluate<B: Backend>(
+    evaluator: Evaluator,
+    topology: &PolyNetworkTopology,
+    inputs: &[f32],
+    device: &B::Device,
+    custom: &[CustomEvaluator<'_>],
+) -> Result<Vec<f32>> {
+    let outputs = match evaluator {
+        Evaluator::Simple => SimplePolyNetwork::from_topology(topology)?
+            .predict(inputs)
+            .collect(),
+        Evaluator::Compiled => CompiledNetwork::from_topology(topology)?.predict(inputs),
+        Evaluator::Polynomial => output_polynomials(topology)?
+            .iter()
+            .map(|polynomial| polynomial.evaluate(inputs))
+            .collect(),
+        Evaluator::Burn => BurnNetwork::<B>::from_topology(topology, device.clone())?
+            .predict_batch_rows(&[inputs])
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap(),
+        Evaluator::Layered => LayeredNetwork::<B>::from_topology(topology, device.clone())?
+            .predict_batch_rows(&[inputs])
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap(),
+        Evaluator::Population => {
+            PopulationNetwork::<B>::from_topologies(slice::from_ref(topology), device.clone())?
+                .predict_batch_rows(&[inputs])
+                .to_vecs()
+                .remove(0)
+                .into_iter()
+                .map(|samples| samples[0])
+                .collect()
+        }
+        Evaluator::Custom(name) => {
+            let (_, evaluate) = custom
+                .iter()
+                .find(|(custom, _)| *custom == name)
+                .expect("custom evaluators are only named by compare");
+            evaluate(topology, inputs)?
+        }
+    };
+    Ok(outputs)
+}
+
+fn agrees(expected: f32, actual: f32, tolerance: f32) -> bool {
+    if expected.is_nan() || actual.is_nan() {
+        return expected.is_nan() && actual.is_nan();
+    }
+    if expected.is_infinite() || actual.is_infinite() {
+        return expected == actual;
+    }
+    (expected - actual).abs() <= tolerance * expected.abs().max(1.)
+}
+
+/// Shrink `topology` while it keeps producing a mismatch.
+///
+/// Each round tries, in order, dropping an output (as long as one is left), a hidden
+/// neuron, and a single connection, and restarts from the first reduction that still
+/// mismatches. Input neurons are kept so the inputs keep their meaning.
+fn minimise<B: Backend>(
+    topology: &PolyNetworkTopology,
+    inputs: &[f32],
+    tolerance: f32,
+    device: &B::Device,
+    custom: &[CustomEvaluator<'_>],
+) -> Result<PolyNetworkTopology> {
+    let mut current = topology.try_deep_clone()?;
+
+    'shrink: loop {
+        for candidate in reductions(&current)? {
+            if !compare::<B>(&candidate, inputs, tolerance, device, custom)?.is_consistent() {
+                // deep cloning drops the connections to removed neurons
+                current = candidate.try_deep_clone()?;
+                continue 'shrink;
+            }
+        }
+        return Ok(current);
+    }
+}
+
+/// Every topology one step smaller than `topology`.
+fn reductions(topology: &PolyNetworkTopology) -> Result<Vec<PolyNetworkTopology>> {
+    let info = topology.try_info()?;
+    let mut outputs = Vec::new();
+    let mut hidden = Vec::new();
+    let mut connections = Vec::new();
+
+    for (index, neuron) in topology.neurons().iter().enumerate() {
+        let neuron = neuron.read()?;
+        if neuron.is_output() && info.num_outputs > 1 {
+            outputs.push(index);
+        } else if neuron.is_hidden() {
+            hidden.push(index);
+        }
+        if let Some(props) = neuron.props() {
+            connections.extend((0..props.inputs().len()).map(|input| (index, input)));
+        }
+    }
+
+    let mut candidates = Vec::with_capacity(outputs.len() + hidden.len() + connections.len());
+    for index in outputs.into_iter().chain(hidden) {
+        let clone = topology.try_deep_clone()?;
+        let mut neurons = clone.neurons().clone();
+        neurons.remove(index);
+        candidates.push(PolyNetworkTopology::from_raw_parts(
+            neurons,
+            *clone.mutation_chances(),
+        ));
+    }
+    for (index, input) in connections {
+        let clone = topology.try_deep_clone()?;
+        if let Some(props) = clone.neurons()[index].write()?.props_mut() {
+            props.trim_inputs(&[input]);
+        }
+        candidates.push(clone);
+    }
+
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn tolerance_is_relative_above_one() {
+        assert!(agrees(1000., 1000.5, 1e-3));
+        assert!(!agrees(1000., 1002., 1e-3));
+        assert!(agrees(0., 5e-4, 1e-3));
+        assert!(agrees(f32::NAN, f32::NAN, 1e-3));
+        assert!(!agrees(f32::INFINITY, f32::MAX, 1e-3));
+    }
+
+    #[test]
+    fn reductions_cover_neurons_and_connections() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&x, 1., 1)],
+        ));
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&hidden, 1., 1),
+                PolyInputTopology::downgrade(&x, 1., 2),
+            ],
+        ));
+        let topology =
+            PolyNetworkTopology::from_raw_parts(vec![x, hidden, output], MutationChances::none());
+
+        // the hidden neuron, then its connection and the output's two
+        let candidates = reductions(&topology).unwrap();
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(candidates[0].info().num_hidden, 0);
+        assert!(candidates.iter().all(|c| c.info().num_outputs == 1));
+    }
+}
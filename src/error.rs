@@ -0,0 +1,148 @@
+//! The crate-wide error type.
+//!
+//! Building an evaluator from a genome walks the whole topology under its locks and
+//! maps every neuron to a position. Any of that can fail on a malformed or poisoned
+//! genome, and a long-running evolution should be able to discard such a genome
+//! instead of going down with it. The fallible constructors therefore return
+//! [`Result`] with a [`PolyNeatError`].
+
+use std::{fmt, path::Path, sync::PoisonError};
+
+use crate::{
+    burn_net::expander::ExpansionLimit,
+    core::{numeric::NumericPolicy, validation::PredictError},
+};
+
+/// Everything that can go wrong in this crate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolyNeatError {
+    /// A thread panicked while holding the lock of a neuron.
+    PoisonedLock,
+    /// A polynomial refers to a variable that has no mapping, e.g. an input neuron that
+    /// is no longer part of the topology.
+    UnknownVariable { variable: String },
+    /// A neuron refers to another neuron that isn't part of the network.
+    UnknownNeuron { id: String },
+    /// A term of an output polynomial has no matching row in the basis.
+    MissingBasisTerm { output: usize },
+    /// A checked prediction was refused.
+    Predict(PredictError),
+    /// A dataset has a different number of target rows than input rows.
+    RowCount { inputs: usize, targets: usize },
+    /// A target row of a dataset doesn't have one value per output.
+    TargetLength {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// Expanding the network into polynomials ran into one of its limits.
+    ExpansionLimit(ExpansionLimit),
+    /// A sum of several terms is raised to a negative power, e.g. `1 / (x + y)`, so the
+    /// output has no closed-form polynomial.
+    NotAPolynomial { terms: usize },
+    /// An evaluator can't honour a numeric policy, e.g. [`NumericPolicy::Epsilon`] on a
+    /// network whose hidden neurons were expanded away.
+    UnsupportedPolicy { policy: NumericPolicy },
+    /// A name can't be used as a Rust identifier in generated source.
+    InvalidIdentifier { name: String },
+    /// A file could not be read or written.
+    Io { path: String, message: String },
+    /// A saved genome or dataset is malformed.
+    Parse { message: String },
+    /// A key of an experiment config is malformed or out of range.
+    Config { key: String, message: String },
+}
+
+/// A `Result` defaulting to [`PolyNeatError`].
+pub type Result<T, E = PolyNeatError> = std::result::Result<T, E>;
+
+impl fmt::Display for PolyNeatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolyNeatError::PoisonedLock => write!(f, "a neuron lock was poisoned"),
+            PolyNeatError::UnknownVariable { variable } => {
+                write!(f, "polynomial variable {variable} has no mapping")
+            }
+            PolyNeatError::UnknownNeuron { id } => {
+                write!(f, "neuron {id} is not part of the network")
+            }
+            PolyNeatError::MissingBasisTerm { output } => {
+                write!(f, "a term of output {output} is missing from the basis")
+            }
+            PolyNeatError::Predict(error) => write!(f, "{error}"),
+            PolyNeatError::RowCount { inputs, targets } => {
+                write!(
+                    f,
+                    "expected a target row per input row, got {targets} for {inputs}"
+                )
+            }
+            PolyNeatError::TargetLength {
+                row,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "target row {row}: expected {expected} outputs, got {actual}"
+            ),
+            PolyNeatError::ExpansionLimit(limit) => write!(f, "{limit}"),
+            PolyNeatError::NotAPolynomial { terms } => write!(
+                f,
+                "a sum of {terms} terms raised to a negative power is not a polynomial"
+            ),
+            PolyNeatError::UnsupportedPolicy { policy } => {
+                write!(f, "{policy:?} is not supported by expanded networks")
+            }
+            PolyNeatError::InvalidIdentifier { name } => {
+                write!(f, "{name:?} is not a valid Rust identifier")
+            }
+            PolyNeatError::Io { path, message } => write!(f, "{path}: {message}"),
+            PolyNeatError::Parse { message } => write!(f, "{message}"),
+            PolyNeatError::Config { key, message } if key.is_empty() => write!(f, "{message}"),
+            PolyNeatError::Config { key, message } => write!(f, "{key}: {message}"),
+        }
+    }
+}
+
+impl PolyNeatError {
+    pub(crate) fn io(path: &Path, error: std::io::Error) -> Self {
+        PolyNeatError::Io {
+            path: path.display().to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    pub(crate) fn parse(message: impl Into<String>) -> Self {
+        PolyNeatError::Parse {
+            message: message.into(),
+        }
+    }
+}
+
+impl std::error::Error for PolyNeatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PolyNeatError::Predict(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl<T> From<PoisonError<T>> for PolyNeatError {
+    fn from(_: PoisonError<T>) -> Self {
+        PolyNeatError::PoisonedLock
+    }
+}
+
+impl From<serde_json::Error> for PolyNeatError {
+    fn from(error: serde_json::Error) -> Self {
+        PolyNeatError::Parse {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<PredictError> for PolyNeatError {
+    fn from(error: PredictError) -> Self {
+        PolyNeatError::Predict(error)
+    }
+}
@@ -0,0 +1,354 @@
+//! Saving a run and resuming it later.
+//!
+//! A [`Checkpoint`] holds everything an [`Evolution`] needs to carry on as if it had
+//! never stopped: its settings, every genome with its fitness and
+//! [`MutationChances`], the species and the next species id, the generation counter,
+//! the summary of the last scored generation and what it is selected by, the
+//! [`HallOfFame`], the [`NoveltyArchive`] and the state of the random number generator. Resuming a checkpoint of a seeded run therefore breeds exactly the
+//! genomes the uninterrupted run would have.
+//!
+//! There is no innovation tracker to save. Neurons are identified by random UUIDs that
+//! [`replicate`](PolyNetworkTopology::replicate) reissues for every offspring, and
+//! [species](super::species) are told apart by structural traits instead of innovation
+//! numbers, so no counter is shared between genomes.
+//!
+//! Checkpoints are JSON, with genomes in the same [`GenomeData`] format as
+//! [`save_json`](PolyNetworkTopology::save_json). A checkpoint is written to a temporary
+//! file first and then renamed over `path`, so a crash mid-write leaves the previous one
+//! intact.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::evolution::{Evolution, EvolutionSettings, Xor};
+//! use polynomial_neat::prelude::*;
+//!
+//! let path = std::env::temp_dir().join("polynomial_neat_checkpoint_doc.json");
+//! let settings = EvolutionSettings::default()
+//!     .with_population_size(10)
+//!     .with_max_generations(4);
+//! let mut evolution = Evolution::new(settings, &Xor, MutationChances::new(50), 7);
+//! evolution.run_with_checkpoints(&Xor, &path, 2, |_, _| Ok(()))?;
+//!
+//! let resumed = Evolution::resume(&path)?;
+//! assert_eq!(resumed.population().generation(), 3);
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{fs, path::Path};
+
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Evolution, EvolutionSettings, GenerationSummary, Genome, HallOfFame, NoveltyArchive,
+    Population, Species,
+};
+use crate::{
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
+
+/// The version written by this crate. Checkpoints of other versions are refused.
+const VERSION: u32 = 1;
+
+/// A genome and its fitness.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenomeRecord {
+    pub genome: GenomeData,
+    pub fitness: f32,
+}
+
+/// The whole state of an [`Evolution`]. See the [module docs](self).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub settings: EvolutionSettings,
+    pub generation: usize,
+    pub genomes: Vec<GenomeRecord>,
+    pub species: Vec<Species>,
+    pub next_species_id: usize,
+    /// `None` if the checkpoint was taken before its generation was scored.
+    pub summary: Option<GenerationSummary>,
+    /// The scores the last generation is selected by, if not its fitness.
+    #[serde(default)]
+    pub scores: Option<Vec<f32>>,
+    #[serde(default)]
+    pub hall_of_fame: HallOfFame,
+    #[serde(default)]
+    pub novelty_archive: NoveltyArchive,
+    pub rng: ChaCha12Rng,
+}
+
+impl Checkpoint {
+    /// # Errors
+    /// Fails if `json` isn't a checkpoint.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let checkpoint: Self = serde_json::from_str(json)?;
+        if checkpoint.version != VERSION {
+            return Err(PolyNeatError::parse(format!(
+                "expected a version {VERSION} checkpoint, found version {}",
+                checkpoint.version
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("checkpoints are always serializable")
+    }
+
+    /// Read a checkpoint written by [`save`](Self::save).
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Io`] if the file can't be read, and with
+    /// [`PolyNeatError::Parse`] naming the file if it isn't a checkpoint.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| PolyNeatError::io(path, e))?;
+        Self::from_json(&json).map_err(|error| match error {
+            PolyNeatError::Parse { message } => {
+                PolyNeatError::parse(format!("{}: {message}", path.display()))
+            }
+            error => error,
+        })
+    }
+
+    /// Write this checkpoint to `path`, replacing any previous one only once it has been
+    /// written in full.
+    ///
+    /// # Errors
+    /// Fails if the file can't be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        fs::write(&partial, self.to_json()).map_err(|e| PolyNeatError::io(path, e))?;
+        fs::rename(&partial, path).map_err(|e| PolyNeatError::io(path, e))
+    }
+
+    /// Rebuild the [`Evolution`] this checkpoint was taken of.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Parse`] if a genome is malformed, a species names a
+    /// genome that doesn't exist or there isn't one score per genome.
+    pub fn restore(self) -> Result<Evolution> {
+        let genomes = self
+            .genomes
+            .iter()
+            .map(|record| {
+                Ok(Genome {
+                    topology: PolyNetworkTopology::from_genome(&record.genome)?,
+                    fitness: record.fitness,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let num_genomes = genomes.len();
+        if let Some(scores) = &self.scores
+            && scores.len() != num_genomes
+        {
+            return Err(PolyNeatError::parse(format!(
+                "expected {num_genomes} scores, found {}",
+                scores.len()
+            )));
+        }
+        if let Some(member) = self
+            .species
+            .iter()
+            .flat_map(|species| species.members.iter())
+            .find(|&&member| member >= num_genomes)
+        {
+            return Err(PolyNeatError::parse(format!(
+                "species member {member} is not one of the {num_genomes} genomes"
+            )));
+        }
+
+        let population = Population {
+            genomes,
+            generation: self.generation,
+            species: self.species,
+            next_species_id: self.next_species_id,
+            scores: self.scores,
+        };
+        Ok(Evolution {
+            settings: self.settings,
+            population,
+            rng: self.rng,
+            summary: self.summary,
+            hall_of_fame: self.hall_of_fame,
+            novelty_archive: self.novelty_archive,
+        })
+    }
+}
+
+impl Evolution {
+    /// Capture the whole state of this run. See [`Checkpoint`].
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn checkpoint(&self) -> Result<Checkpoint> {
+        let genomes = self
+            .population
+            .genomes
+            .iter()
+            .map(|genome| {
+                Ok(GenomeRecord {
+                    genome: genome.topology.to_genome()?,
+                    fitness: genome.fitness,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Checkpoint {
+            version: VERSION,
+            settings: self.settings.clone(),
+            generation: self.population.generation,
+            genomes,
+            species: self.population.species.clone(),
+            next_species_id: self.population.next_species_id,
+            summary: self.summary,
+            scores: self.population.scores.clone(),
+            hall_of_fame: self.hall_of_fame.clone(),
+            novelty_archive: self.novelty_archive.clone(),
+            rng: self.rng.clone(),
+        })
+    }
+
+    /// Save a [`Checkpoint`] of this run to `path`, to be picked up by
+    /// [`resume`](Self::resume).
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned or the file can't be written.
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.checkpoint()?.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::evolution::{NoveltySettings, ParetoSettings, SpeciationSettings, Traits, Xor};
+
+    fn settings() -> EvolutionSettings {
+        EvolutionSettings::default()
+            .with_population_size(16)
+            .with_max_generations(8)
+            .with_speciation(Some(SpeciationSettings::default()))
+    }
+
+    fn evolution() -> Evolution {
+        Evolution::new(settings(), &Xor, MutationChances::new(60), 11)
+    }
+
+    fn traits(evolution: &Evolution) -> Vec<Traits> {
+        evolution
+            .population()
+            .genomes()
+            .iter()
+            .map(|genome| Traits::of(&genome.topology).unwrap())
+            .collect()
+    }
+
+    /// Interrupt a run with `settings` after a few generations and check that resuming
+    /// it finishes exactly like the uninterrupted run.
+    fn assert_resumes(name: &str, settings: EvolutionSettings) {
+        let path = env::temp_dir().join(format!(
+            "polynomial_neat_checkpoint_{name}_{}.json",
+            std::process::id()
+        ));
+        let evolution = || Evolution::new(settings.clone(), &Xor, MutationChances::new(60), 11);
+
+        let mut straight = evolution();
+        let mut expected = Vec::new();
+        straight.run(&Xor, |summary| expected.push(*summary));
+
+        let mut interrupted = evolution();
+        let mut actual = Vec::new();
+        for _ in 0..3 {
+            actual.push(interrupted.evaluate(&Xor));
+            interrupted.breed();
+        }
+        actual.push(interrupted.evaluate(&Xor));
+        interrupted.save_checkpoint(&path).unwrap();
+        drop(interrupted);
+
+        let mut resumed = Evolution::resume(&path).unwrap();
+        assert_eq!(resumed.summary(), actual.last());
+        resumed.run(&Xor, |summary| actual.push(*summary));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(traits(&resumed), traits(&straight));
+        assert_eq!(
+            resumed.population().species(),
+            straight.population().species()
+        );
+        let fitness = |evolution: &Evolution| -> Vec<f32> {
+            let entries = evolution.hall_of_fame().entries();
+            entries.iter().map(|entry| entry.fitness).collect()
+        };
+        assert!(!resumed.hall_of_fame().is_empty());
+        assert_eq!(fitness(&resumed), fitness(&straight));
+        assert_eq!(resumed.novelty_archive(), straight.novelty_archive());
+        assert_eq!(
+            resumed.population().scores(),
+            straight.population().scores()
+        );
+    }
+
+    #[test]
+    fn resuming_continues_the_same_run() {
+        assert_resumes("plain", settings());
+    }
+
+    #[test]
+    fn resuming_continues_a_novelty_search() {
+        let novelty = NoveltySettings::default()
+            .with_k(5)
+            .with_fitness_weight(0.3);
+        let settings = settings().with_novelty(Some(novelty));
+        assert_resumes("novelty", settings);
+    }
+
+    #[test]
+    fn resuming_continues_a_pareto_run() {
+        let settings = settings().with_pareto(Some(ParetoSettings::default()));
+        assert_resumes("pareto", settings);
+    }
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let mut evolution = evolution();
+        evolution.evaluate(&Xor);
+        let checkpoint = evolution.checkpoint().unwrap();
+
+        let json = checkpoint.to_json();
+        assert_eq!(Checkpoint::from_json(&json).unwrap(), checkpoint);
+
+        let mut future = checkpoint;
+        future.version += 1;
+        assert!(Checkpoint::from_json(&future.to_json()).is_err());
+    }
+
+    #[test]
+    fn malformed_checkpoints_are_parse_errors() {
+        let path = env::temp_dir().join(format!(
+            "polynomial_neat_checkpoint_malformed_{}.json",
+            std::process::id()
+        ));
+        fs::write(&path, "{\"version\": ").unwrap();
+
+        let error = Checkpoint::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        let PolyNeatError::Parse { message } = error else {
+            panic!("expected a parse error, got {error:?}");
+        };
+        assert!(
+            message.starts_with(&format!("{}: ", path.display())),
+            "{message}"
+        );
+    }
+}
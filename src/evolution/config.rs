@@ -0,0 +1,737 @@
+//! Experiment files for the `evolve` command.
+//!
+//! An experiment is a TOML or JSON file that names a task and, optionally, everything
+//! about how to evolve it. Every section and key but `task` can be left out:
+//!
+//! ```toml
+//! seed = 7
+//!
+//! [task]
+//! kind = "dataset"      # or "xor"
+//! path = "samples.csv"  # relative to the experiment file
+//! inputs = 2
+//!
+//! [network]
+//! inputs = 2            # checked against the task, if given
+//! outputs = 1
+//! initial_topology = "random"  # or "fully_connected"
+//!
+//! [mutation]
+//! self_mutation = 80    # percent chance of mutating at all, 0 to 100
+//! split_connection = 40.0
+//! add_connection = 30.0
+//! remove_connection = 5.0
+//! mutate_weight = 20.0
+//! mutate_exponent = 5.0
+//!
+//! [mutation.bounds]
+//! max_weight = 4.0
+//! min_exponent = -1
+//! max_exponent = 3
+//!
+//! [population]
+//! size = 100
+//!
+//! [selection]
+//! elite_fraction = 0.2
+//! parent_fraction = 0.5
+//!
+//! [speciation]
+//! enabled = true
+//! compatibility_threshold = 3.0
+//!
+//! [novelty]
+//! enabled = true        # select by the novelty of the outputs on the task's samples
+//! k = 15
+//! archive_per_generation = 1
+//! fitness_weight = 0.2  # 0 for pure novelty search
+//!
+//! [pareto]
+//! enabled = true        # select by Pareto front instead of a single score
+//! objectives = ["fitness", "expanded_terms"]
+//!
+//! [hall_of_fame]
+//! size = 10             # 0 keeps none
+//!
+//! [termination]
+//! max_generations = 500
+//! target_fitness = 0.95
+//! ```
+//!
+//! The relative mutation chances don't have to add up to anything; they are normalised
+//! by [`MutationChances::new_from_raw`].
+//!
+//! Both malformed files and out of range values are reported as
+//! [`PolyNeatError::Config`] naming the offending key, e.g. `selection.elite_fraction`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::evolution::ExperimentConfig;
+//! use polynomial_neat::prelude::PolyNeatError;
+//!
+//! let config = ExperimentConfig::from_toml("[task]\nkind = \"xor\"\n\n[population]\nsize = 20\n")?;
+//! assert_eq!(config.settings().population_size(), 20);
+//!
+//! let error = ExperimentConfig::from_toml("[task]\nkind = \"xor\"\n\n[population]\nsize = 0\n");
+//! assert!(matches!(error, Err(PolyNeatError::Config { key, .. }) if key == "population.size"));
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::{
+    Dataset, EvolutionSettings, InitialTopology, NoveltySettings, Objective, ParetoSettings,
+    SpeciationSettings, Task, Xor,
+};
+use crate::{
+    error::{PolyNeatError, Result},
+    prelude::*,
+    topology::mutation::MutationBounds,
+};
+
+/// Everything needed to start an [`Evolution`](super::Evolution).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentConfig {
+    #[serde(default)]
+    pub seed: u64,
+    pub task: TaskConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub mutation: MutationConfig,
+    #[serde(default)]
+    pub population: PopulationConfig,
+    #[serde(default)]
+    pub selection: SelectionConfig,
+    #[serde(default)]
+    pub speciation: SpeciationConfig,
+    #[serde(default)]
+    pub novelty: NoveltyConfig,
+    #[serde(default)]
+    pub pareto: ParetoConfig,
+    #[serde(default)]
+    pub hall_of_fame: HallOfFameConfig,
+    #[serde(default)]
+    pub termination: TerminationConfig,
+}
+
+/// The problem an experiment evolves against.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum TaskConfig {
+    /// The built-in [`Xor`] task.
+    #[default]
+    Xor,
+    /// A [`Dataset`] read from a CSV file whose first `inputs` columns are inputs.
+    Dataset { path: PathBuf, inputs: usize },
+}
+
+/// The shape of the genomes. Input and output counts default to the task's.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub inputs: Option<usize>,
+    pub outputs: Option<usize>,
+    pub initial_topology: InitialTopology,
+}
+
+/// The chances every genome starts with, and the bounds on what mutation may produce.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutationConfig {
+    /// Percent chance, 0 to 100, that a genome mutates at all when replicated.
+    pub self_mutation: u8,
+    pub split_connection: f32,
+    pub add_connection: f32,
+    pub remove_connection: f32,
+    pub mutate_weight: f32,
+    pub mutate_exponent: f32,
+    pub bounds: BoundsConfig,
+}
+
+impl Default for MutationConfig {
+    /// The chances the XOR integration test evolves with.
+    fn default() -> Self {
+        Self {
+            self_mutation: 80,
+            split_connection: 40.,
+            add_connection: 30.,
+            remove_connection: 5.,
+            mutate_weight: 20.,
+            mutate_exponent: 5.,
+            bounds: BoundsConfig::default(),
+        }
+    }
+}
+
+impl MutationConfig {
+    pub fn chances(&self) -> MutationChances {
+        MutationChances::new_from_raw(
+            self.self_mutation,
+            self.split_connection,
+            self.add_connection,
+            self.remove_connection,
+            self.mutate_weight,
+            self.mutate_exponent,
+        )
+    }
+}
+
+/// See [`MutationBounds`]. Every bound is optional.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoundsConfig {
+    pub max_weight: Option<f32>,
+    pub min_exponent: Option<i32>,
+    pub max_exponent: Option<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PopulationConfig {
+    pub size: usize,
+}
+
+impl Default for PopulationConfig {
+    fn default() -> Self {
+        Self {
+            size: EvolutionSettings::default().population_size(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelectionConfig {
+    pub elite_fraction: f32,
+    pub parent_fraction: f32,
+}
+
+impl Default for SelectionConfig {
+    fn default() -> Self {
+        let settings = EvolutionSettings::default();
+        Self {
+            elite_fraction: settings.elite_fraction(),
+            parent_fraction: settings.parent_fraction(),
+        }
+    }
+}
+
+/// See [`SpeciationSettings`]. Off unless `enabled` is set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeciationConfig {
+    pub enabled: bool,
+    pub compatibility_threshold: f32,
+    pub structure_coefficient: f32,
+    pub weight_coefficient: f32,
+    pub exponent_coefficient: f32,
+}
+
+impl Default for SpeciationConfig {
+    fn default() -> Self {
+        let settings = SpeciationSettings::default();
+        Self {
+            enabled: false,
+            compatibility_threshold: settings.compatibility_threshold(),
+            structure_coefficient: settings.structure_coefficient(),
+            weight_coefficient: settings.weight_coefficient(),
+            exponent_coefficient: settings.exponent_coefficient(),
+        }
+    }
+}
+
+/// See [`NoveltySettings`]. Off unless `enabled` is set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoveltyConfig {
+    pub enabled: bool,
+    pub k: usize,
+    pub archive_per_generation: usize,
+    pub max_archive_size: Option<usize>,
+    pub fitness_weight: f32,
+}
+
+impl Default for NoveltyConfig {
+    fn default() -> Self {
+        let settings = NoveltySettings::default();
+        Self {
+            enabled: false,
+            k: settings.k(),
+            archive_per_generation: settings.archive_per_generation(),
+            max_archive_size: settings.max_archive_size(),
+            fitness_weight: settings.fitness_weight(),
+        }
+    }
+}
+
+/// See [`ParetoSettings`]. Off unless `enabled` is set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParetoConfig {
+    pub enabled: bool,
+    pub objectives: Vec<Objective>,
+}
+
+impl Default for ParetoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            objectives: ParetoSettings::default().objectives().to_vec(),
+        }
+    }
+}
+
+/// See [`HallOfFame`](super::HallOfFame).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HallOfFameConfig {
+    pub size: usize,
+}
+
+impl Default for HallOfFameConfig {
+    fn default() -> Self {
+        Self {
+            size: EvolutionSettings::default().hall_of_fame_size(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerminationConfig {
+    pub max_generations: usize,
+    pub target_fitness: Option<f32>,
+}
+
+impl Default for TerminationConfig {
+    fn default() -> Self {
+        let settings = EvolutionSettings::default();
+        Self {
+            max_generations: settings.max_generations(),
+            target_fitness: settings.target_fitness(),
+        }
+    }
+}
+
+/// A [`PolyNeatError::Config`] for `key`.
+fn invalid(key: &str, message: impl Into<String>) -> PolyNeatError {
+    PolyNeatError::Config {
+        key: key.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Fails unless `value` is finite and within `[min, max]`.
+fn check_range(key: &str, value: f32, min: f32, max: f32) -> Result<()> {
+    if value.is_finite() && (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(invalid(
+            key,
+            format!("must be between {min} and {max}, found {value}"),
+        ))
+    }
+}
+
+impl ExperimentConfig {
+    /// Parse and [validate](Self::validate) a TOML experiment.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Config`] naming the key that is malformed or invalid.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let deserializer = toml::Deserializer::new(toml);
+        Self::deserialize_validated(deserializer, |error| error.message().to_string())
+    }
+
+    /// Parse and [validate](Self::validate) a JSON experiment.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Config`] naming the key that is malformed or invalid.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let config = Self::deserialize_validated(&mut deserializer, |error| format!("{error}"))?;
+        deserializer.end()?;
+        Ok(config)
+    }
+
+    fn deserialize_validated<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+        message: impl FnOnce(&D::Error) -> String,
+    ) -> Result<Self>
+    where
+        Self: DeserializeOwned,
+    {
+        let config: Self = serde_path_to_error::deserialize(deserializer).map_err(|error| {
+            let key = error.path().to_string();
+            let message = message(error.inner());
+            // errors before any key, e.g. a missing table, are reported at the top level
+            invalid(if key == "." { "" } else { &key }, message)
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read an experiment, choosing TOML or JSON by the file extension, and resolve a
+    /// dataset path against the file's directory.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read, has neither a `.toml` nor a `.json` extension,
+    /// or doesn't parse or validate.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| PolyNeatError::io(path, e))?;
+        let mut config = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents)?,
+            Some("json") => Self::from_json(&contents)?,
+            _ => {
+                return Err(PolyNeatError::Io {
+                    path: path.display().to_string(),
+                    message: "expected a .toml or .json experiment".to_string(),
+                });
+            }
+        };
+
+        if let TaskConfig::Dataset { path: dataset, .. } = &mut config.task
+            && dataset.is_relative()
+            && let Some(dir) = path.parent()
+        {
+            *dataset = dir.join(&*dataset);
+        }
+        Ok(config)
+    }
+
+    /// Check every value that the types alone don't constrain.
+    ///
+    /// Called by every constructor, so only needed after editing a config by hand.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Config`] naming the first invalid key.
+    pub fn validate(&self) -> Result<()> {
+        if let TaskConfig::Dataset { inputs: 0, .. } = self.task {
+            return Err(invalid("task.inputs", "must be at least 1"));
+        }
+        if self.network.inputs == Some(0) {
+            return Err(invalid("network.inputs", "must be at least 1"));
+        }
+        if self.network.outputs == Some(0) {
+            return Err(invalid("network.outputs", "must be at least 1"));
+        }
+        // the shape of a dataset is only known once it's read, except for its inputs
+        if let (TaskConfig::Dataset { inputs, .. }, Some(network)) =
+            (&self.task, self.network.inputs)
+            && *inputs != network
+        {
+            return Err(invalid(
+                "network.inputs",
+                format!("the task has {inputs} inputs, found {network}"),
+            ));
+        }
+
+        let mutation = &self.mutation;
+        if mutation.self_mutation > 100 {
+            return Err(invalid(
+                "mutation.self_mutation",
+                format!(
+                    "must be between 0 and 100, found {}",
+                    mutation.self_mutation
+                ),
+            ));
+        }
+        let chances = [
+            ("mutation.split_connection", mutation.split_connection),
+            ("mutation.add_connection", mutation.add_connection),
+            ("mutation.remove_connection", mutation.remove_connection),
+            ("mutation.mutate_weight", mutation.mutate_weight),
+            ("mutation.mutate_exponent", mutation.mutate_exponent),
+        ];
+        for (key, chance) in chances {
+            check_range(key, chance, 0., f32::MAX)?;
+        }
+        if chances.iter().all(|(_, chance)| *chance == 0.) {
+            return Err(invalid(
+                "mutation",
+                "at least one mutation chance must be above 0",
+            ));
+        }
+
+        let bounds = &mutation.bounds;
+        if let Some(max_weight) = bounds.max_weight {
+            check_range("mutation.bounds.max_weight", max_weight, 0., f32::MAX)?;
+        }
+        if let (Some(min), Some(max)) = (bounds.min_exponent, bounds.max_exponent)
+            && min > max
+        {
+            return Err(invalid(
+                "mutation.bounds.max_exponent",
+                format!("must be at least min_exponent ({min}), found {max}"),
+            ));
+        }
+
+        if self.population.size == 0 {
+            return Err(invalid("population.size", "must be at least 1"));
+        }
+        check_range(
+            "selection.elite_fraction",
+            self.selection.elite_fraction,
+            0.,
+            1.,
+        )?;
+        check_range(
+            "selection.parent_fraction",
+            self.selection.parent_fraction,
+            0.,
+            1.,
+        )?;
+        if self.selection.parent_fraction == 0. {
+            return Err(invalid("selection.parent_fraction", "must be above 0"));
+        }
+
+        let speciation = &self.speciation;
+        check_range(
+            "speciation.compatibility_threshold",
+            speciation.compatibility_threshold,
+            0.,
+            f32::MAX,
+        )?;
+        check_range(
+            "speciation.structure_coefficient",
+            speciation.structure_coefficient,
+            0.,
+            f32::MAX,
+        )?;
+        check_range(
+            "speciation.weight_coefficient",
+            speciation.weight_coefficient,
+            0.,
+            f32::MAX,
+        )?;
+        check_range(
+            "speciation.exponent_coefficient",
+            speciation.exponent_coefficient,
+            0.,
+            f32::MAX,
+        )?;
+
+        if self.novelty.k == 0 {
+            return Err(invalid("novelty.k", "must be at least 1"));
+        }
+        check_range(
+            "novelty.fitness_weight",
+            self.novelty.fitness_weight,
+            0.,
+            1.,
+        )?;
+
+        if self.pareto.objectives.is_empty() {
+            return Err(invalid(
+                "pareto.objectives",
+                "must name at least one objective",
+            ));
+        }
+
+        if self.termination.max_generations == 0 {
+            return Err(invalid("termination.max_generations", "must be at least 1"));
+        }
+        if let Some(target) = self.termination.target_fitness
+            && !target.is_finite()
+        {
+            return Err(invalid(
+                "termination.target_fitness",
+                format!("must be finite, found {target}"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The [`EvolutionSettings`] this experiment describes.
+    pub fn settings(&self) -> EvolutionSettings {
+        let bounds = &self.mutation.bounds;
+        let speciation = &self.speciation;
+        let novelty = &self.novelty;
+        EvolutionSettings::default()
+            .with_population_size(self.population.size)
+            .with_initial_topology(self.network.initial_topology)
+            .with_elite_fraction(self.selection.elite_fraction)
+            .with_parent_fraction(self.selection.parent_fraction)
+            .with_bounds(
+                MutationBounds::default()
+                    .with_max_weight(bounds.max_weight)
+                    .with_exponents(bounds.min_exponent, bounds.max_exponent),
+            )
+            .with_speciation(speciation.enabled.then(|| {
+                SpeciationSettings::default()
+                    .with_compatibility_threshold(speciation.compatibility_threshold)
+                    .with_structure_coefficient(speciation.structure_coefficient)
+                    .with_weight_coefficient(speciation.weight_coefficient)
+                    .with_exponent_coefficient(speciation.exponent_coefficient)
+            }))
+            .with_novelty(novelty.enabled.then(|| {
+                NoveltySettings::default()
+                    .with_k(novelty.k)
+                    .with_archive_per_generation(novelty.archive_per_generation)
+                    .with_max_archive_size(novelty.max_archive_size)
+                    .with_fitness_weight(novelty.fitness_weight)
+            }))
+            .with_pareto(
+                self.pareto.enabled.then(|| {
+                    ParetoSettings::default().with_objectives(self.pareto.objectives.clone())
+                }),
+            )
+            .with_hall_of_fame_size(self.hall_of_fame.size)
+            .with_max_generations(self.termination.max_generations)
+            .with_target_fitness(self.termination.target_fitness)
+    }
+
+    /// Build the task, reading its dataset if it has one, and check it against
+    /// [`network`](Self::network).
+    ///
+    /// # Errors
+    /// Fails if the dataset can't be read, or with [`PolyNeatError::Config`] if its shape
+    /// doesn't match `network.inputs` or `network.outputs`.
+    pub fn load_task(&self) -> Result<Box<dyn Task>> {
+        let task: Box<dyn Task> = match &self.task {
+            TaskConfig::Xor => Box::new(Xor),
+            TaskConfig::Dataset { path, inputs } => Box::new(Dataset::from_csv(path, *inputs)?),
+        };
+
+        let shape = [
+            ("network.inputs", self.network.inputs, task.num_inputs()),
+            ("network.outputs", self.network.outputs, task.num_outputs()),
+        ];
+        for (key, configured, actual) in shape {
+            if let Some(configured) = configured
+                && configured != actual
+            {
+                return Err(invalid(
+                    key,
+                    format!("the task has {actual}, found {configured}"),
+                ));
+            }
+        }
+        Ok(task)
+    }
+}
+
+impl Task for Box<dyn Task> {
+    fn num_inputs(&self) -> usize {
+        (**self).num_inputs()
+    }
+
+    fn num_outputs(&self) -> usize {
+        (**self).num_outputs()
+    }
+
+    fn fitness(&self, network: &CompiledNetwork) -> f32 {
+        (**self).fitness(network)
+    }
+
+    fn behaviour(&self, network: &SimplePolyNetwork) -> Option<Vec<f32>> {
+        (**self).behaviour(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_of(error: PolyNeatError) -> String {
+        match error {
+            PolyNeatError::Config { key, .. } => key,
+            other => panic!("expected a config error, found {other}"),
+        }
+    }
+
+    #[test]
+    fn errors_name_the_key() {
+        let cases = [
+            (
+                "[task]\nkind = \"xor\"\n[selection]\nelite_fraction = 1.5\n",
+                "selection.elite_fraction",
+            ),
+            (
+                "[task]\nkind = \"xor\"\n[mutation]\nself_mutation = \"often\"\n",
+                "mutation.self_mutation",
+            ),
+            (
+                "[task]\nkind = \"xor\"\n[mutation.bounds]\nmax_wieght = 2.0\n",
+                "mutation.bounds.max_wieght",
+            ),
+            (
+                "[task]\nkind = \"xor\"\n[termination]\nmax_generations = 0\n",
+                "termination.max_generations",
+            ),
+            (
+                "[task]\nkind = \"xor\"\n[novelty]\nenabled = true\nfitness_weight = -0.5\n",
+                "novelty.fitness_weight",
+            ),
+            (
+                "[task]\nkind = \"xor\"\n[pareto]\nenabled = true\nobjectives = []\n",
+                "pareto.objectives",
+            ),
+            (
+                "[task]\nkind = \"xor\"\n[pareto]\nobjectives = [\"fitness\", \"beauty\"]\n",
+                "pareto.objectives[1]",
+            ),
+            (
+                "[task]\nkind = \"dataset\"\npath = \"a.csv\"\ninputs = 2\n[network]\ninputs = 3\n",
+                "network.inputs",
+            ),
+            ("[task]\nkind = \"sine\"\n", "task.kind"),
+        ];
+
+        for (toml, key) in cases {
+            let error = ExperimentConfig::from_toml(toml).unwrap_err();
+            assert_eq!(key_of(error), key, "{toml}");
+        }
+
+        let error =
+            ExperimentConfig::from_json(r#"{"task": {"kind": "xor"}, "population": {"size": -3}}"#)
+                .unwrap_err();
+        assert_eq!(key_of(error), "population.size");
+    }
+
+    #[test]
+    fn toml_and_json_agree() {
+        let toml = ExperimentConfig::from_toml(
+            "seed = 3\n[task]\nkind = \"xor\"\n[speciation]\nenabled = true\n[mutation.bounds]\nmax_exponent = 2\n",
+        )
+        .unwrap();
+        let json = ExperimentConfig::from_json(
+            r#"{"seed": 3, "task": {"kind": "xor"}, "speciation": {"enabled": true}, "mutation": {"bounds": {"max_exponent": 2}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(toml, json);
+        let settings = toml.settings();
+        assert!(settings.speciation().is_some());
+        assert_eq!(settings.bounds().max_exponent(), Some(2));
+        assert!(settings.pareto().is_none());
+    }
+
+    #[test]
+    fn reads_pareto_objectives() {
+        let config = ExperimentConfig::from_toml(
+            "[task]\nkind = \"xor\"\n[pareto]\nenabled = true\nobjectives = [\"fitness\", \"neurons\", \"max_exponent\"]\n",
+        )
+        .unwrap();
+
+        let settings = config.settings();
+        assert_eq!(
+            settings.pareto().unwrap().objectives(),
+            [
+                Objective::Fitness,
+                Objective::Neurons,
+                Objective::MaxExponent
+            ]
+        );
+    }
+}
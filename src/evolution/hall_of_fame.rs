@@ -0,0 +1,200 @@
+//! The fittest genomes of a whole run.
+//!
+//! Elites only survive as long as they stay on top of their generation, so with a noisy
+//! task a lucky champion can be bred away for good. A [`HallOfFame`] keeps copies of the
+//! `capacity` fittest genomes seen in any generation, fittest first.
+//!
+//! Offspring get fresh neuron ids, so a genome that survives unmutated, or is bred again
+//! identically, would otherwise fill the hall with copies of itself. Genomes are therefore
+//! compared by [structure](GenomeData::same_structure), and a structural duplicate only
+//! replaces the copy already kept if it scored better.
+//!
+//! The hall holds [`GenomeData`] snapshots rather than live topologies, so it can be
+//! saved in a [`Checkpoint`](super::Checkpoint) as it is.
+
+use serde::{Deserialize, Serialize};
+
+use super::Population;
+use crate::{error::Result, prelude::*};
+
+/// A genome kept by a [`HallOfFame`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HallOfFameEntry {
+    pub genome: GenomeData,
+    pub fitness: f32,
+    /// The generation it scored `fitness` in.
+    pub generation: usize,
+}
+
+impl HallOfFameEntry {
+    /// Rebuild the network of this entry.
+    ///
+    /// # Errors
+    /// Fails if the genome is malformed, which it can only be if it was edited by hand.
+    pub fn topology(&self) -> Result<PolyNetworkTopology> {
+        PolyNetworkTopology::from_genome(&self.genome)
+    }
+}
+
+/// The structurally distinct fittest genomes of a run. See the [module docs](self).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HallOfFame {
+    capacity: usize,
+    entries: Vec<HallOfFameEntry>,
+}
+
+impl HallOfFame {
+    /// An empty hall that keeps at most `capacity` genomes. A capacity of 0 keeps none.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Every genome kept, fittest first.
+    pub fn entries(&self) -> &[HallOfFameEntry] {
+        &self.entries
+    }
+
+    /// The fittest genome seen so far.
+    pub fn best(&self) -> Option<&HallOfFameEntry> {
+        self.entries.first()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Offer every genome of a scored generation.
+    ///
+    /// Genomes with non-finite fitness or poisoned locks are passed over.
+    pub fn update(&mut self, population: &Population) {
+        for genome in population.genomes() {
+            if !self.qualifies(genome.fitness) {
+                continue;
+            }
+            let Ok(data) = genome.topology.to_genome() else {
+                continue;
+            };
+            self.insert(HallOfFameEntry {
+                genome: data,
+                fitness: genome.fitness,
+                generation: population.generation(),
+            });
+        }
+    }
+
+    /// Whether a genome scoring `fitness` could get in, before looking at its structure.
+    fn qualifies(&self, fitness: f32) -> bool {
+        fitness.is_finite()
+            && self.capacity > 0
+            && (self.entries.len() < self.capacity
+                || self
+                    .entries
+                    .last()
+                    .is_some_and(|worst| fitness > worst.fitness))
+    }
+
+    /// Add `entry`, unless a structurally identical genome is already kept with at least
+    /// the same fitness.
+    pub fn insert(&mut self, entry: HallOfFameEntry) {
+        if !self.qualifies(entry.fitness) {
+            return;
+        }
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|kept| kept.genome.same_structure(&entry.genome))
+        {
+            if self.entries[index].fitness >= entry.fitness {
+                return;
+            }
+            self.entries.remove(index);
+        }
+
+        // after every entry at least as fit, so earlier finds win ties
+        let index = self
+            .entries
+            .partition_point(|kept| kept.fitness >= entry.fitness);
+        self.entries.insert(index, entry);
+        self.entries.truncate(self.capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::evolution::Genome;
+
+    #[test]
+    fn keeps_the_fittest_distinct_genomes() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let champion =
+            PolyNetworkTopology::new_thoroughly_connected(2, 1, MutationChances::none(), &mut rng);
+        let others: Vec<PolyNetworkTopology> = (0..4)
+            .map(|_| {
+                PolyNetworkTopology::new_thoroughly_connected(
+                    2,
+                    1,
+                    MutationChances::none(),
+                    &mut rng,
+                )
+            })
+            .collect();
+        let mut hall = HallOfFame::new(3);
+
+        let first = vec![
+            Genome {
+                topology: champion.clone(),
+                fitness: 10.,
+            },
+            Genome {
+                topology: others[0].clone(),
+                fitness: 1.,
+            },
+            Genome {
+                topology: others[1].clone(),
+                fitness: 2.,
+            },
+        ];
+        hall.update(&Population::from_genomes(first, 0));
+
+        // the champion is gone from the population and an unmutated copy of it scores
+        // worse, one newcomer beats the weakest genome kept and one failed to score
+        let second = vec![
+            Genome {
+                topology: champion.replicate(&mut rng),
+                fitness: 4.,
+            },
+            Genome {
+                topology: others[2].clone(),
+                fitness: 3.,
+            },
+            Genome {
+                topology: others[3].clone(),
+                fitness: f32::NAN,
+            },
+        ];
+        hall.update(&Population::from_genomes(second, 1));
+
+        let kept: Vec<(f32, usize)> = hall
+            .entries()
+            .iter()
+            .map(|entry| (entry.fitness, entry.generation))
+            .collect();
+        assert_eq!(kept, vec![(10., 0), (3., 1), (2., 0)]);
+        let best = hall.best().unwrap().topology().unwrap();
+        assert_eq!(best.neuron_ids(), champion.neuron_ids());
+    }
+}
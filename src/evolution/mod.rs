@@ -0,0 +1,831 @@
+//! A generational evolution loop.
+//!
+//! [`Evolution`] keeps a [`Population`] of genomes and the random number generator that
+//! breeds them. Every generation it scores each genome on a [`Task`], then replaces the
+//! population with the best genomes of the last one plus mutated offspring of the better
+//! half:
+//!
+//! 1. Genomes are sorted by fitness, with non-finite fitness last. With
+//!    [novelty search](novelty) enabled, they are sorted by novelty blended with fitness
+//!    instead. With [multi-objective selection](pareto) enabled, they are sorted by Pareto
+//!    front and crowding distance over fitness and complexity.
+//! 2. The top [`elite_fraction`](EvolutionSettings::with_elite_fraction) is carried over
+//!    unchanged.
+//! 3. The rest of the next generation is filled by [replicating](PolyNetworkTopology::replicate)
+//!    random parents from the top [`parent_fraction`](EvolutionSettings::with_parent_fraction).
+//!    With [speciation](species) enabled, parents are ranked by scores shared within
+//!    their species instead.
+//! 4. Offspring are clamped into the [`MutationBounds`], if any.
+//!
+//! Elites only last as long as they stay on top, so the fittest distinct genomes of the
+//! whole run are also kept in a [`HallOfFame`].
+//!
+//! Genomes are scored in parallel through a [`CompiledNetwork`] each. A run can be
+//! saved to a [`Checkpoint`] after any generation and resumed from it later, and its
+//! progress recorded with [`stats`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::evolution::{Evolution, EvolutionSettings, Xor};
+//! use polynomial_neat::prelude::*;
+//!
+//! let settings = EvolutionSettings::default()
+//!     .with_population_size(20)
+//!     .with_max_generations(5);
+//! let mut evolution = Evolution::new(settings, &Xor, MutationChances::new(50), 7);
+//!
+//! let last = evolution.run(&Xor, |summary| println!("{summary}"));
+//! assert_eq!(last.generation, 4);
+//! assert!(evolution.best().fitness >= 0.);
+//! ```
+
+use std::{cmp::Ordering, fmt};
+
+use std::path::Path;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Result, prelude::*, topology::mutation::MutationBounds};
+
+pub mod checkpoint;
+pub mod config;
+pub mod hall_of_fame;
+pub mod novelty;
+pub mod pareto;
+pub mod species;
+pub mod stats;
+pub mod task;
+
+pub use checkpoint::Checkpoint;
+pub use config::{ExperimentConfig, TaskConfig};
+pub use hall_of_fame::{HallOfFame, HallOfFameEntry};
+pub use novelty::{NoveltyArchive, NoveltySettings, WithBehaviour};
+pub use pareto::{Objective, ParetoPoint, ParetoSettings};
+pub use species::{SpeciationSettings, Species, Traits};
+pub use task::{Dataset, Task, Xor};
+
+/// A topology and its fitness on the last task it was scored on.
+#[derive(Clone, Debug)]
+pub struct Genome {
+    pub topology: PolyNetworkTopology,
+    pub fitness: f32,
+}
+
+impl Genome {
+    /// An unscored genome.
+    pub fn new(topology: PolyNetworkTopology) -> Self {
+        Self {
+            topology,
+            fitness: 0.,
+        }
+    }
+
+    /// Score this genome on `task`. A genome that can't be compiled scores 0.
+    pub fn evaluate(&mut self, task: &impl Task) {
+        self.fitness = match CompiledNetwork::from_topology(&self.topology) {
+            Ok(network) => task.fitness(&network),
+            Err(_) => 0.,
+        };
+        if !self.fitness.is_finite() {
+            self.fitness = 0.;
+        }
+    }
+}
+
+/// Orders fitness values best first, with non-finite values last.
+fn best_first(a: f32, b: f32) -> Ordering {
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => b.total_cmp(&a),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => Ordering::Equal,
+    }
+}
+
+/// Indices of `values`, best first.
+fn ranked(values: &[f32]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| best_first(values[a], values[b]));
+    order
+}
+
+/// How the first generation is wired.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitialTopology {
+    /// Every output starts with a random subset of the inputs.
+    /// See [`PolyNetworkTopology::new`].
+    #[default]
+    Random,
+    /// Every output starts connected to every input.
+    /// See [`PolyNetworkTopology::new_thoroughly_connected`].
+    FullyConnected,
+}
+
+/// How an [`Evolution`] starts, selects and when it stops.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvolutionSettings {
+    population_size: usize,
+    initial_topology: InitialTopology,
+    elite_fraction: f32,
+    parent_fraction: f32,
+    bounds: MutationBounds,
+    speciation: Option<SpeciationSettings>,
+    novelty: Option<NoveltySettings>,
+    pareto: Option<ParetoSettings>,
+    hall_of_fame_size: usize,
+    max_generations: usize,
+    target_fitness: Option<f32>,
+}
+
+impl Default for EvolutionSettings {
+    fn default() -> Self {
+        Self {
+            population_size: 50,
+            initial_topology: InitialTopology::default(),
+            elite_fraction: 0.2,
+            parent_fraction: 0.5,
+            bounds: MutationBounds::default(),
+            speciation: None,
+            novelty: None,
+            pareto: None,
+            hall_of_fame_size: 10,
+            max_generations: 1000,
+            target_fitness: None,
+        }
+    }
+}
+
+impl EvolutionSettings {
+    pub fn with_initial_topology(mut self, initial_topology: InitialTopology) -> Self {
+        self.initial_topology = initial_topology;
+        self
+    }
+
+    /// Clamp every offspring into `bounds`.
+    pub fn with_bounds(mut self, bounds: MutationBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Group genomes into species and share fitness within them. See [`species`].
+    pub fn with_speciation(mut self, speciation: Option<SpeciationSettings>) -> Self {
+        self.speciation = speciation;
+        self
+    }
+
+    /// Select by novelty blended with fitness. See [`novelty`].
+    pub fn with_novelty(mut self, novelty: Option<NoveltySettings>) -> Self {
+        self.novelty = novelty;
+        self
+    }
+
+    /// Select by Pareto front and crowding distance over several objectives. See
+    /// [`pareto`].
+    pub fn with_pareto(mut self, pareto: Option<ParetoSettings>) -> Self {
+        self.pareto = pareto;
+        self
+    }
+
+    pub fn with_population_size(mut self, population_size: usize) -> Self {
+        self.population_size = population_size;
+        self
+    }
+
+    /// The fraction of each generation carried over unchanged.
+    pub fn with_elite_fraction(mut self, elite_fraction: f32) -> Self {
+        self.elite_fraction = elite_fraction;
+        self
+    }
+
+    /// The fraction of each generation, best first, that offspring are bred from.
+    pub fn with_parent_fraction(mut self, parent_fraction: f32) -> Self {
+        self.parent_fraction = parent_fraction;
+        self
+    }
+
+    /// Keep the `hall_of_fame_size` fittest distinct genomes of the whole run. See
+    /// [`HallOfFame`].
+    pub fn with_hall_of_fame_size(mut self, hall_of_fame_size: usize) -> Self {
+        self.hall_of_fame_size = hall_of_fame_size;
+        self
+    }
+
+    pub fn with_max_generations(mut self, max_generations: usize) -> Self {
+        self.max_generations = max_generations;
+        self
+    }
+
+    /// Stop as soon as a genome reaches `target_fitness`.
+    pub fn with_target_fitness(mut self, target_fitness: Option<f32>) -> Self {
+        self.target_fitness = target_fitness;
+        self
+    }
+
+    pub fn population_size(&self) -> usize {
+        self.population_size
+    }
+
+    pub fn initial_topology(&self) -> InitialTopology {
+        self.initial_topology
+    }
+
+    pub fn bounds(&self) -> &MutationBounds {
+        &self.bounds
+    }
+
+    pub fn speciation(&self) -> Option<&SpeciationSettings> {
+        self.speciation.as_ref()
+    }
+
+    pub fn novelty(&self) -> Option<&NoveltySettings> {
+        self.novelty.as_ref()
+    }
+
+    pub fn pareto(&self) -> Option<&ParetoSettings> {
+        self.pareto.as_ref()
+    }
+
+    pub fn elite_fraction(&self) -> f32 {
+        self.elite_fraction
+    }
+
+    pub fn parent_fraction(&self) -> f32 {
+        self.parent_fraction
+    }
+
+    pub fn hall_of_fame_size(&self) -> usize {
+        self.hall_of_fame_size
+    }
+
+    pub fn max_generations(&self) -> usize {
+        self.max_generations
+    }
+
+    pub fn target_fitness(&self) -> Option<f32> {
+        self.target_fitness
+    }
+
+    /// The number of genomes carried over unchanged, at most the whole population.
+    pub fn num_elites(&self) -> usize {
+        ((self.population_size as f32 * self.elite_fraction).round() as usize)
+            .min(self.population_size)
+    }
+
+    /// The number of genomes offspring are bred from, at least one.
+    pub fn num_parents(&self) -> usize {
+        ((self.population_size as f32 * self.parent_fraction).round() as usize)
+            .clamp(1, self.population_size.max(1))
+    }
+}
+
+/// The genomes of one generation.
+#[derive(Clone, Debug, Default)]
+pub struct Population {
+    genomes: Vec<Genome>,
+    generation: usize,
+    species: Vec<Species>,
+    next_species_id: usize,
+    /// What to select by instead of fitness, one per genome.
+    scores: Option<Vec<f32>>,
+}
+
+impl Population {
+    /// A first generation of [`population_size`](EvolutionSettings::population_size)
+    /// random genomes with `num_inputs` inputs and `num_outputs` outputs.
+    pub fn new(
+        settings: &EvolutionSettings,
+        num_inputs: usize,
+        num_outputs: usize,
+        mutation_chances: MutationChances,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let genomes = (0..settings.population_size())
+            .map(|_| {
+                let topology = match settings.initial_topology() {
+                    InitialTopology::Random => {
+                        PolyNetworkTopology::new(num_inputs, num_outputs, mutation_chances, rng)
+                    }
+                    InitialTopology::FullyConnected => {
+                        PolyNetworkTopology::new_thoroughly_connected(
+                            num_inputs,
+                            num_outputs,
+                            mutation_chances,
+                            rng,
+                        )
+                    }
+                };
+                Genome::new(topology)
+            })
+            .collect();
+
+        Self::from_genomes(genomes, 0)
+    }
+
+    pub fn from_genomes(genomes: Vec<Genome>, generation: usize) -> Self {
+        Self {
+            genomes,
+            generation,
+            species: Vec::new(),
+            next_species_id: 0,
+            scores: None,
+        }
+    }
+
+    pub fn genomes(&self) -> &[Genome] {
+        &self.genomes
+    }
+
+    pub fn len(&self) -> usize {
+        self.genomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genomes.is_empty()
+    }
+
+    /// The number of generations bred since the first one.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Score every genome on `task`, in parallel.
+    pub fn evaluate(&mut self, task: &impl Task) {
+        self.genomes
+            .par_iter_mut()
+            .for_each(|genome| genome.evaluate(task));
+        self.scores = None;
+    }
+
+    /// The [`behaviour`](Task::behaviour) of every genome on `task`, in parallel. Genomes
+    /// that can't be built, or that behave with non-finite values, have none.
+    pub fn behaviours(&self, task: &impl Task) -> Vec<Option<Vec<f32>>> {
+        self.genomes
+            .par_iter()
+            .map(|genome| {
+                let network = SimplePolyNetwork::from_topology(&genome.topology).ok()?;
+                task.behaviour(&network)
+                    .filter(|behaviour| behaviour.iter().all(|value| value.is_finite()))
+            })
+            .collect()
+    }
+
+    /// The score every genome is selected by: its fitness, unless other scores were
+    /// [set](Self::set_scores) since the generation was last evaluated.
+    pub fn scores(&self) -> Vec<f32> {
+        match &self.scores {
+            Some(scores) => scores.clone(),
+            None => self.genomes.iter().map(|genome| genome.fitness).collect(),
+        }
+    }
+
+    /// Select this generation by `scores` instead of fitness, e.g. blended
+    /// [novelty](novelty).
+    ///
+    /// # Panics
+    /// Panics unless there is one score per genome.
+    pub fn set_scores(&mut self, scores: Vec<f32>) {
+        assert_eq!(scores.len(), self.genomes.len(), "one score per genome");
+        self.scores = Some(scores);
+    }
+
+    /// The species of the current generation, empty unless
+    /// [`speciate`](Self::speciate) has been called on it.
+    pub fn species(&self) -> &[Species] {
+        &self.species
+    }
+
+    /// Sort the current generation into species. See [`species`].
+    pub fn speciate(&mut self, settings: &SpeciationSettings) {
+        // a genome whose lock is poisoned can't be read, so it gets a species of its own
+        let traits: Vec<Traits> = self
+            .genomes
+            .iter()
+            .map(|genome| {
+                Traits::of(&genome.topology).unwrap_or(Traits {
+                    hidden: usize::MAX,
+                    ..Traits::default()
+                })
+            })
+            .collect();
+        self.species =
+            species::speciate(&traits, &self.species, settings, &mut self.next_species_id);
+    }
+
+    /// The fitness of every genome divided by the size of its species, or the plain
+    /// fitness if the generation hasn't been sorted into species.
+    pub fn shared_fitness(&self) -> Vec<f32> {
+        let fitness = self.genomes.iter().map(|genome| genome.fitness).collect();
+        self.shared(fitness)
+    }
+
+    /// `values`, one per genome, each divided by the size of the genome's species.
+    fn shared(&self, mut shared: Vec<f32>) -> Vec<f32> {
+        for species in self.species.iter() {
+            for &member in species.members.iter() {
+                shared[member] /= species.members.len() as f32;
+            }
+        }
+        shared
+    }
+
+    /// The fittest genome.
+    pub fn best(&self) -> Option<&Genome> {
+        self.genomes
+            .iter()
+            .min_by(|a, b| best_first(a.fitness, b.fitness))
+    }
+
+    pub fn average_fitness(&self) -> f32 {
+        let sum: f32 = self.genomes.iter().map(|genome| genome.fitness).sum();
+        sum / self.genomes.len().max(1) as f32
+    }
+
+    /// Replace this generation with the next one. See the [module docs](self).
+    pub fn breed(&mut self, settings: &EvolutionSettings, rng: &mut impl Rng) {
+        let scores = self.scores();
+        let elites = ranked(&scores);
+        let parents = ranked(&self.shared(scores));
+
+        let size = settings.population_size();
+        let num_parents = settings.num_parents().min(parents.len());
+        let mut next: Vec<Genome> = elites
+            .iter()
+            .take(settings.num_elites())
+            .map(|&i| self.genomes[i].clone())
+            .collect();
+
+        let mut parents: Vec<usize> = parents.into_iter().take(num_parents).collect();
+        while next.len() < size && !parents.is_empty() {
+            let pick = rng.random_range(0..parents.len());
+            let Ok(offspring) = self.genomes[parents[pick]].topology.try_replicate(rng) else {
+                // a parent whose lock is poisoned can't be read, so it breeds no further
+                parents.swap_remove(pick);
+                continue;
+            };
+            // bounds only fail on a poisoned lock, which the offspring can't have yet
+            let _ = offspring.apply_bounds(settings.bounds());
+            next.push(Genome::new(offspring));
+        }
+
+        self.genomes = next;
+        self.generation += 1;
+        self.scores = None;
+        // members index the generation that was just replaced
+        for species in self.species.iter_mut() {
+            species.members.clear();
+        }
+    }
+}
+
+/// How one generation scored.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenerationSummary {
+    pub generation: usize,
+    pub best_fitness: f32,
+    pub average_fitness: f32,
+    /// 0 without speciation.
+    pub num_species: usize,
+}
+
+impl fmt::Display for GenerationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "generation {}: best fitness {:.4}, average fitness {:.4}",
+            self.generation, self.best_fitness, self.average_fitness
+        )?;
+        if self.num_species > 0 {
+            write!(f, ", {} species", self.num_species)?;
+        }
+        Ok(())
+    }
+}
+
+/// A population, the settings that drive it and the random number generator that
+/// breeds it. See the [module docs](self).
+pub struct Evolution {
+    settings: EvolutionSettings,
+    population: Population,
+    rng: ChaCha12Rng,
+    /// How the current generation scored, if it has been evaluated yet.
+    summary: Option<GenerationSummary>,
+    hall_of_fame: HallOfFame,
+    novelty_archive: NoveltyArchive,
+}
+
+impl Evolution {
+    /// A random population shaped for `task`, seeded with `seed`.
+    pub fn new(
+        settings: EvolutionSettings,
+        task: &impl Task,
+        mutation_chances: MutationChances,
+        seed: u64,
+    ) -> Self {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let population = Population::new(
+            &settings,
+            task.num_inputs(),
+            task.num_outputs(),
+            mutation_chances,
+            &mut rng,
+        );
+
+        Self {
+            hall_of_fame: HallOfFame::new(settings.hall_of_fame_size()),
+            novelty_archive: NoveltyArchive::new(),
+            settings,
+            population,
+            rng,
+            summary: None,
+        }
+    }
+
+    /// Continue a run from the checkpoint saved at `path`. See [`Checkpoint`].
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or isn't a checkpoint.
+    pub fn resume(path: impl AsRef<Path>) -> Result<Self> {
+        Checkpoint::load(path)?.restore()
+    }
+
+    pub fn settings(&self) -> &EvolutionSettings {
+        &self.settings
+    }
+
+    pub fn population(&self) -> &Population {
+        &self.population
+    }
+
+    /// How the current generation scored, or `None` if it hasn't been evaluated yet.
+    pub fn summary(&self) -> Option<&GenerationSummary> {
+        self.summary.as_ref()
+    }
+
+    /// The fittest genomes of every generation scored so far.
+    pub fn hall_of_fame(&self) -> &HallOfFame {
+        &self.hall_of_fame
+    }
+
+    /// The behaviours archived by novelty search, empty unless it is enabled.
+    pub fn novelty_archive(&self) -> &NoveltyArchive {
+        &self.novelty_archive
+    }
+
+    /// The first Pareto front of the current generation, on the objectives it is selected
+    /// by or, if multi-objective selection is off, on fitness against expanded terms.
+    ///
+    /// Meant for a generation that was just evaluated: bred genomes have no fitness yet.
+    pub fn pareto_front(&self) -> Vec<ParetoPoint> {
+        let default = ParetoSettings::default();
+        let objectives = self.settings.pareto().unwrap_or(&default).objectives();
+        pareto::front(&self.population, objectives)
+    }
+
+    /// The fittest genome of the current generation.
+    ///
+    /// # Panics
+    /// Panics if the population is empty.
+    pub fn best(&self) -> &Genome {
+        self.population.best().expect("the population is empty")
+    }
+
+    /// Score the current generation on `task` and offer it to the [`HallOfFame`]. Then
+    /// measure its novelty, rank it by its objectives and sort it into species, if any
+    /// of those are enabled.
+    pub fn evaluate(&mut self, task: &impl Task) -> GenerationSummary {
+        self.population.evaluate(task);
+        self.hall_of_fame.update(&self.population);
+        if let Some(novelty) = self.settings.novelty() {
+            let behaviours = self.population.behaviours(task);
+            let sparseness = self.novelty_archive.sparseness(&behaviours, novelty.k());
+            self.novelty_archive
+                .update(&behaviours, &sparseness, novelty);
+            let fitness: Vec<f32> = self.population.genomes.iter().map(|g| g.fitness).collect();
+            self.population.set_scores(novelty::blend(
+                &sparseness,
+                &fitness,
+                novelty.fitness_weight(),
+            ));
+        }
+        if let Some(pareto) = self.settings.pareto() {
+            let scores = pareto::scores(&self.population, pareto);
+            self.population.set_scores(scores);
+        }
+        if let Some(speciation) = self.settings.speciation() {
+            self.population.speciate(speciation);
+        }
+        let summary = GenerationSummary {
+            generation: self.population.generation(),
+            best_fitness: self.population.best().map_or(0., |best| best.fitness),
+            average_fitness: self.population.average_fitness(),
+            num_species: self.population.species().len(),
+        };
+        self.summary = Some(summary);
+        summary
+    }
+
+    /// Replace the current generation with its offspring.
+    pub fn breed(&mut self) {
+        self.population.breed(&self.settings, &mut self.rng);
+        self.summary = None;
+    }
+
+    /// Whether a generation that scored `summary` is the last one.
+    pub fn is_done(&self, summary: &GenerationSummary) -> bool {
+        summary.generation + 1 >= self.settings.max_generations()
+            || self
+                .settings
+                .target_fitness()
+                .is_some_and(|target| summary.best_fitness >= target)
+    }
+
+    /// Evaluate and breed until the target fitness or the generation limit is reached,
+    /// calling `on_generation` after every evaluation.
+    ///
+    /// A generation that was already evaluated, e.g. the last one of a resumed
+    /// checkpoint, is bred without being scored or reported again. The last generation
+    /// is left scored and unbred, so [`best`](Self::best) is the fittest genome found.
+    pub fn run(
+        &mut self,
+        task: &impl Task,
+        mut on_generation: impl FnMut(&GenerationSummary),
+    ) -> GenerationSummary {
+        let result: Result<_> = self.run_with(task, |_, summary| {
+            on_generation(summary);
+            Ok(())
+        });
+        result.expect("on_generation can't fail")
+    }
+
+    /// Like [`run_with`](Self::run_with), but also save a [`Checkpoint`] to `path` after
+    /// every `every` generations and after the last one, once `after` has seen them.
+    ///
+    /// # Errors
+    /// Fails if `after` does or a checkpoint can't be written. The run stops at that
+    /// generation.
+    ///
+    /// # Panics
+    /// Panics if `every` is 0.
+    pub fn run_with_checkpoints(
+        &mut self,
+        task: &impl Task,
+        path: impl AsRef<Path>,
+        every: usize,
+        mut after: impl FnMut(&Self, &GenerationSummary) -> Result<()>,
+    ) -> Result<GenerationSummary> {
+        assert!(
+            every > 0,
+            "checkpoints must be at least one generation apart"
+        );
+        let path = path.as_ref();
+        self.run_with(task, |evolution, summary| {
+            after(evolution, summary)?;
+            if (summary.generation + 1) % every == 0 || evolution.is_done(summary) {
+                evolution.save_checkpoint(path)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Like [`run`](Self::run), but `after` is also handed the evolution itself, e.g. to
+    /// record [`GenerationStats`](stats::GenerationStats) or save a checkpoint, and can
+    /// stop the run by failing.
+    ///
+    /// # Errors
+    /// Fails with the first error `after` returns.
+    pub fn run_with(
+        &mut self,
+        task: &impl Task,
+        mut after: impl FnMut(&Self, &GenerationSummary) -> Result<()>,
+    ) -> Result<GenerationSummary> {
+        loop {
+            let summary = match self.summary {
+                Some(summary) => summary,
+                None => {
+                    let summary = self.evaluate(task);
+                    after(self, &summary)?;
+                    summary
+                }
+            };
+            if self.is_done(&summary) {
+                return Ok(summary);
+            }
+            self.breed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    #[test]
+    fn breeding_keeps_the_elites() {
+        let settings = EvolutionSettings::default().with_population_size(10);
+        let mut rng = StdRng::seed_from_u64(44);
+        let mut population = Population::new(&settings, 2, 1, MutationChances::new(50), &mut rng);
+        for (i, genome) in population.genomes.iter_mut().enumerate() {
+            genome.fitness = if i == 3 { f32::NAN } else { i as f32 };
+        }
+        let elites: Vec<_> = [9, 8]
+            .map(|i| population.genomes[i].topology.neuron_ids())
+            .into();
+
+        population.breed(&settings, &mut rng);
+
+        assert_eq!(population.len(), 10);
+        assert_eq!(population.generation(), 1);
+        assert_eq!(settings.num_elites(), 2);
+        let kept: Vec<_> = population.genomes[..2]
+            .iter()
+            .map(|genome| genome.topology.neuron_ids())
+            .collect();
+        assert_eq!(kept, elites);
+    }
+
+    #[test]
+    fn poisoned_genomes_are_passed_over() {
+        let settings = EvolutionSettings::default()
+            .with_population_size(4)
+            .with_parent_fraction(1.);
+        let mut rng = StdRng::seed_from_u64(36);
+        let mut population = Population::new(&settings, 2, 1, MutationChances::new(50), &mut rng);
+        for (i, genome) in population.genomes.iter_mut().enumerate() {
+            genome.fitness = i as f32;
+        }
+        let poisoned = Arc::clone(&population.genomes[0].topology.neurons()[0]);
+        let _ = std::thread::spawn(move || {
+            let _lock = poisoned.write().unwrap();
+            panic!("poison a neuron");
+        })
+        .join();
+
+        let objectives = [Objective::Fitness, Objective::Neurons];
+        let values = pareto::measure(&population, &objectives);
+        assert!(values[0][1].is_nan());
+        assert!(values[1..].iter().all(|values| values[1].is_finite()));
+
+        population.breed(&settings, &mut rng);
+        assert_eq!(population.len(), 4);
+        assert!(
+            population
+                .genomes()
+                .iter()
+                .all(|genome| genome.topology.try_info().is_ok())
+        );
+    }
+
+    #[test]
+    fn breeding_selects_by_scores() {
+        let settings = EvolutionSettings::default()
+            .with_population_size(4)
+            .with_elite_fraction(0.25);
+        let mut rng = StdRng::seed_from_u64(49);
+        let mut population = Population::new(&settings, 2, 1, MutationChances::new(50), &mut rng);
+        for (i, genome) in population.genomes.iter_mut().enumerate() {
+            genome.fitness = i as f32;
+        }
+        // the least fit genome is the most novel
+        population.set_scores(vec![1., 0., 0., 0.]);
+        let novel = population.genomes[0].topology.neuron_ids();
+
+        population.breed(&settings, &mut rng);
+
+        assert_eq!(population.genomes[0].topology.neuron_ids(), novel);
+        assert_eq!(population.scores(), vec![0., 0., 0., 0.]);
+    }
+
+    #[test]
+    fn fitness_is_shared_within_species() {
+        let settings = EvolutionSettings::default().with_population_size(4);
+        let mut rng = StdRng::seed_from_u64(45);
+        let mut population = Population::new(&settings, 2, 1, MutationChances::new(50), &mut rng);
+        for genome in population.genomes.iter_mut() {
+            genome.fitness = 1.;
+        }
+
+        // a huge threshold puts everyone in one species
+        population.speciate(&SpeciationSettings::default().with_compatibility_threshold(1e6));
+        assert_eq!(population.species().len(), 1);
+        assert_eq!(population.shared_fitness(), vec![0.25; 4]);
+
+        population.breed(&settings, &mut rng);
+        assert!(population.species()[0].members.is_empty());
+        let fitness: Vec<f32> = population
+            .genomes()
+            .iter()
+            .map(|genome| genome.fitness)
+            .collect();
+        assert_eq!(population.shared_fitness(), fitness);
+    }
+}
@@ -0,0 +1,322 @@
+//! Selecting for new behaviour instead of, or as well as, fitness.
+//!
+//! On a deceptive task the path to a good network leads through networks that score
+//! worse, and a loop that only selects for fitness never takes it. Novelty search rewards
+//! genomes for behaving unlike anything seen before instead:
+//!
+//! 1. Every genome's behaviour is characterised as a vector by
+//!    [`Task::behaviour`], e.g. its outputs on a fixed set of inputs. Wrap a task
+//!    in [`WithBehaviour`] to characterise it with any function of a
+//!    [`SimplePolyNetwork`].
+//! 2. Its novelty is its sparseness: the mean distance to the
+//!    [`k`](NoveltySettings::with_k) nearest behaviours among the rest of the generation
+//!    and a [`NoveltyArchive`] of behaviours from earlier generations.
+//! 3. The [`archive_per_generation`](NoveltySettings::with_archive_per_generation) most
+//!    novel behaviours of each generation are added to the archive, so the search keeps
+//!    moving away from where it has already been.
+//! 4. Genomes are selected by novelty blended with fitness, both scaled to `[0, 1]`
+//!    across the generation, weighted by
+//!    [`fitness_weight`](NoveltySettings::with_fitness_weight).
+//!
+//! [`Genome::fitness`](super::Genome::fitness) stays the plain task fitness, so the
+//! summaries, statistics and [`HallOfFame`](super::HallOfFame) still track progress on
+//! the task itself.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::evolution::{
+//!     Evolution, EvolutionSettings, Xor,
+//!     novelty::{NoveltySettings, WithBehaviour},
+//! };
+//! use polynomial_neat::prelude::*;
+//!
+//! // characterise a network by its output at a few points along the first input
+//! let task = WithBehaviour::new(Xor, |network: &SimplePolyNetwork| {
+//!     [-1., 0., 1.]
+//!         .iter()
+//!         .map(|x| network.predict(&[*x, 0.]).next().unwrap_or(0.))
+//!         .collect::<Vec<f32>>()
+//! });
+//! let settings = EvolutionSettings::default()
+//!     .with_population_size(20)
+//!     .with_max_generations(5)
+//!     .with_novelty(Some(NoveltySettings::default().with_fitness_weight(0.2)));
+//!
+//! let mut evolution = Evolution::new(settings, &task, MutationChances::new(50), 7);
+//! evolution.run(&task, |_| {});
+//! assert_eq!(evolution.novelty_archive().len(), 5);
+//! ```
+
+use std::collections::VecDeque;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Task;
+use crate::prelude::*;
+
+/// How novelty is measured, archived and blended with fitness.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NoveltySettings {
+    k: usize,
+    archive_per_generation: usize,
+    max_archive_size: Option<usize>,
+    fitness_weight: f32,
+}
+
+impl Default for NoveltySettings {
+    fn default() -> Self {
+        Self {
+            k: 15,
+            archive_per_generation: 1,
+            max_archive_size: None,
+            fitness_weight: 0.,
+        }
+    }
+}
+
+impl NoveltySettings {
+    /// How many nearest behaviours a genome's novelty is averaged over.
+    pub fn with_k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// How many of the most novel behaviours of each generation are archived.
+    pub fn with_archive_per_generation(mut self, archive_per_generation: usize) -> Self {
+        self.archive_per_generation = archive_per_generation;
+        self
+    }
+
+    /// Drop the oldest archived behaviours beyond this many. Unbounded by default.
+    pub fn with_max_archive_size(mut self, max_archive_size: Option<usize>) -> Self {
+        self.max_archive_size = max_archive_size;
+        self
+    }
+
+    /// How much fitness counts against novelty, from 0 for pure novelty search to 1 for
+    /// plain fitness.
+    pub fn with_fitness_weight(mut self, fitness_weight: f32) -> Self {
+        self.fitness_weight = fitness_weight;
+        self
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn archive_per_generation(&self) -> usize {
+        self.archive_per_generation
+    }
+
+    pub fn max_archive_size(&self) -> Option<usize> {
+        self.max_archive_size
+    }
+
+    pub fn fitness_weight(&self) -> f32 {
+        self.fitness_weight
+    }
+}
+
+/// A task whose networks are characterised by `behaviour`. See the [module docs](self).
+#[derive(Clone, Copy, Debug)]
+pub struct WithBehaviour<T, F> {
+    task: T,
+    behaviour: F,
+}
+
+impl<T, F> WithBehaviour<T, F>
+where
+    T: Task,
+    F: Fn(&SimplePolyNetwork) -> Vec<f32> + Sync,
+{
+    pub fn new(task: T, behaviour: F) -> Self {
+        Self { task, behaviour }
+    }
+
+    pub fn task(&self) -> &T {
+        &self.task
+    }
+}
+
+impl<T, F> Task for WithBehaviour<T, F>
+where
+    T: Task,
+    F: Fn(&SimplePolyNetwork) -> Vec<f32> + Sync,
+{
+    fn num_inputs(&self) -> usize {
+        self.task.num_inputs()
+    }
+
+    fn num_outputs(&self) -> usize {
+        self.task.num_outputs()
+    }
+
+    fn fitness(&self, network: &CompiledNetwork) -> f32 {
+        self.task.fitness(network)
+    }
+
+    fn behaviour(&self, network: &SimplePolyNetwork) -> Option<Vec<f32>> {
+        Some((self.behaviour)(network))
+    }
+}
+
+/// The Euclidean distance between two behaviours, over the dimensions both have.
+pub fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Behaviours of earlier generations, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoveltyArchive {
+    behaviours: VecDeque<Vec<f32>>,
+}
+
+impl NoveltyArchive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn behaviours(&self) -> impl Iterator<Item = &[f32]> {
+        self.behaviours.iter().map(Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.behaviours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.behaviours.is_empty()
+    }
+
+    /// The novelty of every behaviour of a generation: the mean distance to its `k`
+    /// nearest neighbours among the other behaviours and the archive.
+    ///
+    /// Genomes without a behaviour, e.g. because they failed to build or output NaN,
+    /// have no novelty and are nobody's neighbour.
+    pub fn sparseness(&self, behaviours: &[Option<Vec<f32>>], k: usize) -> Vec<f32> {
+        behaviours
+            .par_iter()
+            .enumerate()
+            .map(|(index, behaviour)| {
+                let Some(behaviour) = behaviour else {
+                    return 0.;
+                };
+                let others = behaviours
+                    .iter()
+                    .enumerate()
+                    .filter(|&(other, _)| other != index)
+                    .filter_map(|(_, other)| other.as_deref());
+                let mut distances: Vec<f32> = others
+                    .chain(self.behaviours())
+                    .map(|other| distance(behaviour, other))
+                    .collect();
+                if distances.is_empty() || k == 0 {
+                    return 0.;
+                }
+
+                distances.sort_by(f32::total_cmp);
+                let nearest = &distances[..k.min(distances.len())];
+                let novelty = nearest.iter().sum::<f32>() / nearest.len() as f32;
+                // the distances between huge behaviours can overflow
+                if novelty.is_finite() {
+                    novelty
+                } else {
+                    f32::MAX
+                }
+            })
+            .collect()
+    }
+
+    /// Archive the most novel behaviours of a generation, given their `novelty` from
+    /// [`sparseness`](Self::sparseness).
+    pub fn update(
+        &mut self,
+        behaviours: &[Option<Vec<f32>>],
+        novelty: &[f32],
+        settings: &NoveltySettings,
+    ) {
+        let mut order: Vec<usize> = (0..behaviours.len())
+            .filter(|&index| behaviours[index].is_some())
+            .collect();
+        order.sort_by(|&a, &b| novelty[b].total_cmp(&novelty[a]));
+
+        for index in order.into_iter().take(settings.archive_per_generation()) {
+            self.behaviours.extend(behaviours[index].clone());
+        }
+        if let Some(max) = settings.max_archive_size() {
+            let excess = self.behaviours.len().saturating_sub(max);
+            self.behaviours.drain(..excess);
+        }
+    }
+}
+
+/// Scale `values` to `[0, 1]`, or to all 0 if they are all the same.
+fn normalised(values: &[f32]) -> Vec<f32> {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    values
+        .iter()
+        .map(|value| {
+            if range > 0. && range.is_finite() {
+                (value - min) / range
+            } else {
+                0.
+            }
+        })
+        .collect()
+}
+
+/// The scores a generation is selected by: `novelty` and `fitness`, each scaled to
+/// `[0, 1]`, weighted `1 - fitness_weight` and `fitness_weight`.
+pub fn blend(novelty: &[f32], fitness: &[f32], fitness_weight: f32) -> Vec<f32> {
+    normalised(novelty)
+        .into_iter()
+        .zip(normalised(fitness))
+        .map(|(novelty, fitness)| (1. - fitness_weight) * novelty + fitness_weight * fitness)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn novelty_is_the_mean_distance_to_the_nearest() {
+        let mut archive = NoveltyArchive::new();
+        let behaviours = vec![Some(vec![0.]), Some(vec![1.]), Some(vec![3.]), None];
+
+        assert_eq!(archive.sparseness(&behaviours, 2), vec![2., 1.5, 2.5, 0.]);
+
+        let settings = NoveltySettings::default()
+            .with_archive_per_generation(2)
+            .with_max_archive_size(Some(3));
+        let novelty = archive.sparseness(&behaviours, 2);
+        archive.update(&behaviours, &novelty, &settings);
+        assert_eq!(archive.behaviours().collect::<Vec<_>>(), vec![[3.], [0.]]);
+
+        // the archive counts as neighbours, and only the newest behaviours are kept
+        assert_eq!(archive.sparseness(&[Some(vec![2.])], 1), vec![1.]);
+        archive.update(&behaviours, &novelty, &settings);
+        assert_eq!(
+            archive.behaviours().collect::<Vec<_>>(),
+            vec![[0.], [3.], [0.]]
+        );
+    }
+
+    #[test]
+    fn blends_scaled_novelty_and_fitness() {
+        let novelty = [0., 5., 10.];
+        let fitness = [1., 0.5, 0.];
+
+        assert_eq!(blend(&novelty, &fitness, 0.), vec![0., 0.5, 1.]);
+        assert_eq!(blend(&novelty, &fitness, 1.), vec![1., 0.5, 0.]);
+        assert_eq!(blend(&novelty, &[2.; 3], 0.5), vec![0., 0.25, 0.5]);
+    }
+}
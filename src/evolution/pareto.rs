@@ -0,0 +1,464 @@
+//! Selecting for several objectives at once, NSGA-II style.
+//!
+//! A network that fits the task slightly better is rarely worth a formula ten times the
+//! size. Instead of collapsing accuracy and simplicity into one number, genomes are
+//! measured on a list of [`Objective`]s and selected by Pareto dominance: a genome
+//! dominates another if it is at least as good on every objective and better on one.
+//!
+//! 1. Every genome is measured on every objective. [`Objective::Fitness`] is maximised,
+//!    the complexity objectives are minimised.
+//! 2. The generation is sorted into [`fronts`]: the first holds every genome nothing
+//!    dominates, the second every genome only the first dominates, and so on.
+//! 3. Within a front, genomes are told apart by their [`crowding_distance`], how far
+//!    apart their neighbours on each objective are, so the edges and sparse stretches of
+//!    a front are preferred over its crowded middle.
+//! 4. Genomes are selected by [`crowded_scores`], which order them by front first and
+//!    crowding distance second. Elites are therefore the best fronts, which is what
+//!    keeps NSGA-II elitist.
+//!
+//! [`Genome::fitness`](super::Genome::fitness) stays the plain task fitness, so the
+//! summaries, statistics and [`HallOfFame`](super::HallOfFame) still track progress on
+//! the task itself. The trade-off found is the first front of a generation, see
+//! [`Evolution::pareto_front`](super::Evolution::pareto_front).
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::evolution::{
+//!     Evolution, EvolutionSettings, Xor,
+//!     pareto::{Objective, ParetoSettings},
+//! };
+//! use polynomial_neat::prelude::*;
+//!
+//! let pareto = ParetoSettings::default()
+//!     .with_objectives(vec![Objective::Fitness, Objective::ExpandedTerms]);
+//! let settings = EvolutionSettings::default()
+//!     .with_population_size(20)
+//!     .with_max_generations(5)
+//!     .with_pareto(Some(pareto));
+//!
+//! let mut evolution = Evolution::new(settings, &Xor, MutationChances::new(50), 7);
+//! evolution.run(&Xor, |_| {});
+//! for point in evolution.pareto_front() {
+//!     let [fitness, terms] = point.values[..] else { unreachable!() };
+//!     println!("{fitness:.3} with {terms} terms");
+//! }
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::fmt;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{Genome, Population};
+use crate::{
+    burn_net::{expander::ExpansionLimits, output_polynomials_with_limits},
+    error::PolyNeatError,
+};
+
+/// Genomes are expanded up to this many terms per output to measure
+/// [`Objective::ExpandedTerms`]. Past it, a genome measures one more than this.
+pub const MAX_MEASURED_TERMS: usize = 4096;
+
+/// Something a genome is measured on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    /// The score the genome would be selected by otherwise: its fitness, or its novelty
+    /// blended with fitness if [novelty search](super::novelty) is enabled. Maximised.
+    Fitness,
+    /// Input, hidden and output neurons. Minimised.
+    Neurons,
+    /// Connections from neurons still in the network. Minimised.
+    Connections,
+    /// The largest absolute exponent of any connection. Minimised.
+    MaxExponent,
+    /// The terms of the largest expanded output, counted by expanding the genome. Genomes
+    /// that would exceed [`MAX_MEASURED_TERMS`] all measure `MAX_MEASURED_TERMS + 1`, so
+    /// they tie with each other and lose to any genome that could be expanded. Minimised.
+    ExpandedTerms,
+}
+
+impl Objective {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Objective::Fitness => "fitness",
+            Objective::Neurons => "neurons",
+            Objective::Connections => "connections",
+            Objective::MaxExponent => "max_exponent",
+            Objective::ExpandedTerms => "expanded_terms",
+        }
+    }
+
+    /// Whether smaller values are better.
+    pub fn is_minimised(&self) -> bool {
+        !matches!(self, Objective::Fitness)
+    }
+
+    /// The value of this objective for `genome`, which is selected by `score`.
+    ///
+    /// A genome that can't be measured, e.g. because of a poisoned lock, measures NaN.
+    pub fn measure(&self, genome: &Genome, score: f32) -> f32 {
+        let topology = &genome.topology;
+        match self {
+            Objective::Fitness => score,
+            Objective::Neurons => topology
+                .try_info()
+                .map_or(f32::NAN, |info| info.num_neurons() as f32),
+            Objective::Connections => topology
+                .try_info()
+                .map_or(f32::NAN, |info| info.num_connections as f32),
+            Objective::MaxExponent => topology.to_genome().map_or(f32::NAN, |data| {
+                data.neurons
+                    .iter()
+                    .flat_map(|neuron| neuron.inputs.iter())
+                    .map(|connection| connection.exponent.unsigned_abs())
+                    .max()
+                    .unwrap_or(0) as f32
+            }),
+            Objective::ExpandedTerms => {
+                let limits = ExpansionLimits::default().with_max_terms(MAX_MEASURED_TERMS);
+                match output_polynomials_with_limits(topology, &limits) {
+                    Ok(polynomials) => polynomials
+                        .iter()
+                        .map(|polynomial| polynomial.components().len())
+                        .max()
+                        .unwrap_or(0) as f32,
+                    Err(PolyNeatError::ExpansionLimit(_)) => (MAX_MEASURED_TERMS + 1) as f32,
+                    Err(_) => f32::NAN,
+                }
+            }
+        }
+    }
+
+    /// `value` turned around so that larger is better, with NaN worst of all.
+    fn maximised(&self, value: f32) -> f32 {
+        if value.is_nan() {
+            f32::NEG_INFINITY
+        } else if self.is_minimised() {
+            -value
+        } else {
+            value
+        }
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Which objectives a generation is selected by.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParetoSettings {
+    objectives: Vec<Objective>,
+}
+
+impl Default for ParetoSettings {
+    fn default() -> Self {
+        Self {
+            objectives: vec![Objective::Fitness, Objective::ExpandedTerms],
+        }
+    }
+}
+
+impl ParetoSettings {
+    /// Select by these objectives. Fitness against expanded terms by default.
+    pub fn with_objectives(mut self, objectives: Vec<Objective>) -> Self {
+        self.objectives = objectives;
+        self
+    }
+
+    pub fn objectives(&self) -> &[Objective] {
+        &self.objectives
+    }
+}
+
+/// A genome of a generation's first front.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParetoPoint {
+    /// The genome's index in its [`Population`].
+    pub index: usize,
+    /// Its value on every objective, in the order they were given.
+    pub values: Vec<f32>,
+}
+
+/// The value of every objective for every genome, as measured by [`Objective::measure`].
+pub fn measure(population: &Population, objectives: &[Objective]) -> Vec<Vec<f32>> {
+    let scores = population.scores();
+    population
+        .genomes()
+        .par_iter()
+        .zip(scores)
+        .map(|(genome, score)| {
+            objectives
+                .iter()
+                .map(|objective| objective.measure(genome, score))
+                .collect()
+        })
+        .collect()
+}
+
+/// `values` from [`measure`] turned around so that larger is better on every objective.
+fn maximised(values: &[Vec<f32>], objectives: &[Objective]) -> Vec<Vec<f32>> {
+    values
+        .iter()
+        .map(|values| {
+            objectives
+                .iter()
+                .zip(values)
+                .map(|(objective, &value)| objective.maximised(value))
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether `a` is at least as large as `b` everywhere and larger somewhere.
+pub fn dominates(a: &[f32], b: &[f32]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a >= b) && a.iter().zip(b).any(|(a, b)| a > b)
+}
+
+/// Indices of `values` sorted into non-dominated fronts, best first, where larger is
+/// better on every objective.
+pub fn fronts(values: &[Vec<f32>]) -> Vec<Vec<usize>> {
+    let n = values.len();
+    let mut dominated: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut num_dominating = vec![0usize; n];
+    for a in 0..n {
+        for b in (a + 1)..n {
+            if dominates(&values[a], &values[b]) {
+                dominated[a].push(b);
+                num_dominating[b] += 1;
+            } else if dominates(&values[b], &values[a]) {
+                dominated[b].push(a);
+                num_dominating[a] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..n).filter(|&i| num_dominating[i] == 0).collect();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for &a in front.iter() {
+            for &b in dominated[a].iter() {
+                num_dominating[b] -= 1;
+                if num_dominating[b] == 0 {
+                    next.push(b);
+                }
+            }
+        }
+        next.sort_unstable();
+        fronts.push(front);
+        front = next;
+    }
+    fronts
+}
+
+/// The crowding distance of every member of `front`, in the same order: the sum over
+/// the objectives of the gap between its two neighbours, scaled by the objective's range
+/// within the front. The extremes of every objective are infinitely far from the crowd.
+pub fn crowding_distance(values: &[Vec<f32>], front: &[usize]) -> Vec<f32> {
+    let mut distance = vec![0.; front.len()];
+    let num_objectives = front.first().map_or(0, |&i| values[i].len());
+    let columns = (0..num_objectives)
+        .map(|objective| -> Vec<f32> { front.iter().map(|&i| values[i][objective]).collect() });
+    for column in columns {
+        let value = |position: usize| column[position];
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|&a, &b| value(a).total_cmp(&value(b)));
+
+        let (Some(&first), Some(&last)) = (order.first(), order.last()) else {
+            continue;
+        };
+        distance[first] = f32::INFINITY;
+        distance[last] = f32::INFINITY;
+        let range = value(last) - value(first);
+        if !(range > 0. && range.is_finite()) {
+            continue;
+        }
+        for window in order.windows(3) {
+            distance[window[1]] += (value(window[2]) - value(window[0])) / range;
+        }
+    }
+    distance
+}
+
+/// Scores that order `values` by front, then by crowding distance, higher first. Every
+/// score is positive, so they can be shared within species like fitness.
+pub fn crowded_scores(values: &[Vec<f32>]) -> Vec<f32> {
+    let fronts = fronts(values);
+    let mut scores = vec![0.; values.len()];
+    for (rank, front) in fronts.iter().enumerate() {
+        let base = (fronts.len() - rank) as f32;
+        for (&index, distance) in front.iter().zip(crowding_distance(values, front)) {
+            // half a front at most, so no crowding distance lifts a genome past a
+            // better front
+            let spread = if distance.is_finite() {
+                distance / (distance + 1.)
+            } else {
+                1.
+            };
+            scores[index] = base + spread / 2.;
+        }
+    }
+    scores
+}
+
+/// The scores a generation is selected by under `settings`. See the [module docs](self).
+pub fn scores(population: &Population, settings: &ParetoSettings) -> Vec<f32> {
+    let objectives = settings.objectives();
+    crowded_scores(&maximised(&measure(population, objectives), objectives))
+}
+
+/// The genomes of the first front of `population`, best on the first objective first.
+pub fn front(population: &Population, objectives: &[Objective]) -> Vec<ParetoPoint> {
+    let values = measure(population, objectives);
+    let maximised = maximised(&values, objectives);
+    let Some(first) = fronts(&maximised).into_iter().next() else {
+        return Vec::new();
+    };
+
+    let mut points: Vec<ParetoPoint> = first
+        .into_iter()
+        .map(|index| ParetoPoint {
+            index,
+            values: values[index].clone(),
+        })
+        .collect();
+    points.sort_by(|a, b| {
+        let key = |point: &ParetoPoint| maximised[point.index].as_slice();
+        key(b)
+            .iter()
+            .zip(key(a))
+            .map(|(b, a)| b.total_cmp(a))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn the_front_trades_fitness_for_simplicity() {
+        let mut rng = StdRng::seed_from_u64(50);
+        let mut genome = |inputs: usize, fitness: f32| Genome {
+            topology: PolyNetworkTopology::new_thoroughly_connected(
+                inputs,
+                1,
+                MutationChances::none(),
+                &mut rng,
+            ),
+            fitness,
+        };
+        let genomes = vec![genome(2, 0.5), genome(4, 0.4), genome(4, 0.9)];
+        let population = Population::from_genomes(genomes, 0);
+
+        let front = front(&population, &[Objective::Fitness, Objective::Neurons]);
+        let points: Vec<(usize, Vec<f32>)> = front
+            .into_iter()
+            .map(|point| (point.index, point.values))
+            .collect();
+        assert_eq!(points, vec![(2, vec![0.9, 5.]), (0, vec![0.5, 3.])]);
+
+        let scores = scores(
+            &population,
+            &ParetoSettings::default()
+                .with_objectives(vec![Objective::Fitness, Objective::Neurons]),
+        );
+        assert!(scores[0] > scores[1] && scores[2] > scores[1]);
+    }
+
+    #[test]
+    fn sorts_into_fronts() {
+        // accuracy against simplicity, both maximised
+        let values = vec![
+            vec![1., -10.],
+            vec![0.5, -2.],
+            vec![0.9, -20.],
+            vec![0.5, -5.],
+            vec![0.1, -1.],
+            vec![f32::NEG_INFINITY, -1.],
+        ];
+
+        assert!(dominates(&values[0], &values[2]));
+        assert!(!dominates(&values[0], &values[1]));
+        assert!(!dominates(&values[1], &values[1]));
+        assert_eq!(fronts(&values), vec![vec![0, 1, 4], vec![2, 3, 5]]);
+    }
+
+    #[test]
+    fn prefers_the_edges_of_a_front() {
+        let values = vec![
+            vec![0., 4.],
+            vec![1., 3.],
+            vec![1.5, 2.5],
+            vec![4., 0.],
+            vec![0., 0.],
+        ];
+        assert_eq!(
+            crowding_distance(&values, &[0, 1, 2, 3]),
+            vec![f32::INFINITY, 0.75, 1.5, f32::INFINITY]
+        );
+
+        // the edges first, then the sparser middle, then the dominated genome
+        let scores = crowded_scores(&values);
+        assert_eq!(scores[0], scores[3]);
+        assert!(scores[3] > scores[2] && scores[2] > scores[1] && scores[1] > scores[4]);
+        assert!(scores[4] > 0.);
+    }
+
+    #[test]
+    fn expanded_terms_are_counted() {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        // x + 2x merges into 3x, though the estimate allows for two terms
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 1., 1),
+                PolyInputTopology::downgrade(&x, 2., 1),
+            ],
+        ));
+        let genome = Genome::new(PolyNetworkTopology::from_raw_parts(
+            vec![x, output],
+            MutationChances::none(),
+        ));
+        assert_eq!(genome.topology.estimate_expansion().unwrap().terms, 2);
+        assert_eq!(Objective::ExpandedTerms.measure(&genome, 0.), 1.);
+
+        // (x0 + ... + x4)^20 has C(24, 4) = 10626 terms, too many to expand
+        let inputs: Vec<_> = (0..5)
+            .map(|_| arc(PolyNeuronTopology::input(Uuid::new_v4())))
+            .collect();
+        let sum = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            inputs
+                .iter()
+                .map(|input| PolyInputTopology::downgrade(input, 1., 1))
+                .collect(),
+        ));
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![PolyInputTopology::downgrade(&sum, 1., 20)],
+        ));
+        let neurons = inputs.into_iter().chain([sum, output]).collect();
+        let genome = Genome::new(PolyNetworkTopology::from_raw_parts(
+            neurons,
+            MutationChances::none(),
+        ));
+        assert_eq!(
+            Objective::ExpandedTerms.measure(&genome, 0.),
+            (MAX_MEASURED_TERMS + 1) as f32
+        );
+    }
+}
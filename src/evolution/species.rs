@@ -0,0 +1,228 @@
+//! Grouping structurally similar genomes into species.
+//!
+//! Classic NEAT lines up the genes of two genomes by innovation number to measure how
+//! far apart they are. Genomes here have no innovation numbers, and
+//! [`replicate`](PolyNetworkTopology::replicate) gives every neuron of an offspring a fresh
+//! id, so there is nothing to line up. Genomes are instead compared by a few structural
+//! [`Traits`]: how many hidden neurons and connections they have, and the mean weight and
+//! exponent of those connections.
+//!
+//! Each generation, every genome joins the first species whose representative is within
+//! [`compatibility_threshold`](SpeciationSettings::with_compatibility_threshold) of it, or
+//! founds a new one. Fitness is then shared within each species, so a large species of
+//! near-identical genomes can't crowd out a small one that is still improving.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Result, prelude::*};
+
+/// How genomes are grouped into species.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeciationSettings {
+    compatibility_threshold: f32,
+    structure_coefficient: f32,
+    weight_coefficient: f32,
+    exponent_coefficient: f32,
+}
+
+impl Default for SpeciationSettings {
+    fn default() -> Self {
+        Self {
+            compatibility_threshold: 3.,
+            structure_coefficient: 1.,
+            weight_coefficient: 0.5,
+            exponent_coefficient: 0.5,
+        }
+    }
+}
+
+impl SpeciationSettings {
+    /// The largest [`distance`](Self::distance) at which two genomes share a species.
+    pub fn with_compatibility_threshold(mut self, compatibility_threshold: f32) -> Self {
+        self.compatibility_threshold = compatibility_threshold;
+        self
+    }
+
+    /// How much each differing hidden neuron or connection adds to the distance.
+    pub fn with_structure_coefficient(mut self, structure_coefficient: f32) -> Self {
+        self.structure_coefficient = structure_coefficient;
+        self
+    }
+
+    /// How much the difference in mean weight adds to the distance.
+    pub fn with_weight_coefficient(mut self, weight_coefficient: f32) -> Self {
+        self.weight_coefficient = weight_coefficient;
+        self
+    }
+
+    /// How much the difference in mean exponent adds to the distance.
+    pub fn with_exponent_coefficient(mut self, exponent_coefficient: f32) -> Self {
+        self.exponent_coefficient = exponent_coefficient;
+        self
+    }
+
+    pub fn compatibility_threshold(&self) -> f32 {
+        self.compatibility_threshold
+    }
+
+    pub fn structure_coefficient(&self) -> f32 {
+        self.structure_coefficient
+    }
+
+    pub fn weight_coefficient(&self) -> f32 {
+        self.weight_coefficient
+    }
+
+    pub fn exponent_coefficient(&self) -> f32 {
+        self.exponent_coefficient
+    }
+
+    /// The compatibility distance between two genomes.
+    pub fn distance(&self, a: &Traits, b: &Traits) -> f32 {
+        let structure =
+            (a.hidden.abs_diff(b.hidden) + a.connections.abs_diff(b.connections)) as f32;
+        self.structure_coefficient * structure
+            + self.weight_coefficient * (a.mean_weight - b.mean_weight).abs()
+            + self.exponent_coefficient * (a.mean_exponent - b.mean_exponent).abs()
+    }
+}
+
+/// What two genomes are compared by. See the [module docs](self).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Traits {
+    pub hidden: usize,
+    pub connections: usize,
+    pub mean_weight: f32,
+    pub mean_exponent: f32,
+}
+
+impl Traits {
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn of(topology: &PolyNetworkTopology) -> Result<Self> {
+        let mut traits = Traits::default();
+        let (mut weights, mut exponents) = (0., 0.);
+
+        for neuron in topology.neurons() {
+            let neuron = neuron.read()?;
+            if neuron.is_hidden() {
+                traits.hidden += 1;
+            }
+            for input in neuron
+                .props()
+                .map(|props| props.inputs())
+                .unwrap_or_default()
+            {
+                if input.neuron().is_none() {
+                    continue;
+                }
+                traits.connections += 1;
+                weights += input.weight();
+                exponents += input.exponent() as f32;
+            }
+        }
+
+        if traits.connections > 0 {
+            traits.mean_weight = weights / traits.connections as f32;
+            traits.mean_exponent = exponents / traits.connections as f32;
+        }
+        Ok(traits)
+    }
+}
+
+/// A group of similar genomes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Species {
+    pub id: usize,
+    /// The traits new members are compared against, taken from the species' first
+    /// member of the last generation.
+    pub representative: Traits,
+    /// Indices into the population.
+    pub members: Vec<usize>,
+}
+
+/// Sort `traits` into species, reusing the representatives of `previous` so species
+/// keep their ids across generations.
+///
+/// Species that end up empty are dropped, and the representative of every remaining
+/// species is updated to its first member. New species are numbered from `next_id`,
+/// which is advanced past every id handed out.
+pub fn speciate(
+    traits: &[Traits],
+    previous: &[Species],
+    settings: &SpeciationSettings,
+    next_id: &mut usize,
+) -> Vec<Species> {
+    let mut species: Vec<Species> = previous
+        .iter()
+        .map(|species| Species {
+            id: species.id,
+            representative: species.representative,
+            members: Vec::new(),
+        })
+        .collect();
+
+    for (index, genome) in traits.iter().enumerate() {
+        let compatible = species.iter_mut().find(|species| {
+            settings.distance(&species.representative, genome) <= settings.compatibility_threshold()
+        });
+        match compatible {
+            Some(species) => species.members.push(index),
+            None => {
+                species.push(Species {
+                    id: *next_id,
+                    representative: *genome,
+                    members: vec![index],
+                });
+                *next_id += 1;
+            }
+        }
+    }
+
+    species.retain(|species| !species.members.is_empty());
+    for species in species.iter_mut() {
+        species.representative = traits[species.members[0]];
+    }
+    species
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn traits(hidden: usize, connections: usize) -> Traits {
+        Traits {
+            hidden,
+            connections,
+            mean_weight: 0.5,
+            mean_exponent: 1.,
+        }
+    }
+
+    #[test]
+    fn species_keep_their_ids() {
+        let settings = SpeciationSettings::default().with_compatibility_threshold(2.);
+        let mut next_id = 0;
+
+        let first = speciate(
+            &[traits(0, 2), traits(5, 9), traits(1, 2)],
+            &[],
+            &settings,
+            &mut next_id,
+        );
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].members, vec![0, 2]);
+        assert_eq!(next_id, 2);
+
+        // the small species dies out and the large one is joined by a newcomer
+        let second = speciate(
+            &[traits(5, 10), traits(20, 40)],
+            &first,
+            &settings,
+            &mut next_id,
+        );
+        let ids: Vec<_> = second.iter().map(|species| species.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(second[0].representative, traits(5, 10));
+    }
+}
@@ -0,0 +1,388 @@
+//! Per-generation statistics for plotting learning curves.
+//!
+//! [`GenerationStats::of`] summarises a scored [`Population`]: the spread of its
+//! fitness, the size of its genomes, its species, the magnitude of its weights and
+//! exponents and the mean of every [`MutationChances`](crate::prelude::MutationChances) field, which drift as the
+//! chances evolve along with the genomes. A [`StatsWriter`] streams one row per
+//! generation as CSV or as JSON lines, flushing after every row so a running experiment
+//! can be plotted as it goes.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::evolution::{
+//!     Evolution, EvolutionSettings, Xor,
+//!     stats::{GenerationStats, StatsFormat, StatsWriter},
+//! };
+//! use polynomial_neat::prelude::*;
+//!
+//! let settings = EvolutionSettings::default()
+//!     .with_population_size(10)
+//!     .with_max_generations(3);
+//! let mut evolution = Evolution::new(settings, &Xor, MutationChances::new(50), 7);
+//! let mut writer = StatsWriter::new(Vec::new(), StatsFormat::Csv);
+//! evolution.run_with(&Xor, |evolution, _| {
+//!     writer.write(&GenerationStats::of(evolution.population()))
+//! })?;
+//!
+//! let csv = String::from_utf8(writer.into_inner()).unwrap();
+//! assert_eq!(csv.lines().count(), 4);
+//! assert!(csv.starts_with("generation,best_fitness,"));
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::Population;
+use crate::error::{PolyNeatError, Result};
+
+/// The statistics of one scored generation.
+///
+/// Genomes whose locks are poisoned are left out of the size, magnitude and chance
+/// statistics, but not out of the fitness ones.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: usize,
+    pub best_fitness: f32,
+    pub mean_fitness: f32,
+    pub median_fitness: f32,
+    /// The population standard deviation.
+    pub std_fitness: f32,
+    pub mean_neurons: f32,
+    pub max_neurons: usize,
+    pub mean_connections: f32,
+    pub max_connections: usize,
+    /// 0 without speciation.
+    pub num_species: usize,
+    /// Over every live connection in the population.
+    pub mean_abs_weight: f32,
+    /// Over every live connection in the population.
+    pub mean_abs_exponent: f32,
+    pub mean_self_mutation: f32,
+    pub mean_split_connection: f32,
+    pub mean_add_connection: f32,
+    pub mean_remove_connection: f32,
+    pub mean_mutate_weight: f32,
+    pub mean_mutate_exponent: f32,
+}
+
+/// The mean of `sum` over `count` values, or 0 if there are none.
+fn mean(sum: f32, count: usize) -> f32 {
+    if count == 0 { 0. } else { sum / count as f32 }
+}
+
+impl GenerationStats {
+    pub fn of(population: &Population) -> Self {
+        let mut stats = GenerationStats {
+            generation: population.generation(),
+            num_species: population.species().len(),
+            ..Default::default()
+        };
+
+        let mut fitness: Vec<f32> = population.genomes().iter().map(|g| g.fitness).collect();
+        fitness.sort_by(|a, b| b.total_cmp(a));
+        let n = fitness.len();
+        if n > 0 {
+            stats.best_fitness = fitness[0];
+            stats.mean_fitness = mean(fitness.iter().sum(), n);
+            stats.median_fitness = if n % 2 == 1 {
+                fitness[n / 2]
+            } else {
+                (fitness[n / 2 - 1] + fitness[n / 2]) / 2.
+            };
+            let variance = fitness
+                .iter()
+                .map(|f| (f - stats.mean_fitness).powi(2))
+                .sum::<f32>();
+            stats.std_fitness = mean(variance, n).sqrt();
+        }
+
+        let (mut readable, mut neurons, mut connections) = (0, 0, 0);
+        let (mut links, mut weights, mut exponents) = (0, 0., 0.);
+        let mut chances = [0.; 6];
+        for genome in population.genomes() {
+            let (Ok(data), Ok(info)) = (genome.topology.to_genome(), genome.topology.try_info())
+            else {
+                continue;
+            };
+            readable += 1;
+
+            neurons += info.num_neurons();
+            connections += info.num_connections;
+            stats.max_neurons = stats.max_neurons.max(info.num_neurons());
+            stats.max_connections = stats.max_connections.max(info.num_connections);
+
+            for connection in data.neurons.iter().flat_map(|neuron| neuron.inputs.iter()) {
+                links += 1;
+                weights += connection.weight.abs();
+                exponents += connection.exponent.unsigned_abs() as f32;
+            }
+
+            let c = data.mutation_chances;
+            let fields = [
+                c.self_mutation() as f32,
+                c.split_connection(),
+                c.add_connection(),
+                c.remove_connection(),
+                c.mutate_weight(),
+                c.mutate_exponent(),
+            ];
+            for (total, field) in chances.iter_mut().zip(fields) {
+                *total += field;
+            }
+        }
+
+        stats.mean_neurons = mean(neurons as f32, readable);
+        stats.mean_connections = mean(connections as f32, readable);
+        stats.mean_abs_weight = mean(weights, links);
+        stats.mean_abs_exponent = mean(exponents, links);
+        [
+            stats.mean_self_mutation,
+            stats.mean_split_connection,
+            stats.mean_add_connection,
+            stats.mean_remove_connection,
+            stats.mean_mutate_weight,
+            stats.mean_mutate_exponent,
+        ] = chances.map(|total| mean(total, readable));
+        stats
+    }
+
+    /// The CSV column names, in the order of [`csv_row`](Self::csv_row).
+    pub const COLUMNS: [&'static str; 18] = [
+        "generation",
+        "best_fitness",
+        "mean_fitness",
+        "median_fitness",
+        "std_fitness",
+        "mean_neurons",
+        "max_neurons",
+        "mean_connections",
+        "max_connections",
+        "num_species",
+        "mean_abs_weight",
+        "mean_abs_exponent",
+        "mean_self_mutation",
+        "mean_split_connection",
+        "mean_add_connection",
+        "mean_remove_connection",
+        "mean_mutate_weight",
+        "mean_mutate_exponent",
+    ];
+
+    pub fn csv_row(&self) -> String {
+        [
+            self.generation.to_string(),
+            self.best_fitness.to_string(),
+            self.mean_fitness.to_string(),
+            self.median_fitness.to_string(),
+            self.std_fitness.to_string(),
+            self.mean_neurons.to_string(),
+            self.max_neurons.to_string(),
+            self.mean_connections.to_string(),
+            self.max_connections.to_string(),
+            self.num_species.to_string(),
+            self.mean_abs_weight.to_string(),
+            self.mean_abs_exponent.to_string(),
+            self.mean_self_mutation.to_string(),
+            self.mean_split_connection.to_string(),
+            self.mean_add_connection.to_string(),
+            self.mean_remove_connection.to_string(),
+            self.mean_mutate_weight.to_string(),
+            self.mean_mutate_exponent.to_string(),
+        ]
+        .join(",")
+    }
+}
+
+/// How a [`StatsWriter`] lays out its rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    /// A header line, then one comma separated row per generation.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl StatsFormat {
+    /// The format of a `.csv` or `.jsonl` file.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(StatsFormat::Csv),
+            "jsonl" => Some(StatsFormat::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// Streams [`GenerationStats`] to a writer, one row at a time.
+pub struct StatsWriter<W: Write> {
+    out: W,
+    format: StatsFormat,
+    /// Named in I/O errors.
+    name: String,
+    needs_header: bool,
+}
+
+impl<W: Write> StatsWriter<W> {
+    pub fn new(out: W, format: StatsFormat) -> Self {
+        Self {
+            out,
+            format,
+            name: "stats".to_string(),
+            needs_header: format == StatsFormat::Csv,
+        }
+    }
+
+    /// Write and flush one row, preceded by the header if this is the first CSV row.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Io`] if the writer does.
+    pub fn write(&mut self, stats: &GenerationStats) -> Result<()> {
+        let mut text = String::new();
+        if self.needs_header {
+            text.push_str(&GenerationStats::COLUMNS.join(","));
+            text.push('\n');
+        }
+        match self.format {
+            StatsFormat::Csv => text.push_str(&stats.csv_row()),
+            StatsFormat::Jsonl => text.push_str(&serde_json::to_string(stats)?),
+        }
+        text.push('\n');
+
+        self.out
+            .write_all(text.as_bytes())
+            .and_then(|()| self.out.flush())
+            .map_err(|error| PolyNeatError::Io {
+                path: self.name.clone(),
+                message: error.to_string(),
+            })?;
+        self.needs_header = false;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl StatsWriter<BufWriter<File>> {
+    /// Write to a new `.csv` or `.jsonl` file at `path`, replacing any existing one.
+    ///
+    /// # Errors
+    /// Fails if the file can't be created or has neither extension.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open(path.as_ref(), false)
+    }
+
+    /// Add rows to the end of a `.csv` or `.jsonl` file, creating it if need be. A CSV
+    /// header is only written to a new or empty file.
+    ///
+    /// # Errors
+    /// Fails if the file can't be opened or has neither extension.
+    pub fn append(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open(path.as_ref(), true)
+    }
+
+    fn open(path: &Path, append: bool) -> Result<Self> {
+        let format = StatsFormat::from_path(path).ok_or_else(|| PolyNeatError::Io {
+            path: path.display().to_string(),
+            message: "expected a .csv or .jsonl file".to_string(),
+        })?;
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .map_err(|e| PolyNeatError::io(path, e))?;
+        let is_empty = file
+            .metadata()
+            .map_err(|e| PolyNeatError::io(path, e))?
+            .len()
+            == 0;
+
+        let mut writer = Self::new(BufWriter::new(file), format);
+        writer.name = path.display().to_string();
+        writer.needs_header &= is_empty;
+        Ok(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::{
+        evolution::{EvolutionSettings, Genome},
+        prelude::*,
+    };
+
+    fn population() -> Population {
+        let mut rng = StdRng::seed_from_u64(47);
+        let genomes = [1., 4., 2., 3.]
+            .map(|fitness| Genome {
+                topology: PolyNetworkTopology::new_thoroughly_connected(
+                    2,
+                    1,
+                    MutationChances::new(40),
+                    &mut rng,
+                ),
+                fitness,
+            })
+            .into();
+        Population::from_genomes(genomes, 6)
+    }
+
+    #[test]
+    fn summarises_the_population() {
+        let stats = GenerationStats::of(&population());
+
+        assert_eq!(stats.generation, 6);
+        assert_eq!(stats.best_fitness, 4.);
+        assert_eq!(stats.mean_fitness, 2.5);
+        assert_eq!(stats.median_fitness, 2.5);
+        assert_eq!(stats.std_fitness, 1.25f32.sqrt());
+        assert_eq!((stats.mean_neurons, stats.max_neurons), (3., 3));
+        assert_eq!((stats.mean_connections, stats.max_connections), (2., 2));
+        assert_eq!(stats.mean_self_mutation, 40.);
+        assert!(stats.mean_abs_weight > 0.);
+        assert_eq!(
+            GenerationStats::of(&Population::new(
+                &EvolutionSettings::default().with_population_size(0),
+                1,
+                1,
+                MutationChances::none(),
+                &mut StdRng::seed_from_u64(0),
+            ))
+            .mean_fitness,
+            0.
+        );
+    }
+
+    #[test]
+    fn streams_csv_and_json_lines() {
+        let stats = GenerationStats::of(&population());
+
+        let mut csv = StatsWriter::new(Vec::new(), StatsFormat::Csv);
+        csv.write(&stats).unwrap();
+        csv.write(&stats).unwrap();
+        let csv = String::from_utf8(csv.into_inner()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+        assert!(lines[1].starts_with("6,4,2.5,2.5,"));
+
+        let mut jsonl = StatsWriter::new(Vec::new(), StatsFormat::Jsonl);
+        jsonl.write(&stats).unwrap();
+        let jsonl = String::from_utf8(jsonl.into_inner()).unwrap();
+        let parsed: GenerationStats = serde_json::from_str(jsonl.trim_end()).unwrap();
+        assert_eq!(parsed, stats);
+    }
+}
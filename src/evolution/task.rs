@@ -0,0 +1,299 @@
+//! Problems a population can be evolved against.
+
+use std::{fs, path::Path};
+
+use crate::{
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
+
+/// Something a network can be scored on. Higher fitness is better.
+///
+/// Networks are handed over as a [`CompiledNetwork`], so a task can call
+/// [`predict`](CompiledNetwork::predict) as often as it needs without touching a lock.
+pub trait Task: Sync {
+    fn num_inputs(&self) -> usize;
+
+    fn num_outputs(&self) -> usize;
+
+    /// Score one network. Should be finite; anything else is treated as 0.
+    fn fitness(&self, network: &CompiledNetwork) -> f32;
+
+    /// Characterise what a network does, for [novelty search](super::novelty). Networks
+    /// with similar behaviour should get nearby vectors of the same length.
+    ///
+    /// Tasks without a behaviour return `None`, the default, which gives every network
+    /// a novelty of 0.
+    fn behaviour(&self, _network: &SimplePolyNetwork) -> Option<Vec<f32>> {
+        None
+    }
+}
+
+/// Exclusive or of two inputs, into one output.
+///
+/// Every case contributes its absolute error clamped to `[0, 1]`, and the fitness is
+/// `((4 - total error) / 4)^2`, so a perfect network scores 1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Xor;
+
+impl Xor {
+    const CASES: [([f32; 2], f32); 4] = [
+        ([0., 0.], 0.),
+        ([0., 1.], 1.),
+        ([1., 0.], 1.),
+        ([1., 1.], 0.),
+    ];
+}
+
+impl Task for Xor {
+    fn num_inputs(&self) -> usize {
+        2
+    }
+
+    fn num_outputs(&self) -> usize {
+        1
+    }
+
+    fn fitness(&self, network: &CompiledNetwork) -> f32 {
+        if network.num_outputs() == 0 {
+            return 0.;
+        }
+
+        let mut total_error = 0.;
+        for (inputs, expected) in Self::CASES.iter() {
+            let error = (network.predict(inputs)[0] - expected).abs().clamp(0., 1.);
+            total_error += if error.is_finite() { error } else { 1. };
+        }
+
+        let fitness = 4. - total_error;
+        fitness * fitness / 16.
+    }
+
+    /// The output on each of the four cases.
+    fn behaviour(&self, network: &SimplePolyNetwork) -> Option<Vec<f32>> {
+        let outputs = Self::CASES
+            .iter()
+            .map(|(inputs, _)| network.predict(inputs).next().unwrap_or(0.));
+        Some(outputs.collect())
+    }
+}
+
+/// Regression on a table of samples, scored as `1 / (1 + mean squared error)`.
+///
+/// # Example
+///
+/// ```rust
+/// use polynomial_neat::evolution::{Dataset, Task};
+///
+/// // y = x0 * x1
+/// let dataset = Dataset::from_csv_str("x0,x1,y\n1,2,2\n3,4,12\n", 2)?;
+/// assert_eq!(dataset.len(), 2);
+/// assert_eq!(dataset.num_outputs(), 1);
+/// # Ok::<(), polynomial_neat::prelude::PolyNeatError>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dataset {
+    inputs: Vec<Vec<f32>>,
+    targets: Vec<Vec<f32>>,
+}
+
+impl Dataset {
+    /// # Panics
+    /// Panics if `inputs` and `targets` differ in length, or if their rows don't all
+    /// have the same width.
+    pub fn new(inputs: Vec<Vec<f32>>, targets: Vec<Vec<f32>>) -> Self {
+        assert_eq!(inputs.len(), targets.len(), "one target row per input row");
+        assert!(
+            inputs.windows(2).all(|rows| rows[0].len() == rows[1].len())
+                && targets
+                    .windows(2)
+                    .all(|rows| rows[0].len() == rows[1].len()),
+            "rows must all have the same width"
+        );
+        Self { inputs, targets }
+    }
+
+    /// Parse comma separated rows whose first `num_inputs` columns are inputs and whose
+    /// remaining columns are targets. See [`parse_csv`] for the format.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Parse`] if the rows don't parse or have no target
+    /// column.
+    pub fn from_csv_str(csv: &str, num_inputs: usize) -> Result<Self> {
+        let rows = parse_csv(csv)?;
+        if rows[0].len() <= num_inputs {
+            return Err(PolyNeatError::parse(format!(
+                "expected more than {num_inputs} columns, found {}",
+                rows[0].len()
+            )));
+        }
+
+        let (inputs, targets) = rows
+            .into_iter()
+            .map(|mut row| {
+                let targets = row.split_off(num_inputs);
+                (row, targets)
+            })
+            .unzip();
+        Ok(Self { inputs, targets })
+    }
+
+    /// Read a file in the format of [`from_csv_str`](Self::from_csv_str).
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or doesn't parse.
+    pub fn from_csv(path: impl AsRef<Path>, num_inputs: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let csv = fs::read_to_string(path).map_err(|e| PolyNeatError::io(path, e))?;
+        Self::from_csv_str(&csv, num_inputs)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn inputs(&self) -> &[Vec<f32>] {
+        &self.inputs
+    }
+
+    pub fn targets(&self) -> &[Vec<f32>] {
+        &self.targets
+    }
+
+    /// The mean squared error of `network` over every target of every sample.
+    ///
+    /// Missing outputs count as 0.
+    pub fn mean_squared_error(&self, network: &CompiledNetwork) -> f32 {
+        let mut total = 0.;
+        let mut count = 0;
+        for (inputs, targets) in self.inputs.iter().zip(self.targets.iter()) {
+            let outputs = network.predict(inputs);
+            for (i, target) in targets.iter().enumerate() {
+                let output = outputs.get(i).copied().unwrap_or(0.);
+                total += (output - target).powi(2);
+                count += 1;
+            }
+        }
+        total / count.max(1) as f32
+    }
+}
+
+/// Parse comma separated rows of numbers.
+///
+/// Blank lines and lines starting with `#` are skipped, as is a first line that doesn't
+/// parse as numbers, so a header row is allowed.
+///
+/// # Errors
+/// Fails with [`PolyNeatError::Parse`] naming the line of a malformed row or of one that
+/// is wider or narrower than the first, or if there are no rows.
+pub fn parse_csv(csv: &str) -> Result<Vec<Vec<f32>>> {
+    let mut rows: Vec<Vec<f32>> = Vec::new();
+
+    for (line_i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let row = match parse_row(line) {
+            Ok(row) => row,
+            Err(_) if line_i == 0 => continue,
+            Err(message) => {
+                return Err(PolyNeatError::parse(format!(
+                    "line {}: {message}",
+                    line_i + 1
+                )));
+            }
+        };
+        if let Some(first) = rows.first()
+            && first.len() != row.len()
+        {
+            return Err(PolyNeatError::parse(format!(
+                "line {}: expected {} columns, found {}",
+                line_i + 1,
+                first.len(),
+                row.len()
+            )));
+        }
+        rows.push(row);
+    }
+
+    if rows.is_empty() {
+        return Err(PolyNeatError::parse("no rows"));
+    }
+    Ok(rows)
+}
+
+/// Read a file in the format of [`parse_csv`].
+///
+/// # Errors
+/// Fails if the file can't be read or doesn't parse.
+pub fn read_csv(path: impl AsRef<Path>) -> Result<Vec<Vec<f32>>> {
+    let path = path.as_ref();
+    let csv = fs::read_to_string(path).map_err(|e| PolyNeatError::io(path, e))?;
+    parse_csv(&csv)
+}
+
+/// Splits a row into numbers.
+fn parse_row(line: &str) -> std::result::Result<Vec<f32>, String> {
+    line.split(',')
+        .map(|value| {
+            let value = value.trim();
+            value
+                .parse::<f32>()
+                .map_err(|_| format!("{value:?} is not a number"))
+        })
+        .collect()
+}
+
+impl Task for Dataset {
+    fn num_inputs(&self) -> usize {
+        self.inputs.first().map_or(0, Vec::len)
+    }
+
+    fn num_outputs(&self) -> usize {
+        self.targets.first().map_or(0, Vec::len)
+    }
+
+    fn fitness(&self, network: &CompiledNetwork) -> f32 {
+        let error = self.mean_squared_error(network);
+        if error.is_finite() {
+            1. / (1. + error)
+        } else {
+            0.
+        }
+    }
+
+    /// Every output on every sample, sample by sample.
+    fn behaviour(&self, network: &SimplePolyNetwork) -> Option<Vec<f32>> {
+        let outputs = network.predict_many(&self.inputs);
+        Some(outputs.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_errors_name_the_line() {
+        let error = Dataset::from_csv_str("a,b,y\n1,2,3\n4,5\n", 2).unwrap_err();
+        assert_eq!(
+            error,
+            PolyNeatError::parse("line 3: expected 3 columns, found 2")
+        );
+
+        let error = Dataset::from_csv_str("1,2,3\n# comment\n\n4,x,6\n", 2).unwrap_err();
+        assert_eq!(error, PolyNeatError::parse("line 4: \"x\" is not a number"));
+
+        let error = Dataset::from_csv_str("1,2\n3,4\n", 2).unwrap_err();
+        assert_eq!(
+            error,
+            PolyNeatError::parse("expected more than 2 columns, found 2")
+        );
+    }
+}
@@ -109,7 +109,7 @@
 //! # let topology = PolyNetworkTopology::new(2, 2, mutation_chances, &mut rand::rng());
 //! // Create network on CPU backend
 //! let device = burn::backend::ndarray::NdArrayDevice::default();
-//! let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+//! let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 //!
 //! // Make predictions
 //! let outputs = burn_network.predict(&[1.0, 0.5]);
@@ -128,11 +128,26 @@
 pub mod burn_net;
 // pub mod candle_net;  // Commented out - replaced by burn_net
 
+/// Dependency-free Rust source generation for evolved networks.
+pub mod codegen;
+
+/// Lock-free CPU evaluator that runs a network in a single linear pass.
+pub mod compiled_net;
+
+/// Differential testing that runs every evaluator on the same genome and compares them.
+pub mod cross_check;
+
 /// Core components for polynomial networks.
 ///
 /// Includes activation functions, neuron implementations, and input handling.
 pub mod core;
 
+/// A generational evolution loop, the tasks it evolves against and its experiment files.
+pub mod evolution;
+
+/// The crate-wide error type returned by fallible constructors.
+pub mod error;
+
 /// Simple CPU-based polynomial network implementation.
 ///
 /// Useful for debugging, testing, and environments without GPU support.
@@ -146,17 +161,20 @@ pub mod topology;
 mod test_utils;
 
 pub mod prelude {
+    pub use super::compiled_net::CompiledNetwork;
     pub use super::core::{
         activation::{Bias, Exponent},
         input::PolyInput,
         //neuron::PolyNeuronInner,
         neuron_type::{NeuronType, PolyProps, PropsType},
     };
+    pub use super::error::PolyNeatError;
     pub use super::simple_net::{
         input::NeuronInput, network::SimplePolyNetwork, neuron::SimpleNeuron,
         neuron_type::NeuronProps,
     };
     pub use super::topology::{
+        genome::GenomeData,
         input::PolyInputTopology,
         mutation::{MAX_MUTATIONS, MutationAction, MutationChances},
         network::PolyNetworkTopology,
@@ -1,102 +1,411 @@
-use std::io::{self, Write};
+//! Command line interface: evolve genomes, then run, inspect and cross-check them.
+//!
+//! ```text
+//! polynomial-neat evolve experiment.toml --output best.json --checkpoint run.ckpt --resume
+//! polynomial-neat eval best.json --inputs 0,1
+//! polynomial-neat eval best.json --csv samples.csv
+//! polynomial-neat inspect best.json --formula --format latex
+//! polynomial-neat crosscheck best.json --inputs 0.5,2 --backend ndarray
+//! ```
 
-use burn::backend::Cuda;
-// use burn::backend::{Cuda, Wgpu};
-use polynomial_neat::{
-    burn_net::network::BurnNetwork, prelude::*, topology::mutation::MutationChances,
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
-use rand::{SeedableRng, rngs::StdRng};
-use tracing::info;
 
-//const MAX_GEN: i32 = 10;
-const MAX_GEN: i32 = 5000;
+use burn::backend::{Cuda, NdArray, Wgpu};
+use clap::{Parser, Subcommand, ValueEnum};
+use polynomial_neat::{
+    burn_net::expander::ExpressionFormat,
+    core::validation::InputValidation,
+    cross_check::{CrossCheckReport, cross_check_with},
+    error::Result,
+    evolution::{
+        Evolution, ExperimentConfig, GenerationSummary,
+        stats::{GenerationStats, StatsWriter},
+        task::read_csv,
+    },
+    prelude::*,
+    topology::dot::DotOptions,
+};
 
-fn main() {
-    tracing_subscriber::fmt::init();
-    let mutation_chances = MutationChances::new_from_raw(5, 80., 50., 5., 60., 0.);
+#[derive(Parser)]
+#[command(version, about = "Evolve and examine polynomial networks")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-    let mut seed = 0;
-    let mut rng = StdRng::seed_from_u64(0);
+#[derive(Subcommand)]
+enum Command {
+    /// Evolve a population on the task of an experiment file.
+    Evolve {
+        /// The experiment to run, a `.toml` or `.json` file.
+        config: PathBuf,
+        /// Where to save the fittest genome.
+        #[arg(short, long, default_value = "best.json")]
+        output: PathBuf,
+        /// Only print the final summary.
+        #[arg(short, long)]
+        quiet: bool,
+        /// Save the whole run here every few generations and at the end.
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+        /// How many generations apart checkpoints are saved.
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..), requires = "checkpoint")]
+        checkpoint_every: u64,
+        /// Continue from the checkpoint if it exists, with the settings it was saved with.
+        /// The task is still read from the experiment.
+        #[arg(long, requires = "checkpoint")]
+        resume: bool,
+        /// Record statistics of every generation to a `.csv` or `.jsonl` file. A resumed
+        /// run appends to it, repeating any generations after its checkpoint.
+        #[arg(long)]
+        stats: Option<PathBuf>,
+        /// Save the hall of fame to this directory as `1.json`, `2.json` and so on,
+        /// fittest first.
+        #[arg(long)]
+        hall_of_fame: Option<PathBuf>,
+        /// Save the first Pareto front of the last generation to this directory as
+        /// `1.json`, `2.json` and so on, fittest first, with the value of every objective
+        /// in `front.csv`.
+        #[arg(long)]
+        pareto_front: Option<PathBuf>,
+    },
+    /// Run a saved genome and print one row of outputs per sample.
+    ///
+    /// Samples with the wrong number of inputs, and genomes with a disconnected output,
+    /// are refused rather than padded with zeros.
+    Eval {
+        genome: PathBuf,
+        /// One comma separated sample.
+        #[arg(
+            long,
+            value_delimiter = ',',
+            allow_hyphen_values = true,
+            conflicts_with = "csv"
+        )]
+        inputs: Option<Vec<f32>>,
+        /// A CSV file whose first columns are the inputs of each sample. Extra columns are
+        /// ignored, but every row needs a column per input.
+        #[arg(long, required_unless_present = "inputs")]
+        csv: Option<PathBuf>,
+    },
+    /// Print a saved genome's statistics, graph or closed-form formula.
+    Inspect {
+        genome: PathBuf,
+        /// Print the network as a Graphviz digraph.
+        #[arg(long, conflicts_with = "formula")]
+        dot: bool,
+        /// Print the expanded polynomial of every output.
+        #[arg(long)]
+        formula: bool,
+        /// How to render the formula.
+        #[arg(long, value_enum, default_value_t = Format::Text, requires = "formula")]
+        format: Format,
+    },
+    /// Compare every evaluator on one sample, minimising the genome if they disagree.
+    Crosscheck {
+        genome: PathBuf,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            allow_hyphen_values = true,
+            required = true
+        )]
+        inputs: Vec<f32>,
+        /// The largest relative difference still considered equal.
+        #[arg(long, default_value_t = 1e-3)]
+        tolerance: f32,
+        /// Where the Burn evaluators run.
+        #[arg(long, value_enum, default_value_t = Backend::Ndarray)]
+        backend: Backend,
+    },
+}
 
-    let mut running_topology = PolyNetworkTopology::new(2, 2, mutation_chances, &mut rng);
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Latex,
+    Rust,
+    Python,
+}
 
-    //let device = burn::backend::wgpu::WgpuDevice::DiscreteGpu(0);
-    let device = burn::backend::cuda::CudaDevice::default();
+impl From<Format> for ExpressionFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Text => ExpressionFormat::Text,
+            Format::Latex => ExpressionFormat::Latex,
+            Format::Rust => ExpressionFormat::Rust,
+            Format::Python => ExpressionFormat::Python,
+        }
+    }
+}
 
-    let mut generation = 0;
-    let mut step_for_seed = true;
-    let mut step = false;
-    loop {
-        let mut next = running_topology.replicate(&mut rng);
+#[derive(Clone, Copy, ValueEnum)]
+enum Backend {
+    Ndarray,
+    Wgpu,
+    Cuda,
+}
 
-        loop {
-            let info = next.info();
+fn main() -> ExitCode {
+    tracing_subscriber::fmt::init();
 
-            if info.num_inputs == 0 || info.num_outputs == 0 {
-                next = running_topology.replicate(&mut rng);
-                continue;
-            }
-            break;
+    match run(Cli::parse().command) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
         }
+    }
+}
 
-        let info = next.info();
-        info!("Topology passed! {:?}", info);
-
-        running_topology = next;
-
-        let running_network = running_topology.to_simple_network();
+fn run(command: Command) -> Result<ExitCode> {
+    match command {
+        Command::Evolve {
+            config,
+            output,
+            quiet,
+            checkpoint,
+            checkpoint_every,
+            resume,
+            stats,
+            hall_of_fame,
+            pareto_front,
+        } => {
+            let config = ExperimentConfig::load(config)?;
+            let task = config.load_task()?;
+            let resuming = resume && checkpoint.as_ref().is_some_and(|path| path.exists());
+            let mut evolution = match &checkpoint {
+                Some(checkpoint) if resuming => {
+                    let evolution = Evolution::resume(checkpoint)?;
+                    println!(
+                        "resuming {} at generation {}",
+                        checkpoint.display(),
+                        evolution.population().generation()
+                    );
+                    evolution
+                }
+                _ => Evolution::new(
+                    config.settings(),
+                    &task,
+                    config.mutation.chances(),
+                    config.seed,
+                ),
+            };
 
-        let result = running_network.predict(&[1., 5.]).collect::<Vec<f32>>();
-        let burn_network = BurnNetwork::<Cuda>::from_topology(&running_topology, device.clone());
-        let burn_result = burn_network.predict(&[1., 5.]);
+            let mut stats = match stats {
+                Some(path) if resuming => Some(StatsWriter::append(path)?),
+                Some(path) => Some(StatsWriter::create(path)?),
+                None => None,
+            };
+            let report = |evolution: &Evolution, summary: &GenerationSummary| {
+                if !quiet {
+                    println!("{summary}");
+                }
+                match &mut stats {
+                    Some(stats) => stats.write(&GenerationStats::of(evolution.population())),
+                    None => Ok(()),
+                }
+            };
+            let last = match &checkpoint {
+                Some(checkpoint) => evolution.run_with_checkpoints(
+                    &task,
+                    checkpoint,
+                    checkpoint_every as usize,
+                    report,
+                )?,
+                None => evolution.run_with(&task, report)?,
+            };
+            // the hall of fame remembers champions that were bred away
+            let (best, fitness) = match evolution.hall_of_fame().best() {
+                Some(entry) => (entry.topology()?, entry.fitness),
+                None => (evolution.best().topology.clone(), evolution.best().fitness),
+            };
+            best.save_json(&output)?;
+            println!("{last}");
+            println!(
+                "saved the fittest genome ({fitness:.4}) to {}",
+                output.display()
+            );
 
-        let mut difference = Vec::new();
+            if let Some(dir) = hall_of_fame {
+                fs::create_dir_all(&dir).map_err(io_error(&dir))?;
+                for (rank, entry) in evolution.hall_of_fame().entries().iter().enumerate() {
+                    let path = dir.join(format!("{}.json", rank + 1));
+                    fs::write(&path, entry.genome.to_json()).map_err(io_error(&path))?;
+                }
+                println!(
+                    "saved {} hall of fame genomes to {}",
+                    evolution.hall_of_fame().len(),
+                    dir.display()
+                );
+            }
 
-        #[allow(clippy::unused_enumerate_index)]
-        for (_i, (cpu_p, burn_p)) in result.into_iter().zip(burn_result.into_iter()).enumerate() {
-            if (cpu_p - burn_p).abs() > 0.01 {
-                difference.push((cpu_p, burn_p));
+            if let Some(dir) = pareto_front {
+                // the front is on fitness against expanded terms unless configured
+                let pareto = evolution.settings().pareto().cloned().unwrap_or_default();
+                let front = evolution.pareto_front();
+                fs::create_dir_all(&dir).map_err(io_error(&dir))?;
+                let mut csv = String::from("file");
+                for objective in pareto.objectives() {
+                    csv.push_str(&format!(",{objective}"));
+                }
+                csv.push('\n');
+                for (rank, point) in front.iter().enumerate() {
+                    let file = format!("{}.json", rank + 1);
+                    let path = dir.join(&file);
+                    let genome = &evolution.population().genomes()[point.index];
+                    genome.topology.save_json(&path)?;
+                    csv.push_str(&file);
+                    for value in point.values.iter() {
+                        csv.push_str(&format!(",{value}"));
+                    }
+                    csv.push('\n');
+                }
+                let path = dir.join("front.csv");
+                fs::write(&path, csv).map_err(io_error(&path))?;
+                println!(
+                    "saved the {} genomes of the Pareto front to {}",
+                    front.len(),
+                    dir.display()
+                );
             }
         }
-        if !difference.is_empty() {
-            info!("difference found:");
-            info!(
-                "==network_len==\n\
-                num_nodes: {}\n\
-                num_inputs: {}\n\
-                num_outputs: {}",
-                running_network.num_nodes(),
-                running_network.num_inputs(),
-                running_network.num_outputs()
-            );
-            let mut output = String::new();
-            for (cpu_p, burn_p) in difference {
-                output.push_str(&format!("(cpu: {cpu_p}, gpu: {burn_p}), "));
+        Command::Eval {
+            genome,
+            inputs,
+            csv,
+        } => {
+            let network = CompiledNetwork::from_topology(&PolyNetworkTopology::load_json(genome)?)?;
+            let samples = match (inputs, csv) {
+                (Some(inputs), _) => vec![inputs],
+                (None, Some(csv)) => read_csv(csv)?
+                    .into_iter()
+                    .map(|mut row| {
+                        row.truncate(network.num_inputs());
+                        row
+                    })
+                    .collect(),
+                (None, None) => unreachable!("clap requires --inputs or --csv"),
+            };
+            for sample in samples {
+                let outputs = network.try_predict(&sample, &InputValidation::default())?;
+                println!("{}", join(&outputs));
             }
-            info!("{}", output);
-
-            info!("Seed: {}", seed);
-            step = true;
         }
-
-        if step_for_seed && step {
-            info!("Press enter to continue");
-            io::stdout().flush().unwrap();
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            step_for_seed = false;
-            step = false;
+        Command::Inspect {
+            genome,
+            dot,
+            formula,
+            format,
+        } => {
+            let topology = PolyNetworkTopology::load_json(genome)?;
+            if dot {
+                println!(
+                    "{}",
+                    topology.to_dot(&DotOptions::default().with_layers(true))
+                );
+            } else if formula {
+                for (i, polynomial) in topology.output_polynomials()?.iter().enumerate() {
+                    println!("y{i} = {}", polynomial.expression(format.into()));
+                }
+            } else {
+                print_stats(&topology)?;
+            }
         }
-
-        generation += 1;
-        if generation > MAX_GEN {
-            running_topology = PolyNetworkTopology::new(2, 20, mutation_chances, &mut rng);
-            generation = 0;
-            step_for_seed = true;
-            step = false;
-            seed += 1;
-            info!("Generation {seed}");
-            rng = StdRng::seed_from_u64(seed);
+        Command::Crosscheck {
+            genome,
+            inputs,
+            tolerance,
+            backend,
+        } => {
+            let topology = PolyNetworkTopology::load_json(genome)?;
+            let report: CrossCheckReport = match backend {
+                Backend::Ndarray => {
+                    cross_check_with::<NdArray>(&topology, &inputs, tolerance, Default::default())?
+                }
+                Backend::Wgpu => {
+                    cross_check_with::<Wgpu>(&topology, &inputs, tolerance, Default::default())?
+                }
+                Backend::Cuda => {
+                    cross_check_with::<Cuda>(&topology, &inputs, tolerance, Default::default())?
+                }
+            };
+            println!("{report}");
+            if !report.is_consistent() {
+                return Ok(ExitCode::FAILURE);
+            }
         }
     }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Name `path` in an I/O error.
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> PolyNeatError + '_ {
+    move |error| PolyNeatError::Io {
+        path: path.display().to_string(),
+        message: error.to_string(),
+    }
+}
+
+fn print_stats(topology: &PolyNetworkTopology) -> Result<()> {
+    let info = topology.try_info()?;
+    let network = CompiledNetwork::from_topology(topology)?;
+    let depth = topology.try_depths()?.into_values().max().unwrap_or(0);
+
+    println!("inputs:      {}", info.num_inputs);
+    println!("hidden:      {}", info.num_hidden);
+    println!("outputs:     {}", info.num_outputs);
+    println!("connections: {}", network.num_connections());
+    println!("depth:       {depth}");
+    let estimate = topology.estimate_expansion()?;
+    println!(
+        "expansion:   at most {} terms of degree {}",
+        estimate.terms, estimate.degree
+    );
+    Ok(())
+}
+
+fn join(values: &[f32]) -> String {
+    values
+        .iter()
+        .map(f32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_negative_inputs() {
+        let cli = Cli::try_parse_from([
+            "polynomial-neat",
+            "crosscheck",
+            "genome.json",
+            "--inputs",
+            "-1,2.5",
+            "--backend",
+            "wgpu",
+        ])
+        .unwrap();
+
+        let Command::Crosscheck {
+            inputs, backend, ..
+        } = cli.command
+        else {
+            panic!("expected crosscheck");
+        };
+        assert_eq!(inputs, vec![-1., 2.5]);
+        assert!(matches!(backend, Backend::Wgpu));
+    }
+
+    #[test]
+    fn eval_needs_a_sample() {
+        assert!(Cli::try_parse_from(["polynomial-neat", "eval", "genome.json"]).is_err());
+    }
 }
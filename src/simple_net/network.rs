@@ -1,8 +1,17 @@
 use std::sync::{Arc, RwLock};
 
-use rayon::iter::{IndexedParallelIterator as _, IntoParallelRefIterator, ParallelIterator as _};
-
-use crate::prelude::*;
+use fnv::FnvHashMap;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator as _};
+use uuid::Uuid;
+
+use crate::{
+    core::{
+        numeric::{NumericFlag, NumericPolicy},
+        validation::InputValidation,
+    },
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
 
 /// A simple CPU-based polynomial neural network for inference.
 ///
@@ -22,8 +31,11 @@ use crate::prelude::*;
 ///
 /// # Thread Safety
 ///
-/// The network uses `Arc<RwLock<>>` for thread-safe access to neurons,
-/// allowing parallel evaluation using Rayon.
+/// Evaluation never writes to the neurons. Intermediate values live in a
+/// [`PredictState`] owned by the caller (or created per call by [`predict`]),
+/// so one network can be shared across threads and evaluated concurrently.
+///
+/// [`predict`]: SimplePolyNetwork::predict
 ///
 /// # Example
 ///
@@ -50,17 +62,62 @@ pub struct SimplePolyNetwork {
     input_layer: Vec<Arc<RwLock<SimpleNeuron>>>,
     // contains the output neurons. cloned arc of neurons in neurons
     output_layer: Vec<Arc<RwLock<SimpleNeuron>>>,
+    // position of each neuron in neurons, used to index into a PredictState
+    positions: FnvHashMap<Uuid, usize>,
+    // position of each input layer neuron in neurons
+    input_positions: Vec<usize>,
+    // indices into output_layer of outputs without inputs
+    disconnected_outputs: Vec<usize>,
+    numeric_policy: NumericPolicy,
+    // shared with the topology when built by from_topology
+    numeric_flag: Arc<NumericFlag>,
+}
+
+/// Per-evaluation working values for a [`SimplePolyNetwork`].
+///
+/// Holds the activated value of every neuron during a forward pass. Keeping this
+/// outside of the network is what allows several threads to evaluate the same network
+/// at once: give each thread its own state. A state can be reused across calls to
+/// avoid allocating.
+///
+/// # Example
+/// ```rust
+/// # use polynomial_neat::prelude::*;
+/// use polynomial_neat::simple_net::network::PredictState;
+///
+/// # let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+/// let network = topology.to_simple_network();
+/// let mut state = PredictState::default();
+///
+/// for inputs in [[0.0, 1.0], [1.0, 0.0]] {
+///     let outputs = network.predict_with(&inputs, &mut state);
+///     assert_eq!(outputs.len(), 1);
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PredictState {
+    values: Vec<Option<f32>>,
+}
+
+impl PredictState {
+    fn reset(&mut self, num_neurons: usize) {
+        self.values.clear();
+        self.values.resize(num_neurons, None);
+    }
 }
 
 impl SimplePolyNetwork {
     /// Perform a forward pass through the network with the given inputs.
     ///
     /// This method:
-    /// 1. Resets all neuron states to prepare for a fresh computation
+    /// 1. Creates a fresh [`PredictState`] for this call
     /// 2. Sets the input values on input neurons
     /// 3. Propagates values through the network
     /// 4. Returns the outputs from output neurons
     ///
+    /// Since the network itself isn't modified, this can be called from many threads
+    /// at once. Use [`predict_with`](Self::predict_with) to reuse a state between calls.
+    ///
     /// # Arguments
     /// * `inputs` - Slice of input values. Length should match the number of input neurons.
     ///
@@ -84,37 +141,117 @@ impl SimplePolyNetwork {
     /// If there are fewer inputs than input neurons, the remaining neurons
     /// will have their state set to 0.
     pub fn predict(&self, inputs: &[f32]) -> impl Iterator<Item = f32> {
-        // reset all states first
-        self.neurons.par_iter().for_each(|neuron| {
-            let mut neuron = neuron.write().unwrap();
-            neuron.flush_state();
-        });
-        inputs.par_iter().enumerate().for_each(|(index, value)| {
-            let Some(nw) = self.input_layer.get(index) else {
-                //sim
-                return;
-                //panic!("couldn't flush i {}", index);
-            };
-            let mut nw = nw.write().unwrap();
-            nw.override_state(*value);
-        });
+        let mut state = PredictState::default();
+        self.predict_with(inputs, &mut state).into_iter()
+    }
+
+    /// Perform a forward pass, keeping intermediate values in a caller-owned state.
+    ///
+    /// The state is reset at the start of every call, so it can be reused for any
+    /// number of calls and across networks.
+    ///
+    /// # Returns
+    /// The output values, in output layer order.
+    ///
+    /// # Panics
+    /// Panics if a neuron lock is poisoned. [`try_predict`](Self::try_predict) reports
+    /// that case instead.
+    pub fn predict_with(&self, inputs: &[f32], state: &mut PredictState) -> Vec<f32> {
+        self.evaluate(inputs, state)
+            .expect("failed to read a neuron")
+    }
+
+    fn evaluate(&self, inputs: &[f32], state: &mut PredictState) -> Result<Vec<f32>> {
+        state.reset(self.neurons.len());
+
+        for (position, value) in self.input_positions.iter().zip(inputs) {
+            state.values[*position] = Some(*value);
+        }
 
-        let outputs = self
+        let mut outputs = self
             .output_layer
-            .par_iter()
-            .fold(Vec::new, |mut values, neuron| {
-                let mut neuron = neuron.write().unwrap();
+            .iter()
+            .map(|neuron| self.activate(&*neuron.read()?, state))
+            .collect::<Result<Vec<f32>>>()?;
+        self.numeric_policy.apply(&mut outputs, &self.numeric_flag);
+        Ok(outputs)
+    }
 
-                values.push(neuron.activate());
+    /// Perform a forward pass after checking `inputs` against `validation`.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::Predict`] describing the first failed check, in which
+    /// case the network is not evaluated, or if a neuron lock is poisoned.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use polynomial_neat::prelude::*;
+    /// use polynomial_neat::core::validation::{InputValidation, PredictError};
+    ///
+    /// # let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+    /// let network = topology.to_simple_network();
+    /// let result = network.try_predict(&[1.0, 2.0, 3.0], &InputValidation::default());
+    /// assert_eq!(
+    ///     result,
+    ///     Err(PolyNeatError::Predict(PredictError::InputLength { expected: 2, actual: 3 }))
+    /// );
+    /// ```
+    pub fn try_predict(&self, inputs: &[f32], validation: &InputValidation) -> Result<Vec<f32>> {
+        validation.validate(inputs, self.num_inputs(), &self.disconnected_outputs)?;
+        self.evaluate(inputs, &mut PredictState::default())
+    }
 
-                values
+    /// Evaluate many samples in parallel, one [`PredictState`] per rayon worker.
+    ///
+    /// # Returns
+    /// The outputs for each sample, in the same order as `inputs`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use polynomial_neat::prelude::*;
+    /// # let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+    /// let network = topology.to_simple_network();
+    /// let dataset = vec![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+    ///
+    /// let outputs = network.predict_many(&dataset);
+    /// assert_eq!(outputs.len(), 4);
+    /// ```
+    pub fn predict_many<R: AsRef<[f32]> + Sync>(&self, inputs: &[R]) -> Vec<Vec<f32>> {
+        inputs
+            .par_iter()
+            .map_init(PredictState::default, |state, sample| {
+                self.predict_with(sample.as_ref(), state)
             })
-            .collect_vec_list();
+            .collect()
+    }
+
+    fn activate(&self, neuron: &SimpleNeuron, state: &mut PredictState) -> Result<f32> {
+        let position = self.positions.get(&neuron.id()).copied();
+        if let Some(value) = position.and_then(|position| state.values[position]) {
+            return Ok(value);
+        }
 
-        outputs
-            .into_iter()
-            .flat_map(|outer_vec| outer_vec.into_iter())
-            .flat_map(|inner_vec| inner_vec.into_iter())
+        let value = match neuron.inputs() {
+            // an input neuron that wasn't given a value
+            None => 0.,
+            Some(inputs) => inputs
+                .iter()
+                .map(|input| {
+                    // don't need to activate the neuron since x^0 = 1
+                    if input.exponent() == 0 {
+                        return Ok(input.weight());
+                    }
+                    let source = self.activate(&*input.input().read()?, state)?;
+                    //note that this can totally return inf if the value is zero. This is intentional.
+                    Ok(self.numeric_policy.powi(source, input.exponent()) * input.weight())
+                })
+                .sum::<Result<f32>>()?,
+        };
+
+        if let Some(position) = position {
+            state.values[position] = Some(value);
+        }
+        Ok(value)
     }
 
     /// Create a network from raw components.
@@ -141,18 +278,67 @@ impl SimplePolyNetwork {
     /// let input_layer = vec![input];
     /// let output_layer = vec![output];
     ///
-    /// let network = SimplePolyNetwork::from_raw_parts(neurons, input_layer, output_layer);
+    /// let network = SimplePolyNetwork::from_raw_parts(neurons, input_layer, output_layer)?;
+    /// # Ok::<(), PolyNeatError>(())
     /// ```
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned, or if a neuron of `input_layer` isn't part of
+    /// `neurons`.
     pub fn from_raw_parts(
         neurons: Vec<Arc<RwLock<SimpleNeuron>>>,
         input_layer: Vec<Arc<RwLock<SimpleNeuron>>>,
         output_layer: Vec<Arc<RwLock<SimpleNeuron>>>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        let mut positions: FnvHashMap<Uuid, usize> = FnvHashMap::default();
+        for (position, neuron) in neurons.iter().enumerate() {
+            positions.insert(neuron.read()?.id(), position);
+        }
+
+        let mut input_positions = Vec::with_capacity(input_layer.len());
+        for neuron in input_layer.iter() {
+            let id = neuron.read()?.id();
+            let Some(position) = positions.get(&id) else {
+                return Err(PolyNeatError::UnknownNeuron { id: id.to_string() });
+            };
+            input_positions.push(*position);
+        }
+
+        let mut disconnected_outputs = Vec::new();
+        for (output, neuron) in output_layer.iter().enumerate() {
+            if neuron
+                .read()?
+                .inputs()
+                .is_none_or(|inputs| inputs.is_empty())
+            {
+                disconnected_outputs.push(output);
+            }
+        }
+
+        Ok(Self {
             neurons,
             input_layer,
             output_layer,
-        }
+            positions,
+            input_positions,
+            disconnected_outputs,
+            numeric_policy: NumericPolicy::default(),
+            numeric_flag: Arc::default(),
+        })
+    }
+
+    /// Set how non-finite values are handled. See [`NumericPolicy`].
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Self {
+        self.numeric_policy = policy;
+        self
+    }
+
+    /// The flag raised when a [`NumericPolicy::Sentinel`] replaces an output.
+    ///
+    /// Networks built by [`from_topology`](Self::from_topology) share it with the
+    /// topology.
+    pub fn numeric_flag(&self) -> &Arc<NumericFlag> {
+        &self.numeric_flag
     }
 
     /// Generate a human-readable summary of the network's structure.
@@ -354,6 +540,9 @@ impl SimplePolyNetwork {
     /// # Returns
     /// A new `SimplePolyNetwork` ready for inference
     ///
+    /// # Errors
+    /// Fails if a neuron lock of the topology is poisoned.
+    ///
     /// # Example
     /// ```rust
     /// # use polynomial_neat::prelude::*;
@@ -362,36 +551,123 @@ impl SimplePolyNetwork {
     /// let topology = PolyNetworkTopology::new(3, 2, mutations, &mut rand::rng());
     ///
     /// // Convert to executable network
-    /// let network = SimplePolyNetwork::from_topology(&topology);
+    /// let network = SimplePolyNetwork::from_topology(&topology)?;
     ///
     /// // Now ready for inference
     /// let outputs: Vec<f32> = network.predict(&[1.0, 2.0, 3.0]).collect();
+    /// # Ok::<(), PolyNeatError>(())
     /// ```
-    pub fn from_topology(topology: &PolyNetworkTopology) -> Self {
+    pub fn from_topology(topology: &PolyNetworkTopology) -> Result<Self> {
         let mut neurons: Vec<Arc<RwLock<SimpleNeuron>>> =
             Vec::with_capacity(topology.neurons().len());
         let mut input_layer: Vec<Arc<RwLock<SimpleNeuron>>> = Vec::new();
         let mut output_layer: Vec<Arc<RwLock<SimpleNeuron>>> = Vec::new();
 
         for neuron_replicant in topology.neurons() {
-            let neuron = neuron_replicant.read().unwrap();
+            let neuron = neuron_replicant.read()?;
 
-            neuron.to_neuron(&mut neurons);
-            let neuron = neurons
-                .iter()
-                .find(|n| n.read().unwrap().id() == neuron.id())
-                .unwrap();
-
-            let neuron_read = neuron.read().unwrap();
+            let neuron = neuron.to_neuron(&mut neurons)?;
+            let neuron_read = neuron.read()?;
 
             if neuron_read.is_input() {
-                input_layer.push(Arc::clone(neuron));
+                input_layer.push(Arc::clone(&neuron));
             }
             if neuron_read.is_output() {
-                output_layer.push(Arc::clone(neuron));
+                output_layer.push(Arc::clone(&neuron));
+            }
+        }
+
+        let mut network = SimplePolyNetwork::from_raw_parts(neurons, input_layer, output_layer)?;
+        network.numeric_flag = Arc::clone(topology.numeric_flag());
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+    use rayon::iter::IntoParallelIterator;
+
+    fn evolved_network(seed: u64) -> SimplePolyNetwork {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut topology = PolyNetworkTopology::new(3, 2, MutationChances::new(70), &mut rng);
+        for _ in 0..15 {
+            topology = topology.replicate(&mut rng);
+        }
+        topology.to_simple_network()
+    }
+
+    fn samples() -> Vec<[f32; 3]> {
+        (0..64)
+            .map(|i| {
+                let i = i as f32;
+                [i * 0.1 - 3., (i * 0.37).sin(), 1. / (i + 1.)]
+            })
+            .collect()
+    }
+
+    fn same(a: &[f32], b: &[f32]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| a == b || (a.is_nan() && b.is_nan()))
+    }
+
+    #[test]
+    fn concurrent_predict_matches_sequential() {
+        let network = evolved_network(902);
+        let samples = samples();
+
+        let mut state = PredictState::default();
+        let sequential: Vec<Vec<f32>> = samples
+            .iter()
+            .map(|sample| network.predict_with(sample, &mut state))
+            .collect();
+
+        // many threads hammering the same network at once
+        for _ in 0..4 {
+            let concurrent: Vec<Vec<f32>> = (0..samples.len())
+                .into_par_iter()
+                .map(|i| network.predict(&samples[i]).collect())
+                .collect();
+            for (concurrent, sequential) in concurrent.iter().zip(sequential.iter()) {
+                assert!(same(concurrent, sequential));
             }
         }
 
-        SimplePolyNetwork::from_raw_parts(neurons, input_layer, output_layer)
+        let batched = network.predict_many(&samples);
+        for (batched, sequential) in batched.iter().zip(sequential.iter()) {
+            assert!(same(batched, sequential));
+        }
+    }
+
+    #[test]
+    fn state_is_reset_between_calls() {
+        let network = evolved_network(17);
+        let mut state = PredictState::default();
+
+        let first = network.predict_with(&[1., 2., 3.], &mut state);
+        network.predict_with(&[-4., 0.5, 9.], &mut state);
+        let again = network.predict_with(&[1., 2., 3.], &mut state);
+
+        assert!(same(&first, &again));
+    }
+
+    #[test]
+    fn poisoned_neurons_are_reported() {
+        let network = evolved_network(5);
+        let poisoned = Arc::clone(&network.output_layer[0]);
+        let _ = std::thread::spawn(move || {
+            let _lock = poisoned.write().unwrap();
+            panic!("poison an output neuron");
+        })
+        .join();
+
+        let validation = InputValidation::default().with_connected_outputs(false);
+        assert_eq!(
+            network.try_predict(&[1., 2., 3.], &validation),
+            Err(PolyNeatError::PoisonedLock)
+        );
     }
 }
@@ -38,7 +38,8 @@ fn _test_two() {
 
         let running_network = running_topology.to_simple_network();
         let device = burn::backend::ndarray::NdArrayDevice::default();
-        let burn_network = BurnNetwork::<NdArray>::from_topology(&running_topology, device);
+        let burn_network =
+            BurnNetwork::<NdArray>::from_topology(&running_topology, device).unwrap();
         println!("simple network made");
         let result = running_network.predict(&[1., 5.]).collect::<Vec<f32>>();
         let burn_result = burn_network.predict(&[1., 5.]);
@@ -0,0 +1,360 @@
+//! Graphviz DOT export for network topologies.
+//!
+//! [`PolyNetworkTopology::to_dot`] renders a topology as a `digraph` that can be piped
+//! straight into `dot -Tsvg`. Input, hidden and output neurons get distinct shapes and
+//! colours, and every edge is labelled with its weight and exponent.
+//!
+//! Two kinds of edges are styled differently from live connections:
+//!
+//! - **Disabled** edges have an exponent of 0. They contribute their weight as a
+//!   constant and ignore the value of their source, so they are drawn dotted.
+//! - **Dangling** edges point at a neuron that has been dropped from the network.
+//!   They are drawn dashed into a single placeholder node.
+//!
+//! # Example
+//!
+//! ```
+//! use polynomial_neat::prelude::*;
+//! use polynomial_neat::topology::dot::DotOptions;
+//!
+//! let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+//!
+//! let dot = topology.to_dot(&DotOptions::default().with_layers(true));
+//! assert!(dot.starts_with("digraph"));
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::{Arc, RwLock},
+};
+
+use uuid::Uuid;
+
+use crate::prelude::*;
+
+const DROPPED_NODE: &str = "dropped";
+
+/// Rendering options for [`PolyNetworkTopology::to_dot`].
+#[derive(Clone, Debug)]
+pub struct DotOptions {
+    graph_name: String,
+    layers: bool,
+    show_ids: bool,
+    precision: usize,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self {
+            graph_name: "network".to_string(),
+            layers: false,
+            show_ids: false,
+            precision: 3,
+        }
+    }
+}
+
+impl DotOptions {
+    /// Set the name of the emitted `digraph`.
+    pub fn with_graph_name(mut self, name: impl Into<String>) -> Self {
+        self.graph_name = name.into();
+        self
+    }
+
+    /// Group neurons into ranks by their depth in the network.
+    ///
+    /// See [`PolyNetworkTopology::depths`] for how depth is defined.
+    pub fn with_layers(mut self, layers: bool) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Append the short neuron id to every node label.
+    pub fn with_ids(mut self, show_ids: bool) -> Self {
+        self.show_ids = show_ids;
+        self
+    }
+
+    /// Number of decimal places used for edge weights.
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+}
+
+impl PolyNetworkTopology {
+    /// Render this topology as a Graphviz DOT graph.
+    ///
+    /// Nodes are named `i{n}`, `h{n}` and `o{n}` where `n` is the neuron's index among
+    /// the inputs, hidden neurons and outputs respectively. Input indices match the
+    /// order in which values are passed to `predict`.
+    ///
+    /// # Arguments
+    /// * `options` - Rendering options
+    ///
+    /// # Returns
+    /// The DOT source as a string
+    pub fn to_dot(&self, options: &DotOptions) -> String {
+        let mut names: HashMap<Uuid, String> = HashMap::with_capacity(self.neurons().len());
+        let mut detached: Vec<Arc<RwLock<PolyNeuronTopology>>> = Vec::new();
+        let mut counts = [0usize; 3];
+
+        fn name_neuron(
+            neuron: &PolyNeuronTopology,
+            counts: &mut [usize; 3],
+            names: &mut HashMap<Uuid, String>,
+        ) {
+            let (prefix, count) = match neuron.neuron_type() {
+                NeuronType::Input => ("i", &mut counts[0]),
+                NeuronType::Props(PropsType::Hidden) => ("h", &mut counts[1]),
+                NeuronType::Props(PropsType::Output) => ("o", &mut counts[2]),
+            };
+            names.insert(neuron.id(), format!("{prefix}{count}"));
+            *count += 1;
+        }
+
+        for neuron in self.neurons() {
+            name_neuron(&neuron.read().unwrap(), &mut counts, &mut names);
+        }
+
+        // neurons that are still referenced by a live input but are no longer part of the
+        // network. Evaluators still follow these, so they are drawn too.
+        let mut index = 0;
+        let mut queue = self.neurons().clone();
+        while index < queue.len() {
+            let neuron = Arc::clone(&queue[index]);
+            index += 1;
+            let neuron = neuron.read().unwrap();
+            let Some(props) = neuron.props() else {
+                continue;
+            };
+            for input in props.inputs() {
+                let Some(input_neuron) = input.neuron() else {
+                    continue;
+                };
+                let id = input_neuron.read().unwrap().id();
+                if !names.contains_key(&id) {
+                    name_neuron(&input_neuron.read().unwrap(), &mut counts, &mut names);
+                    detached.push(Arc::clone(&input_neuron));
+                    queue.push(input_neuron);
+                }
+            }
+        }
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph \"{}\" {{", escape(&options.graph_name)).unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [fontname=\"Helvetica\"];").unwrap();
+        writeln!(dot, "    edge [fontname=\"Helvetica\", fontsize=10];").unwrap();
+
+        let all_neurons = self.neurons().iter().chain(detached.iter());
+        let mut has_dropped = false;
+
+        for neuron in all_neurons.clone() {
+            let neuron = neuron.read().unwrap();
+            let name = &names[&neuron.id()];
+            let label = if options.show_ids {
+                format!("{}\\n{}", escape(name), escape(&neuron.id_short()))
+            } else {
+                escape(name)
+            };
+            let style = match neuron.neuron_type() {
+                NeuronType::Input => "shape=box, style=filled, fillcolor=\"#a6cee3\"",
+                NeuronType::Props(PropsType::Hidden) => {
+                    "shape=circle, style=filled, fillcolor=\"#f0f0f0\""
+                }
+                NeuronType::Props(PropsType::Output) => {
+                    "shape=doublecircle, style=filled, fillcolor=\"#fdbf6f\""
+                }
+            };
+            writeln!(dot, "    {name} [label=\"{label}\", {style}];").unwrap();
+        }
+
+        if options.layers {
+            // a poisoned lock leaves every neuron on the first layer
+            let depths = self.try_depths().unwrap_or_default();
+            let mut layers: Vec<Vec<&str>> = Vec::new();
+            for neuron in all_neurons.clone() {
+                let id = neuron.read().unwrap().id();
+                let depth = depths.get(&id).copied().unwrap_or_default();
+                if layers.len() <= depth {
+                    layers.resize_with(depth + 1, Vec::new);
+                }
+                layers[depth].push(&names[&id]);
+            }
+            for (depth, layer) in layers.iter().enumerate() {
+                if layer.is_empty() {
+                    continue;
+                }
+                writeln!(dot, "    subgraph layer_{depth} {{").unwrap();
+                writeln!(dot, "        rank=same;").unwrap();
+                for name in layer {
+                    writeln!(dot, "        {name};").unwrap();
+                }
+                writeln!(dot, "    }}").unwrap();
+            }
+        }
+
+        for neuron in all_neurons {
+            let neuron = neuron.read().unwrap();
+            let Some(props) = neuron.props() else {
+                continue;
+            };
+            let target = &names[&neuron.id()];
+            for input in props.inputs() {
+                let label = escape(&format!(
+                    "{:.*} ^{}",
+                    options.precision,
+                    input.weight(),
+                    input.exponent()
+                ));
+                match input.neuron() {
+                    Some(source) => {
+                        let source = &names[&source.read().unwrap().id()];
+                        let style = if input.exponent() == 0 {
+                            ", style=dotted, color=gray50, fontcolor=gray50"
+                        } else {
+                            ""
+                        };
+                        writeln!(dot, "    {source} -> {target} [label=\"{label}\"{style}];")
+                            .unwrap();
+                    }
+                    None => {
+                        has_dropped = true;
+                        writeln!(
+                            dot,
+                            "    {DROPPED_NODE} -> {target} [label=\"{label}\", style=dashed, color=red, fontcolor=red];"
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+
+        if has_dropped {
+            writeln!(
+                dot,
+                "    {DROPPED_NODE} [label=\"dropped\", shape=point, color=red];"
+            )
+            .unwrap();
+        }
+
+        dot.push('}');
+        dot.push('\n');
+        dot
+    }
+}
+
+/// `text` escaped for use inside a double-quoted DOT string, with line breaks kept as
+/// DOT's own `\n`.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topology_with_hidden() -> (PolyNetworkTopology, Arc<RwLock<PolyNeuronTopology>>) {
+        let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+        let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+
+        let hidden = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&x, 3., 1),
+                PolyInputTopology::downgrade(&y, 1., 0),
+            ],
+        ));
+
+        let output = arc(PolyNeuronTopology::output(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&hidden, 0.5, 2),
+                PolyInputTopology::downgrade(&x, -1., 1),
+            ],
+        ));
+
+        (
+            PolyNetworkTopology::from_raw_parts(
+                vec![x, y, Arc::clone(&hidden), output],
+                MutationChances::none(),
+            ),
+            hidden,
+        )
+    }
+
+    #[test]
+    fn dot_contains_all_nodes_and_edges() {
+        let (topology, _hidden) = topology_with_hidden();
+        let dot = topology.to_dot(&DotOptions::default());
+
+        assert!(dot.starts_with("digraph \"network\" {"));
+        assert!(dot.trim_end().ends_with('}'));
+        for node in ["i0 [", "i1 [", "h0 [", "o0 ["] {
+            assert!(dot.contains(node), "missing node {node}:\n{dot}");
+        }
+        assert!(dot.contains("i0 -> h0 [label=\"3.000 ^1\"];"));
+        assert!(dot.contains("h0 -> o0 [label=\"0.500 ^2\"];"));
+        assert!(dot.contains("i0 -> o0 [label=\"-1.000 ^1\"];"));
+        // exponent 0 ignores its source
+        assert!(dot.contains("i1 -> h0 [label=\"1.000 ^0\", style=dotted"));
+        assert!(!dot.contains(DROPPED_NODE));
+    }
+
+    #[test]
+    fn dot_escapes_the_graph_name() {
+        let (topology, _hidden) = topology_with_hidden();
+        let options = DotOptions::default().with_graph_name(r#"say "hi" \ bye"#);
+        let dot = topology.to_dot(&options);
+
+        assert!(dot.starts_with(r#"digraph "say \"hi\" \\ bye" {"#), "{dot}");
+
+        let dot = topology.to_dot(&DotOptions::default().with_graph_name("two\nlines"));
+        assert!(dot.starts_with(r#"digraph "two\nlines" {"#), "{dot}");
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(escape(r#"a "b" \ c"#), r#"a \"b\" \\ c"#);
+        assert_eq!(escape("one\ntwo"), r"one\ntwo");
+
+        let (topology, _hidden) = topology_with_hidden();
+        let dot = topology.to_dot(&DotOptions::default().with_ids(true));
+        assert!(dot.contains("h0 [label=\"h0\\n"), "{dot}");
+    }
+
+    #[test]
+    fn dot_marks_dangling_edges() {
+        let (mut topology, hidden) = topology_with_hidden();
+        let hidden_id = hidden.read().unwrap().id();
+        drop(hidden);
+        let index = topology
+            .neuron_ids()
+            .iter()
+            .position(|id| *id == hidden_id)
+            .unwrap();
+        let mut neurons = topology.neurons().clone();
+        neurons.remove(index);
+        topology = PolyNetworkTopology::from_raw_parts(neurons, MutationChances::none());
+
+        let dot = topology.to_dot(&DotOptions::default());
+        assert!(dot.contains("dropped -> o0 [label=\"0.500 ^2\", style=dashed"));
+        assert!(dot.contains("dropped [label=\"dropped\""));
+    }
+
+    #[test]
+    fn dot_groups_layers_by_depth() {
+        let (topology, _hidden) = topology_with_hidden();
+        let dot = topology.to_dot(&DotOptions::default().with_layers(true));
+
+        assert!(
+            dot.contains("subgraph layer_0 {\n        rank=same;\n        i0;\n        i1;\n    }")
+        );
+        assert!(dot.contains("subgraph layer_1 {\n        rank=same;\n        h0;\n    }"));
+        assert!(dot.contains("subgraph layer_2 {\n        rank=same;\n        o0;\n    }"));
+    }
+}
@@ -0,0 +1,279 @@
+//! A plain, serializable form of a topology.
+//!
+//! A [`PolyNetworkTopology`] links its neurons through `Arc`/`Weak` pointers, which can't
+//! be written to disk as they are. [`GenomeData`] lists the same neurons by id instead,
+//! with every connection naming its source neuron, so a genome can be saved as JSON and
+//! loaded back into an identical network.
+//!
+//! Neuron order is kept, so inputs and outputs keep their positions. Connections whose
+//! source has been dropped from the network are left out, as every evaluator ignores them
+//! anyway.
+//!
+//! # Example
+//!
+//! ```rust
+//! use polynomial_neat::prelude::*;
+//!
+//! let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+//!
+//! let json = topology.to_genome()?.to_json();
+//! let loaded = PolyNetworkTopology::from_genome(&GenomeData::from_json(&json)?)?;
+//!
+//! assert_eq!(loaded.neuron_ids(), topology.neuron_ids());
+//! # Ok::<(), PolyNeatError>(())
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    error::{PolyNeatError, Result},
+    prelude::*,
+};
+
+/// A topology with its connections spelled out by neuron id.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenomeData {
+    pub mutation_chances: MutationChances,
+    pub neurons: Vec<NeuronData>,
+}
+
+/// One neuron of a [`GenomeData`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NeuronData {
+    pub id: Uuid,
+    pub kind: NeuronKind,
+    /// Always empty for input neurons.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<ConnectionData>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NeuronKind {
+    Input,
+    Hidden,
+    Output,
+}
+
+/// A connection into a neuron, contributing `weight * source^exponent`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionData {
+    pub source: Uuid,
+    pub weight: f32,
+    pub exponent: i32,
+}
+
+impl GenomeData {
+    /// Pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("genome data always serializes")
+    }
+
+    /// # Errors
+    /// Fails with [`PolyNeatError::Parse`] if `json` isn't a genome.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Whether both genomes have the same neurons in the same order, wired the same way
+    /// with the same weights and exponents. Neuron ids and mutation chances are ignored,
+    /// so an unmutated [`replicate`](PolyNetworkTopology::replicate) has the same
+    /// structure as its parent.
+    pub fn same_structure(&self, other: &GenomeData) -> bool {
+        fn positions(genome: &GenomeData) -> HashMap<Uuid, usize> {
+            genome
+                .neurons
+                .iter()
+                .enumerate()
+                .map(|(position, neuron)| (neuron.id, position))
+                .collect()
+        }
+
+        if self.neurons.len() != other.neurons.len() {
+            return false;
+        }
+        let (ours, theirs) = (positions(self), positions(other));
+        self.neurons.iter().zip(other.neurons.iter()).all(|(a, b)| {
+            a.kind == b.kind
+                && a.inputs.len() == b.inputs.len()
+                && a.inputs.iter().zip(b.inputs.iter()).all(|(a, b)| {
+                    a.weight.to_bits() == b.weight.to_bits()
+                        && a.exponent == b.exponent
+                        && ours.get(&a.source) == theirs.get(&b.source)
+                })
+        })
+    }
+}
+
+impl PolyNetworkTopology {
+    /// Describe this network by neuron id. See the [module docs](crate::topology::genome).
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn to_genome(&self) -> Result<GenomeData> {
+        let mut neurons = Vec::with_capacity(self.neurons().len());
+        for neuron in self.neurons() {
+            let neuron = neuron.read()?;
+            let kind = match neuron.neuron_type() {
+                NeuronType::Input => NeuronKind::Input,
+                NeuronType::Props(PropsType::Hidden) => NeuronKind::Hidden,
+                NeuronType::Props(PropsType::Output) => NeuronKind::Output,
+            };
+
+            let mut inputs = Vec::new();
+            for input in neuron
+                .props()
+                .map(|props| props.inputs())
+                .unwrap_or_default()
+            {
+                let Some(source) = input.neuron() else {
+                    continue;
+                };
+                inputs.push(ConnectionData {
+                    source: source.read()?.id(),
+                    weight: input.weight(),
+                    exponent: input.exponent(),
+                });
+            }
+
+            neurons.push(NeuronData {
+                id: neuron.id(),
+                kind,
+                inputs,
+            });
+        }
+
+        Ok(GenomeData {
+            mutation_chances: *self.mutation_chances(),
+            neurons,
+        })
+    }
+
+    /// Rebuild a network from its [`GenomeData`], keeping every neuron id.
+    ///
+    /// # Errors
+    /// Fails with [`PolyNeatError::UnknownNeuron`] if a connection names a neuron that
+    /// isn't listed, and with [`PolyNeatError::Parse`] if an id is listed twice or an
+    /// input neuron has inputs.
+    pub fn from_genome(genome: &GenomeData) -> Result<Self> {
+        let mut by_id = HashMap::with_capacity(genome.neurons.len());
+        let mut neurons = Vec::with_capacity(genome.neurons.len());
+
+        for data in genome.neurons.iter() {
+            let neuron = match data.kind {
+                NeuronKind::Input if !data.inputs.is_empty() => {
+                    return Err(PolyNeatError::parse(format!(
+                        "input neuron {} has inputs",
+                        data.id
+                    )));
+                }
+                NeuronKind::Input => PolyNeuronTopology::input(data.id),
+                NeuronKind::Hidden => PolyNeuronTopology::hidden(data.id, Vec::new()),
+                NeuronKind::Output => PolyNeuronTopology::output(data.id, Vec::new()),
+            };
+            let neuron = Arc::new(RwLock::new(neuron));
+            if by_id.insert(data.id, Arc::clone(&neuron)).is_some() {
+                return Err(PolyNeatError::parse(format!(
+                    "neuron {} is listed twice",
+                    data.id
+                )));
+            }
+            neurons.push(neuron);
+        }
+
+        for (data, neuron) in genome.neurons.iter().zip(neurons.iter()) {
+            let mut neuron = neuron.write()?;
+            let Some(props) = neuron.props_mut() else {
+                continue;
+            };
+            for connection in data.inputs.iter() {
+                let source =
+                    by_id
+                        .get(&connection.source)
+                        .ok_or_else(|| PolyNeatError::UnknownNeuron {
+                            id: connection.source.to_string(),
+                        })?;
+                props.add_input(PolyInputTopology::downgrade(
+                    source,
+                    connection.weight,
+                    connection.exponent,
+                ));
+            }
+        }
+
+        Ok(Self::from_raw_parts(neurons, genome.mutation_chances))
+    }
+
+    /// Write this network to `path` as JSON. See [`to_genome`](Self::to_genome).
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned or the file can't be written.
+    pub fn save_json(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_genome()?.to_json()).map_err(|e| PolyNeatError::io(path, e))
+    }
+
+    /// Read a network written by [`save_json`](Self::save_json).
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or doesn't hold a valid genome.
+    pub fn load_json(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| PolyNeatError::io(path, e))?;
+        Self::from_genome(&GenomeData::from_json(&json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replicas_share_their_structure() {
+        let mut rng = rand::rng();
+        let parent =
+            PolyNetworkTopology::new_thoroughly_connected(3, 2, MutationChances::none(), &mut rng);
+        let child = parent.replicate(&mut rng);
+
+        let (parent, child) = (parent.to_genome().unwrap(), child.to_genome().unwrap());
+        assert_ne!(parent, child);
+        assert!(parent.same_structure(&child));
+
+        let mut heavier = child.clone();
+        heavier.neurons.last_mut().unwrap().inputs[0].weight += 1.;
+        assert!(!parent.same_structure(&heavier));
+    }
+
+    #[test]
+    fn rejects_unknown_sources() {
+        let output = Uuid::new_v4();
+        let missing = Uuid::new_v4();
+        let genome = GenomeData {
+            mutation_chances: MutationChances::none(),
+            neurons: vec![NeuronData {
+                id: output,
+                kind: NeuronKind::Output,
+                inputs: vec![ConnectionData {
+                    source: missing,
+                    weight: 1.,
+                    exponent: 1,
+                }],
+            }],
+        };
+
+        assert_eq!(
+            PolyNetworkTopology::from_genome(&genome).err(),
+            Some(PolyNeatError::UnknownNeuron {
+                id: missing.to_string()
+            })
+        );
+    }
+}
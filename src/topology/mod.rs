@@ -1,3 +1,5 @@
+pub mod dot;
+pub mod genome;
 pub mod input;
 pub mod mutation;
 pub mod network;
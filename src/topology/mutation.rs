@@ -1,4 +1,7 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Result, prelude::*};
 
 /// Represents the different types of mutations that can occur during network evolution.
 ///
@@ -109,7 +112,7 @@ pub const MAX_MUTATIONS: u8 = 200;
 ///     20.0    // low chance to mutate exponents
 /// );
 /// ```
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MutationChances {
     /// Probability (0-100) of performing any mutation at all
     self_mutation: u8,
@@ -434,6 +437,100 @@ impl MutationChances {
     }
 }
 
+/// Limits on the weights and exponents mutation may produce.
+///
+/// Mutation nudges weights and exponents without bound. Applying bounds to an offspring
+/// clamps every connection back into range, which keeps exponents from drifting into
+/// expansions that can't be evaluated.
+///
+/// # Example
+///
+/// ```rust
+/// use polynomial_neat::prelude::*;
+/// use polynomial_neat::topology::mutation::MutationBounds;
+///
+/// let bounds = MutationBounds::default()
+///     .with_max_weight(Some(2.))
+///     .with_exponents(Some(0), Some(3));
+///
+/// let topology = PolyNetworkTopology::new(2, 1, MutationChances::new(50), &mut rand::rng());
+/// topology.apply_bounds(&bounds)?;
+/// # Ok::<(), PolyNeatError>(())
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MutationBounds {
+    max_weight: Option<f32>,
+    min_exponent: Option<i32>,
+    max_exponent: Option<i32>,
+}
+
+impl MutationBounds {
+    /// Keep every weight within `[-max_weight, max_weight]`.
+    pub fn with_max_weight(mut self, max_weight: Option<f32>) -> Self {
+        self.max_weight = max_weight;
+        self
+    }
+
+    /// Keep every exponent within `[min_exponent, max_exponent]`.
+    pub fn with_exponents(mut self, min_exponent: Option<i32>, max_exponent: Option<i32>) -> Self {
+        self.min_exponent = min_exponent;
+        self.max_exponent = max_exponent;
+        self
+    }
+
+    pub fn max_weight(&self) -> Option<f32> {
+        self.max_weight
+    }
+
+    pub fn min_exponent(&self) -> Option<i32> {
+        self.min_exponent
+    }
+
+    pub fn max_exponent(&self) -> Option<i32> {
+        self.max_exponent
+    }
+
+    /// Whether no limit is set.
+    pub fn is_unbounded(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn clamp_weight(&self, weight: f32) -> f32 {
+        match self.max_weight {
+            Some(max) => weight.clamp(-max, max),
+            None => weight,
+        }
+    }
+
+    fn clamp_exponent(&self, exponent: i32) -> i32 {
+        let exponent = self.min_exponent.map_or(exponent, |min| exponent.max(min));
+        self.max_exponent.map_or(exponent, |max| exponent.min(max))
+    }
+}
+
+impl PolyNetworkTopology {
+    /// Clamp the weight and exponent of every connection into `bounds`.
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn apply_bounds(&self, bounds: &MutationBounds) -> Result<()> {
+        if bounds.is_unbounded() {
+            return Ok(());
+        }
+        for neuron in self.neurons() {
+            let mut neuron = neuron.write()?;
+            let Some(props) = neuron.props_mut() else {
+                continue;
+            };
+            for input in props.inputs_mut() {
+                input.set_weight(bounds.clamp_weight(input.weight()));
+                input.adjust_exp(bounds.clamp_exponent(input.exponent()) - input.exponent());
+            }
+        }
+        Ok(())
+    }
+}
+
 #[test]
 pub fn adjust_mutation_chances() {
     let mut chances = MutationChances::new(50);
@@ -1,12 +1,12 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 
 use rand::Rng;
 use uuid::Uuid;
 
-use crate::prelude::*;
+use crate::{core::numeric::NumericFlag, error::Result, prelude::*};
 
 #[derive(Clone, Debug)]
 /// Represents the topology (structure) of a polynomial neural network.
@@ -38,6 +38,8 @@ use crate::prelude::*;
 pub struct PolyNetworkTopology {
     neurons: Vec<Arc<RwLock<PolyNeuronTopology>>>,
     mutation_chances: MutationChances,
+    // raised by evaluators built from this topology, see NumericPolicy::Sentinel
+    numeric_flag: Arc<NumericFlag>,
 }
 
 impl PolyNetworkTopology {
@@ -56,6 +58,7 @@ impl PolyNetworkTopology {
         Self {
             neurons,
             mutation_chances,
+            numeric_flag: Arc::default(),
         }
     }
 
@@ -105,6 +108,7 @@ impl PolyNetworkTopology {
         Self {
             neurons,
             mutation_chances,
+            numeric_flag: Arc::default(),
         }
     }
 
@@ -139,6 +143,7 @@ impl PolyNetworkTopology {
         Self {
             neurons,
             mutation_chances,
+            numeric_flag: Arc::default(),
         }
     }
 
@@ -161,10 +166,29 @@ impl PolyNetworkTopology {
         &self.neurons
     }
 
+    /// Count the neurons of each kind and the live connections between them.
+    ///
+    /// # Panics
+    /// Panics if a neuron lock is poisoned. See [`try_info`](Self::try_info).
     pub fn info(&self) -> TopologyInfo {
+        self.try_info().expect("failed to count neurons")
+    }
+
+    /// Count the neurons of each kind and the live connections between them.
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn try_info(&self) -> Result<TopologyInfo> {
         let mut info = TopologyInfo::default();
         for neuron in self.neurons.iter() {
-            let read_lock = neuron.read().unwrap();
+            let read_lock = neuron.read()?;
+            if let Some(props) = read_lock.props() {
+                info.num_connections += props
+                    .inputs()
+                    .iter()
+                    .filter(|input| input.neuron().is_some())
+                    .count();
+            }
             match read_lock.neuron_type() {
                 NeuronType::Input => {
                     info.num_inputs += 1;
@@ -177,7 +201,129 @@ impl PolyNetworkTopology {
                 }
             }
         }
-        info
+        Ok(info)
+    }
+
+    /// Compute the depth of every neuron reachable from this topology.
+    ///
+    /// Input neurons sit at depth 0. Every other neuron sits one level below the
+    /// deepest of its live inputs, so a neuron with no live inputs has depth 1.
+    /// Dropped (dangling) inputs are ignored.
+    ///
+    /// # Returns
+    /// A map from neuron id to its depth
+    ///
+    /// # Panics
+    /// Panics if a neuron lock is poisoned. See [`try_depths`](Self::try_depths).
+    pub fn depths(&self) -> HashMap<Uuid, usize> {
+        self.try_depths().expect("failed to compute depths")
+    }
+
+    /// Compute the depth of every neuron reachable from this topology.
+    ///
+    /// See [`depths`](Self::depths).
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn try_depths(&self) -> Result<HashMap<Uuid, usize>> {
+        fn visit(
+            neuron: &PolyNeuronTopology,
+            depths: &mut HashMap<Uuid, usize>,
+            visiting: &mut HashSet<Uuid>,
+        ) -> Result<usize> {
+            if let Some(depth) = depths.get(&neuron.id()) {
+                return Ok(*depth);
+            }
+            let Some(props) = neuron.props() else {
+                depths.insert(neuron.id(), 0);
+                return Ok(0);
+            };
+
+            // a cycle should never survive `remove_cycles`, but don't recurse forever if it does.
+            visiting.insert(neuron.id());
+            let mut deepest_input = 0;
+            for input in props.inputs() {
+                let Some(input_neuron) = input.neuron() else {
+                    continue;
+                };
+                let input_neuron = input_neuron.read()?;
+                if visiting.contains(&input_neuron.id()) {
+                    continue;
+                }
+                deepest_input = deepest_input.max(visit(&input_neuron, depths, visiting)?);
+            }
+            visiting.remove(&neuron.id());
+
+            depths.insert(neuron.id(), deepest_input + 1);
+            Ok(deepest_input + 1)
+        }
+
+        let mut depths = HashMap::with_capacity(self.neurons.len());
+        let mut visiting = HashSet::new();
+        for neuron in self.neurons.iter() {
+            visit(&*neuron.read()?, &mut depths, &mut visiting)?;
+        }
+        Ok(depths)
+    }
+
+    /// Order every reachable neuron so that each one comes after all of its live inputs.
+    ///
+    /// Neurons keep their relative order from [`neurons`](Self::neurons) wherever the
+    /// dependencies allow it. Neurons that are no longer part of the network but are
+    /// still referenced by a live input are included, since evaluators follow them too.
+    ///
+    /// # Returns
+    /// All reachable neurons in topological order
+    ///
+    /// # Panics
+    /// Panics if a neuron lock is poisoned. See
+    /// [`try_topological_order`](Self::try_topological_order).
+    pub fn topological_order(&self) -> Vec<Arc<RwLock<PolyNeuronTopology>>> {
+        self.try_topological_order()
+            .expect("failed to order neurons")
+    }
+
+    /// Order every reachable neuron so that each one comes after all of its live inputs.
+    ///
+    /// See [`topological_order`](Self::topological_order).
+    ///
+    /// # Errors
+    /// Fails if a neuron lock is poisoned.
+    pub fn try_topological_order(&self) -> Result<Vec<Arc<RwLock<PolyNeuronTopology>>>> {
+        fn visit(
+            neuron: &Arc<RwLock<PolyNeuronTopology>>,
+            order: &mut Vec<Arc<RwLock<PolyNeuronTopology>>>,
+            visited: &mut HashSet<Uuid>,
+        ) -> Result<()> {
+            let inputs = {
+                let read = neuron.read()?;
+                if !visited.insert(read.id()) {
+                    return Ok(());
+                }
+                read.props()
+                    .map(|props| {
+                        props
+                            .inputs()
+                            .iter()
+                            .filter_map(|input| input.neuron())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default()
+            };
+
+            for input in inputs.iter() {
+                visit(input, order, visited)?;
+            }
+            order.push(Arc::clone(neuron));
+            Ok(())
+        }
+
+        let mut order = Vec::with_capacity(self.neurons.len());
+        let mut visited = HashSet::with_capacity(self.neurons.len());
+        for neuron in self.neurons.iter() {
+            visit(neuron, &mut order, &mut visited)?;
+        }
+        Ok(order)
     }
 
     /// Get the mutation configuration for this network.
@@ -188,6 +334,15 @@ impl PolyNetworkTopology {
         &self.mutation_chances
     }
 
+    /// The flag evaluators built from this topology raise when a
+    /// [`NumericPolicy::Sentinel`](crate::core::numeric::NumericPolicy::Sentinel) replaces
+    /// one of its outputs.
+    ///
+    /// Offspring start with a lowered flag.
+    pub fn numeric_flag(&self) -> &Arc<NumericFlag> {
+        &self.numeric_flag
+    }
+
     /// Find a neuron by its unique identifier.
     ///
     /// # Arguments
@@ -202,6 +357,15 @@ impl PolyNetworkTopology {
             .cloned()
     }
 
+    fn try_find_by_id(&self, id: Uuid) -> Result<Option<Arc<RwLock<PolyNeuronTopology>>>> {
+        for neuron in self.neurons.iter() {
+            if neuron.read()?.id() == id {
+                return Ok(Some(Arc::clone(neuron)));
+            }
+        }
+        Ok(None)
+    }
+
     /// Select a random neuron from the network.
     ///
     /// # Arguments
@@ -209,10 +373,11 @@ impl PolyNetworkTopology {
     ///
     /// # Returns
     /// A randomly selected neuron
+    ///
+    /// # Panics
+    /// Panics if the network has no neurons.
     pub fn random_neuron(&self, rng: &mut impl Rng) -> &Arc<RwLock<PolyNeuronTopology>> {
-        self.neurons
-            .get(rng.random_range(0..self.neurons.len()))
-            .unwrap()
+        &self.neurons[rng.random_range(0..self.neurons.len())]
     }
 
     /// Remove a random non-input, non-output neuron from the network.
@@ -224,7 +389,10 @@ impl PolyNetworkTopology {
     ///
     /// # Arguments
     /// * `rng` - Random number generator for selection
-    pub fn remove_random_neuron(&mut self, rng: &mut impl Rng) {
+    ///
+    /// # Errors
+    /// Fails if the lock of the selected neuron is poisoned.
+    pub fn remove_random_neuron(&mut self, rng: &mut impl Rng) -> Result<()> {
         if self.neurons.len() > 1 {
             let index = rng.random_range(0..self.neurons.len());
 
@@ -232,9 +400,9 @@ impl PolyNetworkTopology {
             {
                 // this grabs any inputs to the random index and pushes if the input is an input neuron
                 // confusing, but input neurons and hidden neurons can both be inputs.
-                let neuron_props = self.neurons.get(index).unwrap().read().unwrap();
+                let neuron_props = self.neurons[index].read()?;
                 if neuron_props.is_input() || neuron_props.is_output() {
-                    return;
+                    return Ok(());
                 }
                 // let Some(props) = neuron_props.props() else {
                 //     return;
@@ -292,6 +460,7 @@ impl PolyNetworkTopology {
 
             self.neurons.remove(index);
         }
+        Ok(())
     }
 
     /// Add a new neuron to the network.
@@ -302,25 +471,39 @@ impl PolyNetworkTopology {
         self.neurons.push(neuron);
     }
 
+    /// Clone every neuron of this network, giving each a fresh id.
+    ///
+    /// # Panics
+    /// Panics if a neuron lock is poisoned. See [`try_deep_clone`](Self::try_deep_clone).
     pub fn deep_clone(&self) -> PolyNetworkTopology {
+        self.try_deep_clone()
+            .expect("failed to deep clone topology")
+    }
+
+    /// Clone every neuron of this network, giving each a fresh id.
+    ///
+    /// # Errors
+    /// Fails if a neuron lock of this network is poisoned.
+    #[allow(clippy::collapsible_if)]
+    pub fn try_deep_clone(&self) -> Result<PolyNetworkTopology> {
         let mut new_neurons: Vec<Arc<RwLock<PolyNeuronTopology>>> =
             Vec::with_capacity(self.neurons.len());
 
         // the deep cloning step removes all original inputs for all nodes
         // this needs to happen in its own iteration before updating the nodes for the deep clones
         for neuron in self.neurons.iter() {
-            let cloned_neuron = neuron.read().unwrap().deep_clone();
+            let cloned_neuron = neuron.read()?.deep_clone();
 
             new_neurons.push(Arc::new(RwLock::new(cloned_neuron)));
         }
 
         // deep clone the input nodes for the new inputs here
         for (original_neuron, new_neuron) in self.neurons.iter().zip(new_neurons.iter()) {
-            let original_neuron = original_neuron.read().unwrap();
+            let original_neuron = original_neuron.read()?;
 
             let Some(og_props) = original_neuron.props() else {
                 assert!(original_neuron.is_input());
-                assert!(new_neuron.read().unwrap().is_input());
+                assert!(new_neuron.read()?.is_input());
                 continue;
             };
 
@@ -348,7 +531,7 @@ impl PolyNetworkTopology {
             }
 
             // inputs should be fully cloned at this point
-            match new_neuron.write().unwrap().props_mut() {
+            match new_neuron.write()?.props_mut() {
                 Some(props_mut) => props_mut.set_inputs(cloned_inputs),
                 None => {
                     unreachable!("this check should be invalid due to the check on the input type")
@@ -356,24 +539,41 @@ impl PolyNetworkTopology {
             }
         }
 
-        PolyNetworkTopology {
+        Ok(PolyNetworkTopology {
             neurons: new_neurons,
             mutation_chances: self.mutation_chances,
-        }
+            numeric_flag: Arc::default(),
+        })
     }
 
     //#[instrument(skip_all)]
+    /// Create a mutated offspring of this network.
+    ///
+    /// # Panics
+    /// Panics if a neuron lock is poisoned. See [`try_replicate`](Self::try_replicate).
     pub fn replicate(&self, rng: &mut impl Rng) -> PolyNetworkTopology {
-        let mut child = self.deep_clone();
+        self.try_replicate(rng)
+            .expect("failed to replicate topology")
+    }
+
+    /// Create a mutated offspring of this network.
+    ///
+    /// Only the parent is read under its locks; the offspring is private to this call
+    /// until it is returned.
+    ///
+    /// # Errors
+    /// Fails if a neuron lock of this network is poisoned.
+    pub fn try_replicate(&self, rng: &mut impl Rng) -> Result<PolyNetworkTopology> {
+        let mut child = self.try_deep_clone()?;
 
         let actions = self.mutation_chances.gen_mutation_actions(rng);
-        child.mutate(actions.as_slice(), rng);
+        child.mutate(actions.as_slice(), rng)?;
 
         child.mutation_chances.adjust_mutation_chances(rng);
 
-        child.remove_cycles();
+        child.remove_cycles()?;
 
-        child
+        Ok(child)
     }
 
     #[cfg(test)]
@@ -430,7 +630,12 @@ impl PolyNetworkTopology {
         str
     }
 
-    pub fn mutate(&mut self, actions: &[MutationAction], rng: &mut impl Rng) {
+    /// Apply `actions` to this network in order.
+    ///
+    /// # Errors
+    /// Fails if the lock of a neuron an action touches is poisoned. The actions before it
+    /// have already been applied.
+    pub fn mutate(&mut self, actions: &[MutationAction], rng: &mut impl Rng) -> Result<()> {
         use MutationAction::*;
 
         for action in actions {
@@ -438,7 +643,7 @@ impl PolyNetworkTopology {
                 SplitConnection => {
                     // clone the arc to borrow later
                     let neuron_to_split = Arc::clone(self.random_neuron(rng));
-                    let removed_input = match neuron_to_split.write().unwrap().props_mut() {
+                    let removed_input = match neuron_to_split.write()?.props_mut() {
                         Some(props) => props.remove_random_input(rng),
                         None => None,
                     };
@@ -462,7 +667,7 @@ impl PolyNetworkTopology {
                         Exponent::rand(rng),
                     );
 
-                    let mut neuron_to_split = neuron_to_split.write().unwrap();
+                    let mut neuron_to_split = neuron_to_split.write()?;
 
                     //If the arc is removed from the array at this point, it will disappear, and the weak reference will
                     //ultimately be removed.
@@ -476,11 +681,11 @@ impl PolyNetworkTopology {
                     let input_neuron = self.random_neuron(rng);
 
                     //the input neuron cannot be an output and the output cannot be an input.
-                    if input_neuron.read().unwrap().is_output() {
+                    if input_neuron.read()?.is_output() {
                         continue;
                     }
 
-                    if let Some(props) = output_neuron.write().unwrap().props_mut() {
+                    if let Some(props) = output_neuron.write()?.props_mut() {
                         let input = PolyInputTopology::new(
                             Arc::downgrade(input_neuron),
                             Bias::rand(rng),
@@ -491,10 +696,10 @@ impl PolyNetworkTopology {
                 }
                 RemoveNeuron => {
                     // remove a random neuron, if it has any.
-                    self.remove_random_neuron(rng);
+                    self.remove_random_neuron(rng)?;
                 }
                 MutateWeight => {
-                    let mut neuron = self.random_neuron(rng).write().unwrap();
+                    let mut neuron = self.random_neuron(rng).write()?;
                     let Some(random_input) = neuron
                         .props_mut()
                         .and_then(|props| props.get_random_input_mut(rng))
@@ -505,7 +710,7 @@ impl PolyNetworkTopology {
                     random_input.adjust_weight(rng.random_range(-1.0..=1.0));
                 }
                 MutateExponent => {
-                    let mut neuron = self.random_neuron(rng).write().unwrap();
+                    let mut neuron = self.random_neuron(rng).write()?;
                     let Some(random_input) = neuron
                         .props_mut()
                         .and_then(|props| props.get_random_input_mut(rng))
@@ -523,6 +728,7 @@ impl PolyNetworkTopology {
                 }
             }
         }
+        Ok(())
     }
 
     /// Remove cycles from the network to ensure it remains feedforward.
@@ -532,7 +738,7 @@ impl PolyNetworkTopology {
     /// can be evaluated in a single forward pass without infinite loops.
     ///
     /// Cycles are removed by disconnecting neurons from their cyclic inputs.
-    fn remove_cycles(&mut self) {
+    fn remove_cycles(&mut self) -> Result<()> {
         let mut stack = HashSet::new();
         let mut visited = HashSet::new();
 
@@ -546,7 +752,7 @@ impl PolyNetworkTopology {
             node: &PolyNeuronTopology,
             stack: &mut HashSet<Uuid>,
             visited: &mut HashSet<Uuid>,
-        ) -> Vec<RemoveFrom> {
+        ) -> Result<Vec<RemoveFrom>> {
             let node_id = node.id();
             visited.insert(node_id);
 
@@ -560,10 +766,10 @@ impl PolyNetworkTopology {
                         let Some(input_neuron) = input.neuron() else {
                             continue;
                         };
-                        let input_neuron_id = input_neuron.read().unwrap().id();
+                        let input_neuron_id = input_neuron.read()?.id();
 
                         if !visited.contains(&input_neuron_id) {
-                            let child_result = dfs(&input_neuron.read().unwrap(), stack, visited);
+                            let child_result = dfs(&*input_neuron.read()?, stack, visited)?;
                             if !child_result.is_empty() {
                                 total_remove.extend(child_result);
                             }
@@ -580,9 +786,9 @@ impl PolyNetworkTopology {
                     }
 
                     stack.remove(&node_id);
-                    total_remove
+                    Ok(total_remove)
                 }
-                None => vec![],
+                None => Ok(vec![]),
             }
         }
         let mut _num_removed = 0;
@@ -590,13 +796,13 @@ impl PolyNetworkTopology {
             let mut remove_queue = Vec::new();
 
             for neuron in self.neurons.iter() {
-                let id = neuron.read().unwrap().id();
+                let id = neuron.read()?.id();
 
                 if visited.contains(&id) {
                     continue;
                 }
 
-                let to_remove = dfs(&neuron.read().unwrap(), &mut stack, &mut visited);
+                let to_remove = dfs(&*neuron.read()?, &mut stack, &mut visited)?;
 
                 if !to_remove.is_empty() {
                     remove_queue = to_remove;
@@ -607,12 +813,11 @@ impl PolyNetworkTopology {
                 break;
             }
             for removal in remove_queue {
-                let neuron_to_trim = self
-                    .neurons
-                    .iter_mut()
-                    .find(|neuron| neuron.read().unwrap().id() == removal.remove_from)
-                    .unwrap();
-                let mut neuron = neuron_to_trim.write().unwrap();
+                // every removal names a neuron dfs just visited
+                let Some(neuron_to_trim) = self.try_find_by_id(removal.remove_from)? else {
+                    continue;
+                };
+                let mut neuron = neuron_to_trim.write()?;
                 let Some(props) = neuron.props_mut() else {
                     panic!("tried to remove inputs from an input node!");
                 };
@@ -624,11 +829,17 @@ impl PolyNetworkTopology {
         //info!("Num removed: {}", num_removed);
         /*
         neuron.write().unwrap().trim_inputs(to_remove);*/
+        Ok(())
     }
 
     //#[instrument(name = "my_span")]
+    /// Convert this topology into a [`SimplePolyNetwork`].
+    ///
+    /// # Panics
+    /// Panics if a neuron lock is poisoned. Use [`SimplePolyNetwork::from_topology`] to
+    /// handle that case.
     pub fn to_simple_network(&self) -> SimplePolyNetwork {
-        SimplePolyNetwork::from_topology(self)
+        SimplePolyNetwork::from_topology(self).expect("failed to build simple network")
     }
 }
 
@@ -637,6 +848,14 @@ pub struct TopologyInfo {
     pub num_inputs: usize,
     pub num_hidden: usize,
     pub num_outputs: usize,
+    /// Connections whose source neuron is still part of the network.
+    pub num_connections: usize,
+}
+
+impl TopologyInfo {
+    pub fn num_neurons(&self) -> usize {
+        self.num_inputs + self.num_hidden + self.num_outputs
+    }
 }
 
 #[test]
@@ -671,4 +890,43 @@ fn make_simple_network() {
 
     assert_eq!(topology.neurons().len(), 4);
     assert_eq!(*topology.mutation_chances(), MutationChances::none());
+    let info = topology.info();
+    assert_eq!((info.num_neurons(), info.num_connections), (4, 5));
+}
+
+#[test]
+fn poisoned_genome_is_an_error() {
+    let input = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&input, 1., 1)],
+    ));
+
+    let poisoned = Arc::clone(&output);
+    let _ = std::thread::spawn(move || {
+        let _lock = poisoned.write().unwrap();
+        panic!("poison the output neuron");
+    })
+    .join();
+
+    let topology =
+        PolyNetworkTopology::from_raw_parts(vec![input, output], MutationChances::none());
+
+    assert!(matches!(
+        topology.try_replicate(&mut rand::rng()),
+        Err(PolyNeatError::PoisonedLock)
+    ));
+    assert!(matches!(
+        SimplePolyNetwork::from_topology(&topology),
+        Err(PolyNeatError::PoisonedLock)
+    ));
+    assert_eq!(
+        topology.try_info().unwrap_err(),
+        PolyNeatError::PoisonedLock
+    );
+    assert_eq!(
+        topology.try_depths().unwrap_err(),
+        PolyNeatError::PoisonedLock
+    );
+    assert!(topology.try_topological_order().is_err());
 }
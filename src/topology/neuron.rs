@@ -2,7 +2,7 @@ use std::sync::{Arc, RwLock};
 
 use uuid::Uuid;
 
-use crate::prelude::*;
+use crate::{error::Result, prelude::*};
 
 /// This defines a node's topology. What does this mean?
 ///
@@ -85,10 +85,22 @@ impl PolyNeuronTopology {
         self.neuron_type() == NeuronType::input()
     }
 
-    pub fn to_neuron(&self, neurons: &mut Vec<Arc<RwLock<SimpleNeuron>>>) {
+    /// Convert this neuron, and every neuron it depends on, into [`SimpleNeuron`]s.
+    ///
+    /// Neurons already present in `neurons` are reused.
+    ///
+    /// # Returns
+    /// The converted neuron, which is also part of `neurons`.
+    ///
+    /// # Errors
+    /// Fails if the lock of a converted neuron is poisoned.
+    pub fn to_neuron(
+        &self,
+        neurons: &mut Vec<Arc<RwLock<SimpleNeuron>>>,
+    ) -> Result<Arc<RwLock<SimpleNeuron>>> {
         for neuron in neurons.iter() {
-            if neuron.read().unwrap().id() == self.id() {
-                return;
+            if neuron.read()?.id() == self.id() {
+                return Ok(Arc::clone(neuron));
             }
         }
 
@@ -98,16 +110,10 @@ impl PolyNeuronTopology {
 
                 for topology_input in topology_props.inputs() {
                     if let Some(topology_input_neuron) = topology_input.neuron() {
-                        topology_input_neuron.read().unwrap().to_neuron(neurons);
-                        let neuron_in_array = neurons
-                            .iter()
-                            .find(|n| {
-                                n.read().unwrap().id() == topology_input_neuron.read().unwrap().id()
-                            })
-                            .unwrap();
+                        let neuron_in_array = topology_input_neuron.read()?.to_neuron(neurons)?;
 
                         new_neuron_inputs.push(NeuronInput::new(
-                            Arc::clone(neuron_in_array),
+                            neuron_in_array,
                             topology_input.weight(),
                             topology_input.exponent(),
                         ));
@@ -124,5 +130,6 @@ impl PolyNeuronTopology {
 
         let neuron = Arc::new(RwLock::new(SimpleNeuron::new(self.id, new_neuron_props)));
         neurons.push(Arc::clone(&neuron));
+        Ok(neuron)
     }
 }
@@ -0,0 +1,283 @@
+//! Integration tests for saved genomes and the command line interface.
+//!
+//! These tests verify that:
+//! - A genome saved as JSON loads back into a network with the same ids and outputs
+//! - `evolve` runs an experiment file and saves the fittest genome and its hall of fame
+//! - `eval`, `inspect` and `crosscheck` work on a saved genome
+//! - `eval` refuses samples with the wrong number of inputs instead of padding them
+//! - `evolve --checkpoint --resume` picks a finished or interrupted run back up
+//! - `evolve --stats` records one row per generation
+//! - `evolve --pareto-front` saves a front that trades fitness for simplicity
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use polynomial_neat::{evolution::Checkpoint, prelude::*};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// Helper function to create a deterministic RNG
+fn test_rng() -> StdRng {
+    StdRng::seed_from_u64(4401)
+}
+
+/// A fresh scratch directory for one test.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("polynomial_neat_cli_{}_{name}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn cli(args: &[&str], dir: &Path) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_polynomial-neat"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{args:?} failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn saved_genomes_round_trip() {
+    let mut rng = test_rng();
+    let dir = scratch_dir("round_trip");
+
+    let mut topology = PolyNetworkTopology::new(3, 2, MutationChances::new(80), &mut rng);
+    for _ in 0..20 {
+        topology = topology.replicate(&mut rng);
+    }
+    let path = dir.join("genome.json");
+    topology.save_json(&path).unwrap();
+    let loaded = PolyNetworkTopology::load_json(&path).unwrap();
+
+    assert_eq!(loaded.neuron_ids(), topology.neuron_ids());
+    assert_eq!(loaded.mutation_chances(), topology.mutation_chances());
+    assert_eq!(loaded.to_genome().unwrap(), topology.to_genome().unwrap());
+
+    let inputs = [0.5, -1.5, 2.];
+    let expected: Vec<f32> = topology.to_simple_network().predict(&inputs).collect();
+    let actual: Vec<f32> = loaded.to_simple_network().predict(&inputs).collect();
+    assert_eq!(format!("{actual:?}"), format!("{expected:?}"));
+}
+
+#[test]
+fn evolve_then_examine() {
+    let dir = scratch_dir("evolve");
+    fs::write(dir.join("samples.csv"), "x0,x1,y\n0,1,1\n1,2,3\n2,2,4\n").unwrap();
+    fs::write(
+        dir.join("experiment.toml"),
+        r#"
+            seed = 3
+
+            [task]
+            kind = "dataset"
+            path = "samples.csv"
+            inputs = 2
+
+            [mutation.bounds]
+            max_exponent = 3
+
+            [population]
+            size = 12
+
+            [speciation]
+            enabled = true
+
+            [termination]
+            max_generations = 4
+        "#,
+    )
+    .unwrap();
+
+    let evolved = stdout(&cli(
+        &[
+            "evolve",
+            "experiment.toml",
+            "--output",
+            "best.json",
+            "--quiet",
+            "--hall-of-fame",
+            "hall",
+        ],
+        &dir,
+    ));
+    assert!(
+        evolved.starts_with("generation 3: best fitness"),
+        "{evolved}"
+    );
+    let best = PolyNetworkTopology::load_json(dir.join("best.json")).unwrap();
+    assert_eq!(best.info().num_inputs, 2);
+    let champion = PolyNetworkTopology::load_json(dir.join("hall").join("1.json")).unwrap();
+    assert_eq!(champion.neuron_ids(), best.neuron_ids());
+
+    let network = CompiledNetwork::from_topology(&best).unwrap();
+    let single = stdout(&cli(&["eval", "best.json", "--inputs", "1,-2"], &dir));
+    let expected: Vec<String> = network
+        .predict(&[1., -2.])
+        .iter()
+        .map(f32::to_string)
+        .collect();
+    assert_eq!(single.trim(), expected.join(","));
+
+    let batch = stdout(&cli(&["eval", "best.json", "--csv", "samples.csv"], &dir));
+    assert_eq!(batch.lines().count(), 3);
+
+    let stats = stdout(&cli(&["inspect", "best.json"], &dir));
+    assert!(stats.contains("inputs:      2"), "{stats}");
+    let dot = stdout(&cli(&["inspect", "best.json", "--dot"], &dir));
+    assert!(dot.starts_with("digraph"));
+    let formula = stdout(&cli(&["inspect", "best.json", "--formula"], &dir));
+    assert_eq!(formula.lines().count(), best.info().num_outputs);
+
+    let report = stdout(&cli(
+        &["crosscheck", "best.json", "--inputs", "0.5,1.5"],
+        &dir,
+    ));
+    assert!(report.contains("all evaluators agree"), "{report}");
+}
+
+#[test]
+fn evolve_resumes_from_a_checkpoint() {
+    let dir = scratch_dir("resume");
+    fs::write(
+        dir.join("experiment.toml"),
+        "seed = 5\n[task]\nkind = \"xor\"\n[population]\nsize = 10\n[termination]\nmax_generations = 5\n",
+    )
+    .unwrap();
+    let evolve = [
+        "evolve",
+        "experiment.toml",
+        "--checkpoint",
+        "run.ckpt",
+        "--checkpoint-every",
+        "2",
+        "--stats",
+        "stats.csv",
+    ];
+
+    let first = stdout(&cli(&evolve, &dir));
+    assert_eq!(
+        first
+            .lines()
+            .filter(|line| line.starts_with("generation"))
+            .count(),
+        6
+    );
+    let checkpoint = Checkpoint::load(dir.join("run.ckpt")).unwrap();
+    assert_eq!(checkpoint.generation, 4);
+    let stats = fs::read_to_string(dir.join("stats.csv")).unwrap();
+    assert_eq!(stats.lines().count(), 6);
+    assert!(stats.lines().nth(5).unwrap().starts_with("4,"), "{stats}");
+
+    // the checkpoint of a finished run resumes straight into its last generation
+    let resumed = stdout(&cli(
+        &[&evolve[..], &["--resume", "--quiet"]].concat(),
+        &dir,
+    ));
+    let lines: Vec<&str> = resumed.lines().collect();
+    assert_eq!(lines[0], "resuming run.ckpt at generation 4");
+    assert_eq!(lines[1], first.lines().nth(4).unwrap());
+    // nothing was scored again, so nothing was appended
+    assert_eq!(fs::read_to_string(dir.join("stats.csv")).unwrap(), stats);
+}
+
+#[test]
+fn evolve_saves_the_pareto_front() {
+    let dir = scratch_dir("pareto");
+    fs::write(
+        dir.join("experiment.toml"),
+        "seed = 9\n[task]\nkind = \"xor\"\n[population]\nsize = 16\n[pareto]\nenabled = true\nobjectives = [\"fitness\", \"connections\"]\n[termination]\nmax_generations = 4\n",
+    )
+    .unwrap();
+
+    let evolved = stdout(&cli(
+        &[
+            "evolve",
+            "experiment.toml",
+            "--quiet",
+            "--pareto-front",
+            "front",
+        ],
+        &dir,
+    ));
+    assert!(evolved.contains("genomes of the Pareto front"), "{evolved}");
+
+    let csv = fs::read_to_string(dir.join("front").join("front.csv")).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("file,fitness,connections"));
+    let points: Vec<(f32, f32)> = lines
+        .map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            let genome = PolyNetworkTopology::load_json(dir.join("front").join(fields[0])).unwrap();
+            let connections: f32 = fields[2].parse().unwrap();
+            assert_eq!(genome.info().num_connections as f32, connections);
+            (fields[1].parse().unwrap(), connections)
+        })
+        .collect();
+
+    // fittest first, and no step down in fitness buys a more complex network
+    assert!(!points.is_empty());
+    for pair in points.windows(2) {
+        assert!(pair[0].0 >= pair[1].0, "{csv}");
+        assert!(pair[0].1 >= pair[1].1, "{csv}");
+    }
+}
+
+#[test]
+fn errors_name_the_file() {
+    let dir = scratch_dir("errors");
+    let output = Command::new(env!("CARGO_BIN_EXE_polynomial-neat"))
+        .args(["inspect", "missing.json"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: missing.json: "));
+}
+
+#[test]
+fn eval_refuses_wrongly_sized_samples() {
+    let mut rng = test_rng();
+    let dir = scratch_dir("eval_length");
+    let topology =
+        PolyNetworkTopology::new_thoroughly_connected(2, 1, MutationChances::none(), &mut rng);
+    topology.save_json(dir.join("genome.json")).unwrap();
+    // the columns are consistent, there just aren't enough of them
+    fs::write(dir.join("samples.csv"), "x0\n1\n3\n").unwrap();
+
+    let eval = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_polynomial-neat"))
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+
+    let short = eval(&["eval", "genome.json", "--inputs", "1"]);
+    assert!(!short.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&short.stderr).trim(),
+        "error: expected 2 inputs, got 1"
+    );
+
+    let csv = eval(&["eval", "genome.json", "--csv", "samples.csv"]);
+    assert!(!csv.status.success());
+    assert!(
+        String::from_utf8_lossy(&csv.stderr).contains("expected 2 inputs, got 1"),
+        "{}",
+        String::from_utf8_lossy(&csv.stderr)
+    );
+}
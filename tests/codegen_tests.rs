@@ -0,0 +1,220 @@
+//! Tests for Rust source generation.
+//!
+//! These tests compile the generated source with `rustc` and verify that:
+//! - Unrolled and expanded code produce the same outputs as `SimplePolyNetwork`
+//! - The generated code builds without any dependency on this crate, and without `std`
+//! - Negative exponents are written out as reciprocals
+//!
+//! Every source file and binary is removed from the temp directory afterwards.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::Command,
+    sync::{Arc, RwLock},
+};
+
+use polynomial_neat::codegen::{CodegenStyle, RustCodegen};
+use polynomial_neat::prelude::*;
+use polynomial_neat::topology::mutation::MutationChances;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use uuid::Uuid;
+
+/// Helper function to create a deterministic RNG
+fn test_rng() -> StdRng {
+    StdRng::seed_from_u64(2718)
+}
+
+fn arc<T>(value: T) -> Arc<RwLock<T>> {
+    Arc::new(RwLock::new(value))
+}
+
+const INPUTS: [[f32; 2]; 3] = [[0.5, 1.5], [2.0, -1.0], [-0.25, 3.0]];
+
+/// A scratch directory that is removed when dropped, even if the test fails.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!(
+            "polynomial_neat_codegen_{}_{name}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn rustc() -> Command {
+    Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+}
+
+/// Compiles `source` on its own as a `no_std` library.
+fn assert_builds_without_std(source: &str, name: &str) {
+    let dir = ScratchDir::new(&format!("{name}_no_std"));
+    let src_path = dir.0.join(format!("{name}.rs"));
+    fs::write(&src_path, format!("#![no_std]\n{source}")).unwrap();
+
+    let status = rustc()
+        .arg("--edition=2021")
+        .arg("--crate-type=rlib")
+        .arg("--out-dir")
+        .arg(&dir.0)
+        .arg(&src_path)
+        .status()
+        .expect("failed to run rustc");
+    assert!(
+        status.success(),
+        "generated source failed to compile without std:\n{source}"
+    );
+}
+
+/// Compiles `source` together with a `main` that prints the outputs for `INPUTS`
+/// and returns them.
+fn run_generated(source: &str, name: &str) -> Vec<Vec<f32>> {
+    let dir = ScratchDir::new(name);
+    let dir = &dir.0;
+
+    let main = format!(
+        "{source}\nfn main() {{\n    for inputs in {INPUTS:?} {{\n        let outputs = network(&inputs);\n        println!(\"{{:?}}\", outputs);\n    }}\n}}\n"
+    );
+    let src_path = dir.join(format!("{name}.rs"));
+    let bin_path = dir.join(name);
+    fs::write(&src_path, main).unwrap();
+
+    let status = rustc()
+        .arg("--edition=2021")
+        .arg("-o")
+        .arg(&bin_path)
+        .arg(&src_path)
+        .status()
+        .expect("failed to run rustc");
+    assert!(
+        status.success(),
+        "generated source failed to compile:\n{source}"
+    );
+
+    let output = Command::new(&bin_path).output().unwrap();
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            line.trim_matches(|c| c == '[' || c == ']')
+                .split(", ")
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<f32>().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+fn assert_close(expected: &[f32], actual: &[f32]) {
+    assert_eq!(expected.len(), actual.len());
+    for (e, a) in expected.iter().zip(actual) {
+        if e.is_nan() {
+            assert!(a.is_nan(), "expected NaN, got {a}");
+        } else if e.is_infinite() {
+            assert_eq!(e, a);
+        } else {
+            let tolerance = 1e-3 * e.abs().max(1.);
+            assert!((e - a).abs() <= tolerance, "expected {e}, got {a}");
+        }
+    }
+}
+
+#[test]
+fn test_generated_code_matches_simple_network() {
+    let mut rng = test_rng();
+    let mutations = MutationChances::new_from_raw(80, 60.0, 40.0, 5.0, 30.0, 10.0);
+
+    let mut topology = PolyNetworkTopology::new(2, 2, mutations, &mut rng);
+    for _ in 0..20 {
+        topology = topology.replicate(&mut rng);
+    }
+
+    assert!(topology.info().num_hidden > 0);
+
+    let network = topology.to_simple_network();
+    let expected: Vec<Vec<f32>> = INPUTS
+        .iter()
+        .map(|inputs| network.predict(inputs).collect())
+        .collect();
+
+    for (style, name) in [
+        (CodegenStyle::Unrolled, "unrolled"),
+        (CodegenStyle::Expanded, "expanded"),
+    ] {
+        let source = RustCodegen::new("network")
+            .with_style(style)
+            .generate(&topology)
+            .unwrap();
+        assert_builds_without_std(&source, name);
+        let actual = run_generated(&source, name);
+
+        assert_eq!(actual.len(), expected.len());
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert_close(expected, actual);
+        }
+    }
+}
+
+#[test]
+fn test_negative_exponents_match_simple_network() {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    // 2x
+    let double = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&x, 2., 1)],
+    ));
+    // 1 / (2x) + 3y^-2
+    let first = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&double, 1., -1),
+            PolyInputTopology::downgrade(&y, 3., -2),
+        ],
+    ));
+    // 0.5(2x)^3 - y
+    let second = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&double, 0.5, 3),
+            PolyInputTopology::downgrade(&y, -1., 1),
+        ],
+    ));
+    let topology = PolyNetworkTopology::from_raw_parts(
+        vec![x, y, double, first, second],
+        MutationChances::none(),
+    );
+
+    let network = topology.to_simple_network();
+    let expected: Vec<Vec<f32>> = INPUTS
+        .iter()
+        .map(|inputs| network.predict(inputs).collect())
+        .collect();
+
+    for (style, name) in [
+        (CodegenStyle::Unrolled, "reciprocal_unrolled"),
+        (CodegenStyle::Expanded, "reciprocal_expanded"),
+    ] {
+        let source = RustCodegen::new("network")
+            .with_style(style)
+            .generate(&topology)
+            .unwrap();
+        assert!(!source.contains("powi"));
+        assert_builds_without_std(&source, name);
+        let actual = run_generated(&source, name);
+
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert_close(expected, actual);
+        }
+    }
+}
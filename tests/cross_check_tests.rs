@@ -0,0 +1,210 @@
+//! Integration tests for differential testing between evaluators.
+//!
+//! These tests verify that:
+//! - Every evaluator agrees on evolved genomes
+//! - A disagreement is reported against the reference and minimised into a reproducer
+//! - Evaluators that can't be built are skipped rather than reported as mismatches
+
+use std::sync::{Arc, RwLock};
+
+use burn::backend::NdArray;
+use polynomial_neat::cross_check::{Evaluator, cross_check, cross_check_including};
+use polynomial_neat::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use uuid::Uuid;
+
+/// Helper function to create a deterministic RNG
+fn test_rng() -> StdRng {
+    StdRng::seed_from_u64(4301)
+}
+
+fn arc<T>(value: T) -> Arc<RwLock<T>> {
+    Arc::new(RwLock::new(value))
+}
+
+#[test]
+fn evolved_genomes_agree() {
+    let mut rng = test_rng();
+
+    for _ in 0..10 {
+        let mut topology = PolyNetworkTopology::new(2, 2, MutationChances::new(50), &mut rng);
+        for _ in 0..8 {
+            topology = topology.replicate(&mut rng);
+            let report = cross_check(&topology, &[0.5, 1.5], 1e-3).unwrap();
+            report.assert_consistent();
+            assert_eq!(report.outputs().len() + report.skipped().len(), 6);
+        }
+    }
+}
+
+/// A deliberately broken evaluator that ignores every connection with a negative
+/// exponent.
+fn without_reciprocals(
+    topology: &PolyNetworkTopology,
+    inputs: &[f32],
+) -> Result<Vec<f32>, PolyNeatError> {
+    let clone = topology.try_deep_clone()?;
+    for neuron in clone.neurons() {
+        if let Some(props) = neuron.write()?.props_mut() {
+            let negative: Vec<usize> = props
+                .inputs()
+                .iter()
+                .enumerate()
+                .filter(|(_, input)| input.exponent() < 0)
+                .map(|(index, _)| index)
+                .collect();
+            props.trim_inputs(&negative);
+        }
+    }
+    Ok(SimplePolyNetwork::from_topology(&clone)?
+        .predict(inputs)
+        .collect())
+}
+
+#[test]
+fn reproducer_keeps_only_the_disagreement() {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    // 2y
+    let hidden = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&y, 2., 1)],
+    ));
+    // 3x + 1 / (2y)
+    let first = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&x, 3., 1),
+            PolyInputTopology::downgrade(&hidden, 1., -1),
+        ],
+    ));
+    // x^2 + 2y
+    let second = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&x, 1., 2),
+            PolyInputTopology::downgrade(&hidden, 1., 1),
+        ],
+    ));
+    let topology = PolyNetworkTopology::from_raw_parts(
+        vec![x, y, hidden, first, second],
+        MutationChances::none(),
+    );
+
+    let report = cross_check_including::<NdArray>(
+        &topology,
+        &[1., 1.],
+        1e-3,
+        Default::default(),
+        &[("without reciprocals", &without_reciprocals)],
+    )
+    .unwrap();
+    assert!(!report.is_consistent());
+
+    // only the injected evaluator disagrees, and only on the first output
+    let mismatches = report.mismatches();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(
+        mismatches[0].evaluator,
+        Evaluator::Custom("without reciprocals")
+    );
+    assert_eq!(mismatches[0].output, 0);
+    assert_eq!(mismatches[0].expected, 3.5);
+    assert_eq!(mismatches[0].actual, Some(3.));
+    assert_eq!(report.skipped().len(), 0);
+
+    // the second output and the 3x connection don't take part in the disagreement
+    let reproducer = report.reproducer().unwrap();
+    assert_eq!(reproducer.info().num_outputs, 1);
+    let exponents: Vec<i32> = reproducer
+        .neurons()
+        .iter()
+        .filter(|neuron| neuron.read().unwrap().is_output())
+        .flat_map(|neuron| {
+            let neuron = neuron.read().unwrap();
+            let props = neuron.props().unwrap();
+            props
+                .inputs()
+                .iter()
+                .map(|input| input.exponent())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(exponents, vec![-1]);
+    assert!(report.to_string().contains("without reciprocals output 0"));
+}
+
+#[test]
+fn non_polynomial_outputs_are_skipped() {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let sum = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&x, 1., 1),
+            PolyInputTopology::downgrade(&y, 1., 1),
+        ],
+    ));
+    let square = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&x, 2., 2)],
+    ));
+    // 1 / (x + y) + 2x^2 + 3y
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&sum, 1., -1),
+            PolyInputTopology::downgrade(&square, 1., 1),
+            PolyInputTopology::downgrade(&y, 3., 1),
+        ],
+    ));
+    let topology = PolyNetworkTopology::from_raw_parts(
+        vec![x, y, sum, square, output],
+        MutationChances::none(),
+    );
+
+    let report = cross_check(&topology, &[1., 1.], 1e-3).unwrap();
+    report.assert_consistent();
+    assert_eq!(report.outputs_of(Evaluator::Simple), Some(&[5.5][..]));
+    assert_eq!(report.outputs_of(Evaluator::Layered), Some(&[5.5][..]));
+
+    // 1 / (x + y) has no closed form to expand into
+    for (evaluator, error) in report.skipped() {
+        assert_eq!(
+            error,
+            &PolyNeatError::NotAPolynomial { terms: 2 },
+            "{evaluator}"
+        );
+    }
+    assert_eq!(report.skipped().len(), 3);
+}
+
+#[test]
+fn unbuildable_evaluators_are_skipped() {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&x, 2., 1),
+            PolyInputTopology::downgrade(&y, 1., 1),
+        ],
+    ));
+    // `y` is no longer listed, so only the graph evaluators can run this
+    let topology = PolyNetworkTopology::from_raw_parts(vec![x, output], MutationChances::none());
+
+    let report = cross_check(&topology, &[3.], 1e-3).unwrap();
+    report.assert_consistent();
+
+    let skipped: Vec<Evaluator> = report.skipped().iter().map(|(e, _)| *e).collect();
+    assert_eq!(
+        skipped,
+        vec![
+            Evaluator::Polynomial,
+            Evaluator::Burn,
+            Evaluator::Population
+        ]
+    );
+    assert!(report.reproducer().is_none());
+}
@@ -292,6 +292,7 @@ fn test_precision_loss_accumulation() {
 }
 
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn test_deterministic_output() {
     // Test that the same input always produces the same output
     let mut rng = test_rng();
@@ -430,3 +431,137 @@ fn test_node_count_validation() {
         );
     }
 }
+
+#[test]
+fn test_try_predict_is_consistent_across_evaluators() {
+    use polynomial_neat::burn_net::network::BurnNetwork;
+    use polynomial_neat::core::validation::{InputValidation, PredictError};
+
+    let mut rng = test_rng();
+    let topology =
+        PolyNetworkTopology::new_thoroughly_connected(3, 2, MutationChances::new(0), &mut rng);
+    let simple = topology.to_simple_network();
+    let compiled = topology.to_compiled_network().unwrap();
+    let device = burn::backend::ndarray::NdArrayDevice::default();
+    let burn = BurnNetwork::<burn::backend::NdArray>::from_topology(&topology, device).unwrap();
+
+    let strict = InputValidation::default().with_finite_inputs(true);
+    let lenient = InputValidation::default().with_exact_length(false);
+
+    for inputs in [vec![1.0, 2.0], vec![1.0, 2.0, 3.0, 4.0]] {
+        let expected = Err(PolyNeatError::Predict(PredictError::InputLength {
+            expected: 3,
+            actual: inputs.len(),
+        }));
+        assert_eq!(simple.try_predict(&inputs, &strict), expected);
+        assert_eq!(compiled.try_predict(&inputs, &strict), expected);
+        assert_eq!(burn.try_predict(&inputs, &strict), expected);
+
+        // without the length check, every evaluator pads or truncates the same way
+        let simple_outputs = simple.try_predict(&inputs, &lenient).unwrap();
+        let compiled_outputs = compiled.try_predict(&inputs, &lenient).unwrap();
+        let burn_outputs = burn.try_predict(&inputs, &lenient).unwrap();
+        for ((s, c), b) in simple_outputs
+            .iter()
+            .zip(compiled_outputs.iter())
+            .zip(burn_outputs.iter())
+        {
+            assert!((s - c).abs() < 1e-4);
+            assert!((s - b).abs() < 1e-3 * s.abs().max(1.0));
+        }
+    }
+
+    let non_finite = [1.0, f32::INFINITY, 3.0];
+    for result in [
+        simple.try_predict(&non_finite, &strict),
+        compiled.try_predict(&non_finite, &strict),
+        burn.try_predict(&non_finite, &strict),
+    ] {
+        assert!(matches!(
+            result,
+            Err(PolyNeatError::Predict(PredictError::NonFiniteInput {
+                index: 1,
+                ..
+            }))
+        ));
+    }
+    assert!(
+        simple
+            .try_predict(&non_finite, &InputValidation::default())
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_burn_predict_pads_short_input() {
+    use polynomial_neat::burn_net::network::BurnNetwork;
+
+    let mut rng = test_rng();
+    let topology =
+        PolyNetworkTopology::new_thoroughly_connected(3, 2, MutationChances::new(0), &mut rng);
+    let simple = topology.to_simple_network();
+    let device = burn::backend::ndarray::NdArrayDevice::default();
+    let burn = BurnNetwork::<burn::backend::NdArray>::from_topology(&topology, device).unwrap();
+
+    // missing inputs read as 0 and extra ones are ignored, instead of panicking
+    for inputs in [vec![], vec![1.0], vec![1.0, 2.0, 3.0, 4.0]] {
+        let expected: Vec<f32> = simple.predict(&inputs).collect();
+        let actual = burn.predict(&inputs);
+        assert_eq!(expected.len(), actual.len());
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert!((e - a).abs() < 1e-3 * e.abs().max(1.0), "{e} != {a}");
+        }
+    }
+}
+
+#[test]
+fn test_try_predict_rejects_disconnected_outputs() {
+    use polynomial_neat::burn_net::network::BurnNetwork;
+    use polynomial_neat::core::validation::{InputValidation, PredictError};
+    use std::sync::{Arc, RwLock};
+    use uuid::Uuid;
+
+    let input = Arc::new(RwLock::new(PolyNeuronTopology::input(Uuid::new_v4())));
+    let connected = Arc::new(RwLock::new(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&input, 1.0, 1)],
+    )));
+    let disconnected = Arc::new(RwLock::new(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![],
+    )));
+    let topology = PolyNetworkTopology::from_raw_parts(
+        vec![input, connected, disconnected],
+        MutationChances::none(),
+    );
+
+    assert_eq!(topology.disconnected_outputs(), Ok(vec![1]));
+
+    let expected = Err(PolyNeatError::Predict(PredictError::DisconnectedOutput {
+        output: 1,
+    }));
+    let validation = InputValidation::default();
+    let device = burn::backend::ndarray::NdArrayDevice::default();
+    let burn = BurnNetwork::<burn::backend::NdArray>::from_topology(&topology, device).unwrap();
+
+    assert_eq!(
+        topology
+            .to_simple_network()
+            .try_predict(&[2.0], &validation),
+        expected
+    );
+    assert_eq!(
+        topology
+            .to_compiled_network()
+            .unwrap()
+            .try_predict(&[2.0], &validation),
+        expected
+    );
+    assert_eq!(burn.try_predict(&[2.0], &validation), expected);
+
+    let allowed = validation.with_connected_outputs(false);
+    assert_eq!(
+        topology.to_simple_network().try_predict(&[2.0], &allowed),
+        Ok(vec![2.0, 0.0])
+    );
+}
@@ -0,0 +1,160 @@
+//! Integration tests for numeric policies.
+//!
+//! These tests verify that:
+//! - Every evaluator handles a division by zero the same way under each policy it supports
+//! - The expanded evaluators refuse the epsilon policy, which the others apply to hidden sums
+//! - Sentinel replacements flag the genome they belong to, and only that genome
+//! - Offspring don't inherit the flag of their parent
+
+use std::sync::{Arc, RwLock};
+
+use burn::backend::NdArray;
+use polynomial_neat::burn_net::{
+    layered::LayeredNetwork, network::BurnNetwork, population::PopulationNetwork,
+};
+use polynomial_neat::core::numeric::NumericPolicy;
+use polynomial_neat::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use uuid::Uuid;
+
+/// Helper function to create a deterministic RNG
+fn test_rng() -> StdRng {
+    StdRng::seed_from_u64(3701)
+}
+
+fn arc<T>(value: T) -> Arc<RwLock<T>> {
+    Arc::new(RwLock::new(value))
+}
+
+/// `1 / x + 2y`, routed through a hidden neuron.
+fn reciprocal_topology() -> PolyNetworkTopology {
+    let x = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let y = arc(PolyNeuronTopology::input(Uuid::new_v4()));
+    let hidden = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&x, 1., 1)],
+    ));
+    let output = arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![
+            PolyInputTopology::downgrade(&hidden, 1., -1),
+            PolyInputTopology::downgrade(&y, 2., 1),
+        ],
+    ));
+
+    PolyNetworkTopology::from_raw_parts(vec![x, y, hidden, output], MutationChances::none())
+}
+
+#[test]
+fn evaluators_agree_under_every_policy() {
+    let device = burn::backend::ndarray::NdArrayDevice::default();
+    let inputs = [0., 1.];
+
+    for (policy, expected) in [
+        (NumericPolicy::Propagate, f32::INFINITY),
+        (NumericPolicy::Clamp, f32::MAX),
+        (NumericPolicy::Sentinel(-1.), -1.),
+    ] {
+        let topology = reciprocal_topology();
+
+        let simple = topology.to_simple_network().with_numeric_policy(policy);
+        let compiled = topology
+            .to_compiled_network()
+            .unwrap()
+            .with_numeric_policy(policy);
+        let burn = BurnNetwork::<NdArray>::from_topology(&topology, device)
+            .unwrap()
+            .with_numeric_policy(policy)
+            .unwrap();
+        let layered = LayeredNetwork::<NdArray>::from_topology(&topology, device)
+            .unwrap()
+            .with_numeric_policy(policy);
+        let population =
+            PopulationNetwork::<NdArray>::from_topologies(std::slice::from_ref(&topology), device)
+                .unwrap()
+                .with_numeric_policy(policy)
+                .unwrap();
+
+        let batch = burn
+            .predict_batch_rows(&[inputs])
+            .to_data()
+            .to_vec::<f32>()
+            .unwrap();
+
+        assert_eq!(simple.predict(&inputs).collect::<Vec<_>>(), vec![expected]);
+        assert_eq!(compiled.predict(&inputs), vec![expected], "{policy:?}");
+        assert_eq!(burn.predict(&inputs), vec![expected], "{policy:?}");
+        assert_eq!(batch, vec![expected], "{policy:?}");
+        assert_eq!(layered.predict(&inputs), vec![expected], "{policy:?}");
+        assert_eq!(
+            population.predict_batch_rows(&[inputs]).to_vecs(),
+            vec![vec![vec![expected]]],
+            "{policy:?}"
+        );
+
+        let sentinel = matches!(policy, NumericPolicy::Sentinel(_));
+        assert_eq!(topology.numeric_flag().is_raised(), sentinel);
+        if sentinel {
+            // simple, compiled, burn, burn batch, layered and population
+            assert_eq!(topology.numeric_flag().count(), 6);
+        }
+    }
+}
+
+#[test]
+fn epsilon_is_refused_by_expanded_evaluators() {
+    let device = burn::backend::ndarray::NdArrayDevice::default();
+    let topology = reciprocal_topology();
+    let policy = NumericPolicy::Epsilon(0.5);
+    let inputs = [0., 1.];
+
+    // the hidden sum is 0, so 1 / 0.5 + 2
+    let simple = topology.to_simple_network().with_numeric_policy(policy);
+    let compiled = topology
+        .to_compiled_network()
+        .unwrap()
+        .with_numeric_policy(policy);
+    let layered = LayeredNetwork::<NdArray>::from_topology(&topology, device)
+        .unwrap()
+        .with_numeric_policy(policy);
+    assert_eq!(simple.predict(&inputs).collect::<Vec<_>>(), vec![4.]);
+    assert_eq!(compiled.predict(&inputs), vec![4.]);
+    assert_eq!(layered.predict(&inputs), vec![4.]);
+
+    let unsupported = Some(PolyNeatError::UnsupportedPolicy { policy });
+    let burn = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
+    assert_eq!(burn.with_numeric_policy(policy).err(), unsupported);
+    let population =
+        PopulationNetwork::<NdArray>::from_topologies(std::slice::from_ref(&topology), device)
+            .unwrap();
+    assert_eq!(population.with_numeric_policy(policy).err(), unsupported);
+}
+
+#[test]
+fn sentinel_flags_only_the_offending_genome() {
+    let device = burn::backend::ndarray::NdArrayDevice::default();
+    let mut rng = test_rng();
+
+    let healthy =
+        PolyNetworkTopology::new_thoroughly_connected(2, 1, MutationChances::none(), &mut rng);
+    let offending = reciprocal_topology();
+    let population = [healthy, offending];
+
+    let network = PopulationNetwork::<NdArray>::from_topologies(&population, device)
+        .unwrap()
+        .with_numeric_policy(NumericPolicy::Sentinel(0.))
+        .unwrap();
+
+    // only the offending genome divides by zero, and only on the first sample
+    let outputs = network.predict_batch_rows(&[[0., 1.], [2., 1.]]).to_vecs();
+    assert_eq!(outputs[1], vec![vec![0., 2.5]]);
+
+    assert!(!population[0].numeric_flag().is_raised());
+    assert_eq!(population[1].numeric_flag().count(), 1);
+
+    // the flag can be used to penalise fitness, and isn't passed on to offspring
+    assert_eq!(population[1].numeric_flag().penalize(1., 0.25), 0.75);
+    assert_eq!(population[0].numeric_flag().penalize(1., 0.25), 1.);
+    assert!(!population[1].replicate(&mut rng).numeric_flag().is_raised());
+}
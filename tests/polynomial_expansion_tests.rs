@@ -429,3 +429,89 @@ fn test_parallel_prediction() {
         );
     }
 }
+
+/// Three inputs summed, then squared through `depth` hidden layers.
+fn squaring_chain(depth: usize) -> PolyNetworkTopology {
+    use std::sync::{Arc, RwLock};
+    use uuid::Uuid;
+
+    let arc = |neuron| Arc::new(RwLock::new(neuron));
+    let inputs: Vec<_> = (0..3)
+        .map(|_| arc(PolyNeuronTopology::input(Uuid::new_v4())))
+        .collect();
+
+    let mut neurons = inputs.clone();
+    let mut previous = arc(PolyNeuronTopology::hidden(
+        Uuid::new_v4(),
+        inputs
+            .iter()
+            .map(|input| PolyInputTopology::downgrade(input, 1., 1))
+            .collect(),
+    ));
+    for _ in 0..depth {
+        neurons.push(Arc::clone(&previous));
+        previous = arc(PolyNeuronTopology::hidden(
+            Uuid::new_v4(),
+            vec![
+                PolyInputTopology::downgrade(&previous, 1., 2),
+                PolyInputTopology::downgrade(&inputs[0], 1., 1),
+            ],
+        ));
+    }
+    neurons.push(Arc::clone(&previous));
+    neurons.push(arc(PolyNeuronTopology::output(
+        Uuid::new_v4(),
+        vec![PolyInputTopology::downgrade(&previous, 1., 1)],
+    )));
+
+    PolyNetworkTopology::from_raw_parts(neurons, MutationChances::none())
+}
+
+#[test]
+fn test_expansion_limits() {
+    use polynomial_neat::burn_net::{
+        expander::{ExpansionLimit, ExpansionLimits},
+        network::BurnNetwork,
+        output_polynomials,
+    };
+    use std::time::Duration;
+
+    type Backend = burn::backend::NdArray;
+    let device = burn::backend::ndarray::NdArrayDevice::default();
+
+    // the estimate is an upper bound on the actual expansion
+    let small = squaring_chain(2);
+    let estimate = small.estimate_expansion().unwrap();
+    let actual = &output_polynomials(&small).unwrap()[0];
+    assert_eq!(estimate.degree, 4);
+    assert_eq!(u64::from(actual.degree()), estimate.degree);
+    assert!(actual.components().len() as u64 <= estimate.terms);
+
+    let generous = ExpansionLimits::default()
+        .with_max_terms(estimate.terms as usize)
+        .with_max_degree(4);
+    assert!(BurnNetwork::<Backend>::from_topology_with_limits(&small, device, &generous).is_ok());
+
+    // a deep chain is rejected by its estimate before any expansion happens
+    let deep = squaring_chain(12);
+    let estimate = deep.estimate_expansion().unwrap();
+    assert_eq!(estimate.degree, 4096);
+    let limits = ExpansionLimits::default().with_max_degree(64);
+    assert!(matches!(
+        BurnNetwork::<Backend>::from_topology_with_limits(&deep, device, &limits),
+        Err(PolyNeatError::ExpansionLimit(ExpansionLimit::Estimate(e))) if e == estimate
+    ));
+
+    // time is checked while expanding
+    let limits = ExpansionLimits::default().with_max_time(Duration::ZERO);
+    assert!(matches!(
+        BurnNetwork::<Backend>::from_topology_with_limits(&small, device, &limits),
+        Err(PolyNeatError::ExpansionLimit(ExpansionLimit::Time(_)))
+    ));
+
+    // the rejected genome can still be evaluated without expanding it
+    assert_eq!(
+        deep.to_compiled_network().unwrap().predict(&[0., 0., 0.]),
+        vec![0.]
+    );
+}
@@ -35,7 +35,7 @@ fn test_burn_network_creation() {
     let topology = create_test_topology(3, 2, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Test that network was created successfully
     let inputs = vec![1.0, 2.0, 3.0];
@@ -64,7 +64,7 @@ fn test_tensor_dimensions() {
 
     for (num_inputs, num_outputs) in test_cases {
         let topology = create_test_topology(num_inputs, num_outputs, &mut rng);
-        let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+        let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
         // Create input tensor
         let inputs = vec![0.5_f32; num_inputs];
@@ -88,7 +88,7 @@ fn test_tensor_initialization() {
     let topology = create_test_topology(2, 1, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Run multiple predictions to ensure consistent initialization
     let inputs = vec![1.0, 1.0];
@@ -126,7 +126,7 @@ fn test_burn_network_with_hidden_layers() {
     }
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Test with various inputs
     let test_inputs = vec![
@@ -156,7 +156,7 @@ fn test_tensor_operations_polynomial() {
     let topology = create_test_topology(1, 1, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Test with different input values to verify polynomial behavior
     let test_values = vec![0.0, 0.5, 1.0, 2.0, -1.0, -2.0];
@@ -179,7 +179,7 @@ fn test_batch_tensor_processing() {
     let topology = create_test_topology(4, 3, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Process multiple inputs
     let batch_size = 10;
@@ -210,7 +210,7 @@ fn test_tensor_memory_layout() {
     let topology = create_test_topology(5, 4, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Create inputs that test memory access patterns
     let inputs = vec![1.0, 2.0, 3.0, 4.0, 5.0];
@@ -237,7 +237,7 @@ fn test_fully_connected_tensor_setup() {
     let topology = PolyNetworkTopology::new_thoroughly_connected(6, 4, mutations, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // In a fully connected network, all inputs affect all outputs
     let inputs = vec![1.0, -1.0, 2.0, -2.0, 0.5, -0.5];
@@ -270,7 +270,7 @@ fn test_tensor_numerical_stability() {
     let topology = create_test_topology(3, 2, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Test with various extreme inputs
     let test_cases = vec![
@@ -306,7 +306,7 @@ fn test_zero_connections_handling() {
     let topology = PolyNetworkTopology::new(1, 1, mutations, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Even with minimal connections, should produce valid output
     let output = burn_network.predict(&[1.0]);
@@ -323,8 +323,8 @@ fn test_tensor_consistency_across_backends() {
     let device = NdArrayDevice::default();
 
     // Create multiple networks from same topology
-    let network1 = BurnNetwork::<NdArray>::from_topology(&topology, device);
-    let network2 = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let network1 = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
+    let network2 = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     let inputs = vec![1.0, 2.0, 3.0];
     let outputs1 = network1.predict(&inputs);
@@ -353,7 +353,7 @@ fn test_evolved_network_tensor_integrity() {
 
     // Test network at each evolution stage
     for generation in 0..10 {
-        let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+        let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
         let inputs = vec![1.0, 2.0, 3.0, 4.0];
         let outputs = burn_network.predict(&inputs);
@@ -382,7 +382,7 @@ fn test_tensor_gradient_flow() {
     let topology = create_test_topology(2, 1, &mut rng);
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Test with inputs that should produce different outputs
     let inputs1 = vec![1.0, 0.0];
@@ -420,7 +420,7 @@ fn test_large_network_tensor_setup() {
     }
 
     let device = NdArrayDevice::default();
-    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device);
+    let burn_network = BurnNetwork::<NdArray>::from_topology(&topology, device).unwrap();
 
     // Test with full input vector
     let inputs = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];